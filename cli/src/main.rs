@@ -0,0 +1,460 @@
+//! `osm`, a command-line client of the OpenStreetMap API built on `openstreetmap-api`
+
+mod output;
+
+use clap::{Args, Parser, Subcommand};
+use openstreetmap_api::config::Config;
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::types;
+use openstreetmap_api::xml;
+use openstreetmap_api::Openstreetmap;
+use output::{Format, Output};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "osm", version, about)]
+struct Cli {
+    /// URL of the API server, instead of the host of the profile, without its credentials
+    /// unless the profile is given
+    #[arg(long, global = true)]
+    host: Option<String>,
+
+    /// profile of the configuration file
+    #[arg(long, short, global = true)]
+    profile: Option<String>,
+
+    /// configuration file with the profiles, instead of the default one
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// format of the output, by default XML for elements, maps and changes and JSON for the
+    /// other results
+    #[arg(long, short, global = true, value_enum)]
+    format: Option<Format>,
+
+    /// writes the output to the file instead of the standard output
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// reads nodes
+    #[command(subcommand)]
+    Node(ElementCommand),
+    /// reads ways
+    #[command(subcommand)]
+    Way(ElementCommand),
+    /// reads relations
+    #[command(subcommand)]
+    Relation(ElementCommand),
+    /// downloads the elements inside a bounding box
+    Map {
+        /// min_lon,min_lat,max_lon,max_lat
+        #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+        bbox: types::BoundingBox,
+    },
+    /// reads and edits changesets
+    #[command(subcommand)]
+    Changeset(ChangesetCommand),
+    /// reads notes
+    #[command(subcommand)]
+    Notes(NotesCommand),
+    /// reads users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// stores the OAuth 2.0 access token of the profile
+    #[command(subcommand)]
+    Token(TokenCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum ElementCommand {
+    /// the current version of the element
+    Get { id: i64 },
+    /// every version of the element
+    History { id: i64 },
+    /// the way or relation with the elements it references
+    Full { id: i64 },
+}
+
+#[derive(Debug, Subcommand)]
+enum ChangesetCommand {
+    /// the latest changesets matching the filters
+    List(ChangesetFilters),
+    /// the changes of a changeset as osmChange
+    Download { id: u64 },
+    /// uploads an .osc or .osc.gz file, in a new changeset closed afterwards unless one is given
+    Upload {
+        file: PathBuf,
+        /// the open changeset to upload into
+        #[arg(long)]
+        changeset: Option<u64>,
+        /// the comment of the new changeset
+        #[arg(long, short = 'm')]
+        comment: Option<String>,
+    },
+    /// closes a changeset
+    Close { id: u64 },
+}
+
+#[derive(Debug, Args)]
+struct ChangesetFilters {
+    /// id of the user
+    #[arg(long)]
+    user: Option<u64>,
+    /// display name of the user
+    #[arg(long)]
+    display_name: Option<String>,
+    /// only the open changesets
+    #[arg(long)]
+    open: bool,
+    /// only the closed changesets
+    #[arg(long)]
+    closed: bool,
+    #[arg(long)]
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Subcommand)]
+enum NotesCommand {
+    /// the notes matching the filters
+    Search {
+        /// text of the comments
+        #[arg(long, short)]
+        query: Option<String>,
+        /// min_lon,min_lat,max_lon,max_lat
+        #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+        bbox: Option<types::BoundingBox>,
+        /// days a closed note is still returned, 0 for the open notes only, -1 for all
+        #[arg(long, allow_hyphen_values = true)]
+        closed: Option<i64>,
+        #[arg(long)]
+        limit: Option<u16>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum UserCommand {
+    /// the user of the credentials
+    Details,
+}
+
+#[derive(Debug, Subcommand)]
+enum TokenCommand {
+    /// saves the token, used instead of the user and password of the profile
+    Save { token: String },
+    /// removes the token
+    Remove,
+}
+
+fn parse_bbox(value: &str) -> Result<types::BoundingBox, String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| coordinate.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|error| error.to_string())?;
+
+    match coordinates.as_slice() {
+        [left, bottom, right, top] => Ok(types::BoundingBox {
+            left: *left,
+            bottom: *bottom,
+            right: *right,
+            top: *top,
+        }),
+        _ => Err("expected min_lon,min_lat,max_lon,max_lat".into()),
+    }
+}
+
+fn note_closed(days: i64) -> types::NoteClosedOption {
+    match days {
+        days if days < 0 => types::NoteClosedOption::All,
+        0 => types::NoteClosedOption::OpenOnly,
+        days => types::NoteClosedOption::ClosedWithin(days.try_into().unwrap_or(u32::MAX)),
+    }
+}
+
+/// moves every element of the changes into the changeset they are uploaded to
+fn assign_changeset(changes: &mut types::ChangesetChanges, changeset_id: u64) {
+    let blocks = changes
+        .creations
+        .iter_mut()
+        .map(|c| (&mut c.nodes, &mut c.ways, &mut c.relations))
+        .chain(
+            changes
+                .modifications
+                .iter_mut()
+                .map(|m| (&mut m.nodes, &mut m.ways, &mut m.relations)),
+        )
+        .chain(
+            changes
+                .deletions
+                .iter_mut()
+                .map(|d| (&mut d.nodes, &mut d.ways, &mut d.relations)),
+        );
+
+    for (nodes, ways, relations) in blocks {
+        nodes
+            .iter_mut()
+            .for_each(|node| node.changeset = changeset_id);
+        ways.iter_mut().for_each(|way| way.changeset = changeset_id);
+        relations
+            .iter_mut()
+            .for_each(|relation| relation.changeset = changeset_id);
+    }
+}
+
+async fn element(
+    client: &Openstreetmap,
+    element_type: types::ElementType,
+    command: ElementCommand,
+    format: Option<Format>,
+) -> Result<Output, Box<dyn Error>> {
+    // the GeoJSON geometry of a way or a relation is built out of the elements it references
+    let command = match (format, command) {
+        (Some(Format::Geojson), ElementCommand::Get { id })
+            if element_type != types::ElementType::Node =>
+        {
+            ElementCommand::Full { id }
+        }
+        (_, command) => command,
+    };
+
+    let output = match (element_type, command) {
+        (types::ElementType::Node, ElementCommand::Get { id }) => {
+            Output::Elements(vec![types::Element::Node(client.nodes().get(id).await?)])
+        }
+        (types::ElementType::Way, ElementCommand::Get { id }) => {
+            Output::Elements(vec![types::Element::Way(client.ways().get(id).await?)])
+        }
+        (types::ElementType::Relation, ElementCommand::Get { id }) => {
+            Output::Elements(vec![types::Element::Relation(
+                client.relations().get(id).await?,
+            )])
+        }
+        (types::ElementType::Node, ElementCommand::History { id }) => Output::Elements(
+            client
+                .nodes()
+                .history(id)
+                .await?
+                .into_iter()
+                .map(types::Element::Node)
+                .collect(),
+        ),
+        (types::ElementType::Way, ElementCommand::History { id }) => Output::Elements(
+            client
+                .ways()
+                .history(id)
+                .await?
+                .into_iter()
+                .map(types::Element::Way)
+                .collect(),
+        ),
+        (types::ElementType::Relation, ElementCommand::History { id }) => Output::Elements(
+            client
+                .relations()
+                .history(id)
+                .await?
+                .into_iter()
+                .map(types::Element::Relation)
+                .collect(),
+        ),
+        (types::ElementType::Way, ElementCommand::Full { id }) => {
+            Output::WayFull(client.ways().full(id).await?)
+        }
+        (types::ElementType::Relation, ElementCommand::Full { id }) => {
+            Output::RelationFull(client.relations().full(id).await?)
+        }
+        (types::ElementType::Node, ElementCommand::Full { .. }) => {
+            return Err("a node has no full version, use `osm node get`".into())
+        }
+    };
+
+    Ok(output)
+}
+
+async fn changeset(
+    client: &Openstreetmap,
+    command: ChangesetCommand,
+) -> Result<Output, Box<dyn Error>> {
+    let output = match command {
+        ChangesetCommand::List(filters) => {
+            let query = types::ChangesetQueryParams {
+                user_id: filters.user,
+                display_name: filters.display_name,
+                open: filters.open.then_some(true),
+                closed: filters.closed.then_some(true),
+                limit: filters.limit,
+                ..Default::default()
+            };
+
+            Output::Changesets(client.changesets(query).await?)
+        }
+        ChangesetCommand::Download { id } => {
+            Output::Changes(client.changeset().download(id).await?)
+        }
+        ChangesetCommand::Upload { .. } => unreachable!("handled by upload()"),
+        ChangesetCommand::Close { id } => {
+            client.changeset().close(id).await?;
+            Output::Nothing
+        }
+    };
+
+    Ok(output)
+}
+
+/// uploads the changes, returning the diff result along with the error of closing the
+/// changeset opened for them, so the result is printed even when the close fails
+async fn upload(
+    client: &Openstreetmap,
+    file: PathBuf,
+    changeset: Option<u64>,
+    comment: Option<String>,
+) -> Result<(Output, Option<Box<dyn Error>>), Box<dyn Error>> {
+    let mut changes = xml::read_changes_file(&file)?;
+    let changeset_id = match changeset {
+        Some(changeset_id) => changeset_id,
+        None => {
+            let tags = comment
+                .iter()
+                .map(|comment| types::Tag::new("comment", comment))
+                .chain([types::Tag::new("created_by", output::GENERATOR)])
+                .collect();
+            let create = types::ChangesetCreate::new(
+                openstreetmap_api::DEFAULT_VERSION,
+                output::GENERATOR,
+                tags,
+            );
+
+            client.changeset().create(vec![create]).await?
+        }
+    };
+    assign_changeset(&mut changes, changeset_id);
+
+    let diff = client.changeset().upload(changeset_id, changes).await;
+    let closed = match changeset {
+        Some(_) => Ok(()),
+        None => client.changeset().close(changeset_id).await,
+    };
+    let close_error = closed.err().map(|error| {
+        format!(
+            "the changes were uploaded but the changeset {changeset_id} was not closed: {error:?}"
+        )
+        .into()
+    });
+
+    Ok((Output::DiffResult(diff?), close_error))
+}
+
+fn token(
+    config: &Config,
+    profile: Option<&str>,
+    command: TokenCommand,
+) -> Result<(), Box<dyn Error>> {
+    let name = config.profile(profile)?.name;
+
+    match command {
+        TokenCommand::Save { token } => config.tokens().save(&name, &token)?,
+        TokenCommand::Remove => {
+            if !config.tokens().remove(&name)? {
+                return Err(format!("the profile {name} has no token").into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config = match cli.config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::load()?,
+    };
+
+    if let Command::Token(command) = cli.command {
+        return token(&config, cli.profile.as_deref(), command);
+    }
+
+    let profile = config.profile(cli.profile.as_deref())?;
+    // the credentials of a profile only go to another host when the profile is named
+    let (host, credentials) = match cli.host {
+        Some(host) if cli.profile.is_none() => (host, types::Credentials::None),
+        Some(host) => (host, profile.credentials),
+        None => (profile.host, profile.credentials),
+    };
+    let client = Openstreetmap::new(host, credentials);
+    let mut close_error = None;
+
+    let output = match cli.command {
+        Command::Node(command) => {
+            element(&client, types::ElementType::Node, command, cli.format).await?
+        }
+        Command::Way(command) => {
+            element(&client, types::ElementType::Way, command, cli.format).await?
+        }
+        Command::Relation(command) => {
+            element(&client, types::ElementType::Relation, command, cli.format).await?
+        }
+        Command::Map { bbox } => Output::Map(client.map(&bbox).await?),
+        Command::Changeset(ChangesetCommand::Upload {
+            file,
+            changeset,
+            comment,
+        }) => {
+            let (output, error) = upload(&client, file, changeset, comment).await?;
+
+            close_error = error;
+            output
+        }
+        Command::Changeset(command) => changeset(&client, command).await?,
+        Command::Notes(NotesCommand::Search {
+            query,
+            bbox,
+            closed,
+            limit,
+        }) => {
+            let options = types::NoteSearchOptions {
+                q: query.unwrap_or_default(),
+                bbox,
+                closed: closed.map(note_closed),
+                limit,
+                ..Default::default()
+            };
+
+            Output::Notes(client.notes().search(&options).await?)
+        }
+        Command::User(UserCommand::Details) => Output::User(client.user().details().await?),
+        Command::Token(_) => unreachable!("handled without a client"),
+    };
+
+    let body = output.render(cli.format)?;
+
+    match cli.output {
+        Some(path) => fs::write(path, body)?,
+        None => io::stdout().write_all(&body)?,
+    }
+
+    match close_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(error) = run(Cli::parse()).await {
+        // the library errors carry their details in the variant rather than in the message
+        match error.downcast_ref::<OpenstreetmapError>() {
+            Some(OpenstreetmapError::Config(message)) => eprintln!("osm: {message}"),
+            Some(error) => eprintln!("osm: {error:?}"),
+            None => eprintln!("osm: {error}"),
+        }
+        std::process::exit(1);
+    }
+}
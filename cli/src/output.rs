@@ -0,0 +1,405 @@
+use openstreetmap_api::geojson;
+use openstreetmap_api::geometry::GeometryBuilder;
+use openstreetmap_api::types;
+use openstreetmap_api::xml::{self, ElementWriter};
+use serde_json::{json, Value};
+use std::error::Error;
+
+pub const GENERATOR: &str = concat!("osm ", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Xml,
+    Json,
+    Geojson,
+}
+
+/// An element borrowed from the result, so elements are written without being cloned
+enum ElementRef<'a> {
+    Node(&'a types::Node),
+    Way(&'a types::Way),
+    Relation(&'a types::Relation),
+}
+
+impl<'a> From<&'a types::Element> for ElementRef<'a> {
+    fn from(element: &'a types::Element) -> Self {
+        match element {
+            types::Element::Node(node) => ElementRef::Node(node),
+            types::Element::Way(way) => ElementRef::Way(way),
+            types::Element::Relation(relation) => ElementRef::Relation(relation),
+        }
+    }
+}
+
+/// The result of a command
+#[derive(Debug)]
+pub enum Output {
+    Elements(Vec<types::Element>),
+    WayFull(types::WayFull),
+    RelationFull(types::RelationFull),
+    Map(types::Map),
+    Changesets(Vec<types::Changeset>),
+    Changes(types::ChangesetChanges),
+    DiffResult(types::DiffResult),
+    Notes(Vec<types::Note>),
+    User(types::User),
+    Nothing,
+}
+
+impl Output {
+    fn name(&self) -> &'static str {
+        match self {
+            Output::Elements(_) => "elements",
+            Output::WayFull(_) => "way",
+            Output::RelationFull(_) => "relation",
+            Output::Map(_) => "map",
+            Output::Changesets(_) => "changesets",
+            Output::Changes(_) => "changes",
+            Output::DiffResult(_) => "diff result",
+            Output::Notes(_) => "notes",
+            Output::User(_) => "user",
+            Output::Nothing => "nothing",
+        }
+    }
+
+    /// OSM XML for the elements and the changes, JSON for the results the API has no
+    /// document format of its own for on the command line
+    fn default_format(&self) -> Format {
+        match self {
+            Output::Changesets(_) | Output::DiffResult(_) | Output::Notes(_) | Output::User(_) => {
+                Format::Json
+            }
+            _ => Format::Xml,
+        }
+    }
+
+    pub fn render(&self, format: Option<Format>) -> Result<Vec<u8>, Box<dyn Error>> {
+        // commands without a result print nothing whatever the format
+        if let Output::Nothing = self {
+            return Ok(vec![]);
+        }
+
+        let format = format.unwrap_or_else(|| self.default_format());
+        let rendered = match format {
+            Format::Xml => self.xml()?,
+            Format::Json => Some(to_json(&self.json())?),
+            Format::Geojson => self.geojson()?,
+        };
+
+        rendered
+            .ok_or_else(|| format!("the {} cannot be written as {format:?}", self.name()).into())
+    }
+
+    fn xml(&self) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let body = match self {
+            Output::Elements(elements) => write_elements(elements.iter().map(ElementRef::from))?,
+            Output::WayFull(full) => {
+                let nodes = full.nodes.iter().map(ElementRef::Node);
+
+                write_elements(nodes.chain([ElementRef::Way(&full.way)]))?
+            }
+            Output::RelationFull(full) => {
+                let nodes = full.nodes.iter().map(ElementRef::Node);
+                let ways = full.ways.iter().map(ElementRef::Way);
+
+                write_elements(
+                    nodes
+                        .chain(ways)
+                        .chain([ElementRef::Relation(&full.relation)]),
+                )?
+            }
+            Output::Map(map) => xml::write_map(vec![], map, GENERATOR)?,
+            Output::Changes(changes) => xml::write_changes(vec![], changes, GENERATOR)?,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(body))
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            Output::Elements(elements) => Value::Array(elements.iter().map(element_json).collect()),
+            Output::WayFull(full) => json!({
+                "way": way_json(&full.way),
+                "nodes": full.nodes.iter().map(node_json).collect::<Vec<_>>(),
+            }),
+            Output::RelationFull(full) => json!({
+                "relation": relation_json(&full.relation),
+                "ways": full.ways.iter().map(way_json).collect::<Vec<_>>(),
+                "nodes": full.nodes.iter().map(node_json).collect::<Vec<_>>(),
+            }),
+            Output::Map(map) => json!({
+                "bounds": bbox_json(&map.bounds),
+                "nodes": map.nodes.iter().map(node_json).collect::<Vec<_>>(),
+                "ways": map.ways.iter().map(way_json).collect::<Vec<_>>(),
+                "relations": map.relations.iter().map(relation_json).collect::<Vec<_>>(),
+            }),
+            Output::Changesets(changesets) => {
+                Value::Array(changesets.iter().map(changeset_json).collect())
+            }
+            Output::Changes(changes) => changes_json(changes),
+            Output::DiffResult(diff) => diff_json(diff),
+            Output::Notes(notes) => Value::Array(notes.iter().map(note_json).collect()),
+            Output::User(user) => user_json(user),
+            Output::Nothing => Value::Null,
+        }
+    }
+
+    fn geojson(&self) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let collection = match self {
+            Output::Map(map) => {
+                let (collection, errors) = geojson::map_to_feature_collection(map);
+
+                if !errors.is_empty() {
+                    eprintln!("osm: skipped {} elements without a geometry", errors.len());
+                }
+                collection
+            }
+            Output::WayFull(full) => {
+                let feature = geojson::way_to_feature(&GeometryBuilder::from(full), &full.way)?;
+
+                ::geojson::FeatureCollection::from_iter([feature])
+            }
+            Output::RelationFull(full) => {
+                let feature =
+                    geojson::relation_to_feature(&GeometryBuilder::from(full), &full.relation)?;
+
+                ::geojson::FeatureCollection::from_iter([feature])
+            }
+            Output::Elements(elements) => elements
+                .iter()
+                .map(|element| -> Result<_, Box<dyn Error>> {
+                    match element {
+                        types::Element::Node(node) => Ok(geojson::node_to_feature(node)?),
+                        // `way get` and `relation get` fetch the full element for the geometry,
+                        // only the versions of a history come without their nodes
+                        types::Element::Way(way) => Err(format!(
+                            "the version {} of the way {} has no geometry without its nodes",
+                            way.version, way.id
+                        )
+                        .into()),
+                        types::Element::Relation(relation) => Err(format!(
+                            "the version {} of the relation {} has no geometry without its members",
+                            relation.version, relation.id
+                        )
+                        .into()),
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+            Output::Changesets(changesets) => geojson::changesets_to_feature_collection(changesets),
+            Output::Notes(notes) => geojson::notes_to_feature_collection(notes),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(to_json(&collection)?))
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut body = serde_json::to_vec_pretty(value)?;
+
+    body.push(b'\n');
+    Ok(body)
+}
+
+fn write_elements<'a, I>(elements: I) -> Result<Vec<u8>, Box<dyn Error>>
+where
+    I: IntoIterator<Item = ElementRef<'a>>,
+{
+    let mut writer = ElementWriter::new(vec![], GENERATOR, None)?;
+
+    for element in elements {
+        match element {
+            ElementRef::Node(node) => writer.write_node(node)?,
+            ElementRef::Way(way) => writer.write_way(way)?,
+            ElementRef::Relation(relation) => writer.write_relation(relation)?,
+        }
+    }
+
+    Ok(writer.finish()?)
+}
+
+fn tags_json(tags: &[types::Tag]) -> Value {
+    Value::Object(
+        tags.iter()
+            .map(|tag| (tag.k.clone(), Value::String(tag.v.clone())))
+            .collect(),
+    )
+}
+
+fn bbox_json(bbox: &types::BoundingBox) -> Value {
+    json!([bbox.left, bbox.bottom, bbox.right, bbox.top])
+}
+
+fn element_json(element: &types::Element) -> Value {
+    match element {
+        types::Element::Node(node) => node_json(node),
+        types::Element::Way(way) => way_json(way),
+        types::Element::Relation(relation) => relation_json(relation),
+    }
+}
+
+fn node_json(node: &types::Node) -> Value {
+    json!({
+        "type": "node",
+        "id": node.id,
+        "version": node.version,
+        "changeset": node.changeset,
+        "timestamp": node.timestamp,
+        "user": node.user,
+        "uid": node.uid,
+        "visible": node.visible,
+        "lat": node.lat,
+        "lon": node.lon,
+        "tags": tags_json(&node.tags),
+    })
+}
+
+fn way_json(way: &types::Way) -> Value {
+    json!({
+        "type": "way",
+        "id": way.id,
+        "version": way.version,
+        "changeset": way.changeset,
+        "timestamp": way.timestamp,
+        "user": way.user,
+        "uid": way.uid,
+        "visible": way.visible,
+        "nodes": way.node_refs.iter().map(|node_ref| node_ref.node_id).collect::<Vec<_>>(),
+        "tags": tags_json(&way.tags),
+    })
+}
+
+fn relation_json(relation: &types::Relation) -> Value {
+    json!({
+        "type": "relation",
+        "id": relation.id,
+        "version": relation.version,
+        "changeset": relation.changeset,
+        "timestamp": relation.timestamp,
+        "user": relation.user,
+        "uid": relation.uid,
+        "visible": relation.visible,
+        "members": relation
+            .members
+            .iter()
+            .map(|member| json!({
+                "type": member.member_type,
+                "ref": member.node_id,
+                "role": member.role,
+            }))
+            .collect::<Vec<_>>(),
+        "tags": tags_json(&relation.tags),
+    })
+}
+
+fn changeset_json(changeset: &types::Changeset) -> Value {
+    json!({
+        "id": changeset.id,
+        "user": changeset.user,
+        "uid": changeset.uid,
+        "created_at": changeset.created_at,
+        "closed_at": changeset.closed_at,
+        "open": changeset.open,
+        "comments_count": changeset.comments_count,
+        "changes_count": changeset.changes_count,
+        "bbox": changeset.bbox.as_ref().map(bbox_json),
+        "tags": tags_json(&changeset.tags),
+    })
+}
+
+fn changes_json(changes: &types::ChangesetChanges) -> Value {
+    let block = |nodes: &[types::Node], ways: &[types::Way], relations: &[types::Relation]| {
+        nodes
+            .iter()
+            .map(node_json)
+            .chain(ways.iter().map(way_json))
+            .chain(relations.iter().map(relation_json))
+            .collect::<Vec<_>>()
+    };
+
+    json!({
+        "create": changes
+            .creations
+            .iter()
+            .flat_map(|c| block(&c.nodes, &c.ways, &c.relations))
+            .collect::<Vec<_>>(),
+        "modify": changes
+            .modifications
+            .iter()
+            .flat_map(|m| block(&m.nodes, &m.ways, &m.relations))
+            .collect::<Vec<_>>(),
+        "delete": changes
+            .deletions
+            .iter()
+            .flat_map(|d| block(&d.nodes, &d.ways, &d.relations))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn diff_json(diff: &types::DiffResult) -> Value {
+    let line = |element_type: &str, old_id: i64, new_id: Option<i64>, new_version: Option<u64>| {
+        json!({
+            "type": element_type,
+            "old_id": old_id,
+            "new_id": new_id,
+            "new_version": new_version,
+        })
+    };
+
+    Value::Array(
+        diff.nodes
+            .iter()
+            .map(|d| line("node", d.old_id, d.new_id, d.new_version))
+            .chain(
+                diff.ways
+                    .iter()
+                    .map(|d| line("way", d.old_id, d.new_id, d.new_version)),
+            )
+            .chain(
+                diff.relations
+                    .iter()
+                    .map(|d| line("relation", d.old_id, d.new_id, d.new_version)),
+            )
+            .collect(),
+    )
+}
+
+fn note_json(note: &types::Note) -> Value {
+    json!({
+        "id": note.id,
+        "lat": note.lat,
+        "lon": note.lon,
+        "url": note.url,
+        "created_at": note.created_at,
+        "status": note.status,
+        "comments": note
+            .comments
+            .iter()
+            .map(|comment| json!({
+                "date": comment.date,
+                "uid": comment.id,
+                "user": comment.user,
+                "action": comment.action,
+                "text": comment.text,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn user_json(user: &types::User) -> Value {
+    json!({
+        "id": user.id,
+        "display_name": user.display_name,
+        "account_created": user.account_created,
+        "description": user.description,
+        "changesets": user.changesets.count,
+        "traces": user.traces.count,
+        "languages": user.languages,
+        "messages": {
+            "received": user.messages.received,
+            "unread": user.messages.unread,
+            "sent": user.messages.sent,
+        },
+    })
+}
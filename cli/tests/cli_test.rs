@@ -0,0 +1,506 @@
+use openstreetmap_api::config::TokenStore;
+use openstreetmap_api::test_server::TestServer;
+use openstreetmap_api::{types, xml, Openstreetmap};
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn temp_path(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("osm-cli-test-{}", std::process::id()));
+
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(name)
+}
+
+/// a configuration file whose default profile has the credentials of the test user
+fn config(server: &TestServer, name: &str) -> PathBuf {
+    let path = temp_path(name).join("config.toml");
+
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        format!(
+            "default_profile = \"test\"\n\n[profiles.test]\nhost = \"{}\"\nuser = \"mapper\"\npassword = \"secret\"\n",
+            server.uri()
+        ),
+    )
+    .unwrap();
+    path
+}
+
+/// runs `osm` with the given arguments, without configuration from the environment
+fn osm(server: &TestServer, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_osm"))
+        .args(args)
+        .env("OPENSTREETMAP_HOST", server.uri())
+        .env("XDG_CONFIG_HOME", temp_path("no-config"))
+        .env_remove("OPENSTREETMAP_CONFIG")
+        .env_remove("OPENSTREETMAP_PROFILE")
+        .env_remove("OPENSTREETMAP_USER")
+        .env_remove("OPENSTREETMAP_PASSWORD")
+        .env_remove("OPENSTREETMAP_TOKEN")
+        .output()
+        .unwrap()
+}
+
+fn stdout_json(output: &Output) -> Value {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// creates a user and a node in a closed changeset, returning the node id
+async fn create_node(server: &TestServer) -> i64 {
+    server.add_user("mapper", "secret");
+
+    let credentials = types::Credentials::Basic("mapper".into(), "secret".into());
+    let client = Openstreetmap::new(server.uri(), credentials);
+    let changeset_id = client
+        .changeset()
+        .create(vec![types::ChangesetCreate::new("0.6", "test", vec![])])
+        .await
+        .unwrap();
+    let node = types::Node {
+        id: 0,
+        visible: true,
+        version: 0,
+        changeset: changeset_id,
+        timestamp: String::new(),
+        user: None,
+        uid: None,
+        lat: Some(51.5),
+        lon: Some(-0.1),
+        tags: vec![types::Tag::new("amenity", "bench")],
+    };
+    let node_id = client.nodes().create(node).await.unwrap();
+
+    client.changeset().close(changeset_id).await.unwrap();
+    node_id
+}
+
+#[tokio::test]
+async fn test_node_get_json() {
+    /*
+    GIVEN a server with a node
+    WHEN running `osm node get` with the JSON format
+    THEN the node is printed as JSON
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let node_id = create_node(&server).await;
+
+    // WHEN
+    let output = osm(
+        &server,
+        &["node", "get", &node_id.to_string(), "-f", "json"],
+    );
+
+    // THEN
+    let nodes = stdout_json(&output);
+
+    assert_eq!(nodes[0]["id"], json!(node_id));
+    assert_eq!(nodes[0]["version"], json!(1));
+    assert_eq!(nodes[0]["tags"], json!({"amenity": "bench"}));
+}
+
+#[tokio::test]
+async fn test_map_osm_file() {
+    /*
+    GIVEN a server with a node
+    WHEN running `osm map` with an output file
+    THEN the file is an OSM XML document with the node inside the bounds
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let node_id = create_node(&server).await;
+    let path = temp_path("map.osm");
+
+    // WHEN
+    let output = osm(
+        &server,
+        &[
+            "map",
+            "--bbox",
+            "-0.2,51.4,0,51.6",
+            "-o",
+            path.to_str().unwrap(),
+        ],
+    );
+
+    // THEN
+    assert!(output.status.success());
+
+    let map = xml::read_map(fs::File::open(&path).map(std::io::BufReader::new).unwrap()).unwrap();
+
+    assert_eq!(map.bounds.left, -0.2);
+    assert_eq!(
+        map.nodes.iter().map(|node| node.id).collect::<Vec<_>>(),
+        vec![node_id]
+    );
+}
+
+#[tokio::test]
+async fn test_changeset_upload() {
+    /*
+    GIVEN a server with a user
+        AND a configuration file with its credentials
+    WHEN running `osm changeset upload` with an osmChange file
+    THEN the diff result is printed
+        AND the changeset is closed
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    create_node(&server).await;
+
+    let config = config(&server, "upload.toml");
+    let changes = temp_path("upload.osc");
+
+    fs::write(
+        &changes,
+        r#"<osmChange version="0.6">
+            <create>
+                <node id="1" version="0" changeset="0" timestamp="" visible="true" lat="51.6" lon="-0.2"/>
+            </create>
+        </osmChange>"#,
+    )
+    .unwrap();
+
+    // WHEN
+    let output = osm(
+        &server,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "changeset",
+            "upload",
+            changes.to_str().unwrap(),
+            "-m",
+            "Add a bench",
+            "-f",
+            "json",
+        ],
+    );
+
+    // THEN
+    let diff = stdout_json(&output);
+    let changesets = stdout_json(&osm(&server, &["changeset", "list", "-f", "json"]));
+
+    assert_eq!(
+        diff,
+        json!([{"type": "node", "old_id": 1, "new_id": 2, "new_version": 1}])
+    );
+    assert_eq!(changesets[0]["open"], json!(false));
+    assert_eq!(changesets[0]["tags"]["comment"], json!("Add a bench"));
+}
+
+#[tokio::test]
+async fn test_user_details() {
+    /*
+    GIVEN a server with a user
+    WHEN running `osm user details` with and without credentials
+    THEN the user is printed with the credentials of the configuration file
+        AND the request fails without credentials
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    server.add_user("mapper", "secret");
+
+    let config = config(&server, "details.toml");
+
+    // WHEN
+    let authenticated = osm(
+        &server,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "user",
+            "details",
+            "-f",
+            "json",
+        ],
+    );
+    let anonymous = osm(&server, &["user", "details", "-f", "json"]);
+
+    // THEN
+    assert_eq!(stdout_json(&authenticated)["display_name"], json!("mapper"));
+    assert!(!anonymous.status.success());
+}
+
+#[tokio::test]
+async fn test_host_credentials() {
+    /*
+    GIVEN a server with a user
+        AND a configuration file with the credentials of the user
+    WHEN running `osm user details` with `--host`, with and without `--profile`
+    THEN the credentials of the profile are only sent with `--profile`
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    server.add_user("mapper", "secret");
+
+    let config = config(&server, "host.toml");
+    let config = config.to_str().unwrap();
+    let host = server.uri();
+
+    // WHEN
+    let with_profile = osm(
+        &server,
+        &[
+            "--config", config, "--host", &host, "-p", "test", "user", "details", "-f", "json",
+        ],
+    );
+    let without_profile = osm(
+        &server,
+        &[
+            "--config", config, "--host", &host, "user", "details", "-f", "json",
+        ],
+    );
+
+    // THEN
+    assert_eq!(stdout_json(&with_profile)["display_name"], json!("mapper"));
+    assert!(!without_profile.status.success());
+}
+
+#[tokio::test]
+async fn test_unsupported_format() {
+    /*
+    GIVEN a server with a user
+    WHEN running `osm user details` with the GeoJSON format
+    THEN the command fails explaining the format is not available
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    server.add_user("mapper", "secret");
+
+    let config = config(&server, "geojson.toml");
+
+    // WHEN
+    let output = osm(
+        &server,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "user",
+            "details",
+            "-f",
+            "geojson",
+        ],
+    );
+
+    // THEN
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "osm: the user cannot be written as Geojson\n"
+    );
+}
+
+#[tokio::test]
+async fn test_token() {
+    /*
+    GIVEN a configuration file
+    WHEN running `osm token save` and `osm token remove` for a profile
+    THEN the token of the profile is stored next to the configuration file, then removed
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let config = config(&server, "token");
+    let tokens = TokenStore::new(config.with_file_name("tokens.toml"));
+    let config = config.to_str().unwrap();
+
+    // WHEN
+    let saved = osm(
+        &server,
+        &[
+            "--config",
+            config,
+            "-p",
+            "dev",
+            "token",
+            "save",
+            "access-token",
+        ],
+    );
+    let stored = tokens.get("dev").unwrap();
+    let removed = osm(
+        &server,
+        &["--config", config, "-p", "dev", "token", "remove"],
+    );
+    let removed_again = osm(
+        &server,
+        &["--config", config, "-p", "dev", "token", "remove"],
+    );
+
+    // THEN
+    assert!(saved.status.success());
+    assert_eq!(stored, Some("access-token".into()));
+    assert!(removed.status.success());
+    assert_eq!(tokens.get("dev").unwrap(), None);
+    assert_eq!(
+        String::from_utf8_lossy(&removed_again.stderr),
+        "osm: the profile dev has no token\n"
+    );
+}
+
+#[tokio::test]
+async fn test_default_formats() {
+    /*
+    GIVEN a server with a user
+        AND a configuration file with its credentials
+    WHEN running commands without a format
+    THEN the elements are printed as XML
+        AND the diff result, the changesets, the notes and the user as JSON
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let node_id = create_node(&server).await;
+    let config = config(&server, "defaults.toml");
+    let config = config.to_str().unwrap();
+    let changes = temp_path("defaults.osc");
+
+    fs::write(
+        &changes,
+        r#"<osmChange version="0.6">
+            <create>
+                <node id="-1" version="0" changeset="0" timestamp="" visible="true" lat="51.6" lon="-0.2"/>
+            </create>
+        </osmChange>"#,
+    )
+    .unwrap();
+
+    // WHEN
+    let node = osm(&server, &["node", "get", &node_id.to_string()]);
+    let diff = osm(
+        &server,
+        &[
+            "--config",
+            config,
+            "changeset",
+            "upload",
+            changes.to_str().unwrap(),
+        ],
+    );
+    let changesets = osm(&server, &["changeset", "list"]);
+    let notes = osm(&server, &["notes", "search", "--bbox=-1,50,1,52"]);
+    let user = osm(&server, &["--config", config, "user", "details"]);
+
+    // THEN
+    assert!(node.status.success());
+    assert!(String::from_utf8_lossy(&node.stdout).contains(&format!("<node id=\"{node_id}\"")));
+    assert_eq!(stdout_json(&diff)[0]["type"], json!("node"));
+    assert_eq!(stdout_json(&changesets).as_array().unwrap().len(), 2);
+    assert_eq!(stdout_json(&notes), json!([]));
+    assert_eq!(stdout_json(&user)["display_name"], json!("mapper"));
+}
+
+#[tokio::test]
+async fn test_changeset_close_json() {
+    /*
+    GIVEN a server with an open changeset
+    WHEN running `osm changeset close` with the JSON format
+    THEN the command succeeds without printing anything
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    server.add_user("mapper", "secret");
+
+    let credentials = types::Credentials::Basic("mapper".into(), "secret".into());
+    let changeset_id = Openstreetmap::new(server.uri(), credentials)
+        .changeset()
+        .create(vec![types::ChangesetCreate::new("0.6", "test", vec![])])
+        .await
+        .unwrap();
+    let config = config(&server, "close.toml");
+
+    // WHEN
+    let output = osm(
+        &server,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "changeset",
+            "close",
+            &changeset_id.to_string(),
+            "-f",
+            "json",
+        ],
+    );
+
+    // THEN
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty());
+}
+
+#[tokio::test]
+async fn test_way_get_geojson() {
+    /*
+    GIVEN a server with a way of two nodes
+    WHEN running `osm way get` with the GeoJSON format
+    THEN the way is printed as a line between its nodes
+    */
+    // GIVEN
+    let server = TestServer::start();
+
+    server.add_user("mapper", "secret");
+
+    let config = config(&server, "way.toml");
+    let changes = temp_path("way.osc");
+
+    fs::write(
+        &changes,
+        r#"<osmChange version="0.6">
+            <create>
+                <node id="-1" version="0" changeset="0" timestamp="" visible="true" lat="51.5" lon="-0.1"/>
+                <node id="-2" version="0" changeset="0" timestamp="" visible="true" lat="51.6" lon="-0.2"/>
+                <way id="-3" version="0" changeset="0" timestamp="" visible="true">
+                    <nd ref="-1"/>
+                    <nd ref="-2"/>
+                    <tag k="highway" v="footway"/>
+                </way>
+            </create>
+        </osmChange>"#,
+    )
+    .unwrap();
+
+    let diff = stdout_json(&osm(
+        &server,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "changeset",
+            "upload",
+            changes.to_str().unwrap(),
+        ],
+    ));
+    let way_id = diff[2]["new_id"].to_string();
+
+    // WHEN
+    let output = osm(&server, &["way", "get", &way_id, "-f", "geojson"]);
+
+    // THEN
+    let collection = stdout_json(&output);
+    let feature = &collection["features"][0];
+
+    assert_eq!(collection["features"].as_array().unwrap().len(), 1);
+    assert_eq!(feature["geometry"]["type"], json!("LineString"));
+    assert_eq!(
+        feature["geometry"]["coordinates"],
+        json!([[-0.1, 51.5], [-0.2, 51.6]])
+    );
+    assert_eq!(feature["properties"]["highway"], json!("footway"));
+}
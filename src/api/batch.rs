@@ -0,0 +1,119 @@
+use crate::Openstreetmap;
+use crate::OpenstreetmapError;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::fmt::Display;
+use std::future::Future;
+
+// Length of the URL-encoded comma (`%2C`) separating the ids in the query string
+const SEPARATOR_LENGTH: usize = 3;
+
+/// Splits the ids into batches whose comma separated representation fits into `available`
+/// characters; a single id longer than the limit still gets its own batch.
+pub(crate) fn chunk_ids<I>(ids: &[I], available: usize) -> Vec<Vec<I>>
+where
+    I: Display + Clone,
+{
+    let mut chunks = vec![];
+    let mut current: Vec<I> = vec![];
+    let mut length = 0;
+
+    for id in ids {
+        let id_length = id.to_string().len();
+
+        if !current.is_empty() && length + SEPARATOR_LENGTH + id_length > available {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        length = if current.is_empty() {
+            id_length
+        } else {
+            length + SEPARATOR_LENGTH + id_length
+        };
+
+        current.push(id.clone());
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Runs `fetch` for every batch with the client's concurrency and returns the fetched items
+/// in batch order. A batch failing with `NotFound` is bisected until the missing ids are
+/// isolated, so they can be reported instead of failing the whole call.
+pub(crate) async fn fetch_chunks<I, T, F, Fut>(
+    client: &Openstreetmap,
+    chunks: Vec<Vec<I>>,
+    fetch: F,
+) -> Result<Vec<T>, OpenstreetmapError>
+where
+    I: Clone,
+    F: Fn(Vec<I>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, OpenstreetmapError>>,
+{
+    let results = stream::iter(chunks)
+        .map(|chunk| fetch_or_split(&fetch, chunk))
+        .buffered(client.concurrency)
+        .try_collect::<Vec<Vec<T>>>()
+        .await?;
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+async fn fetch_or_split<I, T, F, Fut>(
+    fetch: &F,
+    chunk: Vec<I>,
+) -> Result<Vec<T>, OpenstreetmapError>
+where
+    I: Clone,
+    F: Fn(Vec<I>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, OpenstreetmapError>>,
+{
+    let mut items = vec![];
+    let mut pending = vec![chunk];
+
+    while let Some(mut ids) = pending.pop() {
+        match fetch(ids.clone()).await {
+            Ok(fetched) => items.extend(fetched),
+            Err(OpenstreetmapError::NotFound) if ids.len() > 1 => {
+                let right = ids.split_off(ids.len() / 2);
+
+                pending.push(right);
+                pending.push(ids);
+            }
+            Err(OpenstreetmapError::NotFound) => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    #[rstest(ids, available, expected,
+        case(vec![], 10, vec![]),
+        case(vec![1, 2, 3], 100, vec![vec![1, 2, 3]]),
+        case(vec![1, 2, 3], 5, vec![vec![1, 2], vec![3]]),
+        case(vec![1234, 5, 6], 2, vec![vec![1234], vec![5], vec![6]])
+    )]
+    fn test_chunk_ids(ids: Vec<u64>, available: usize, expected: Vec<Vec<u64>>) {
+        /*
+        GIVEN a list of ids and the available URL length
+        WHEN splitting them into batches
+        THEN every batch fits into the available length
+        */
+        // WHEN
+        let actual = chunk_ids(&ids, available);
+
+        // THEN
+        assert_eq!(actual, expected);
+    }
+}
@@ -105,7 +105,7 @@ impl Capabilities {
         let capabilities_and_policies: types::CapabilitiesAndPolicy = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 "capabilities",
                 types::RequestBody::None,
                 RequestOptions::new(),
@@ -1,8 +1,14 @@
+use super::revert;
 use crate::types;
+use crate::xml;
 use crate::Openstreetmap;
 use crate::OpenstreetmapError;
 use crate::RequestOptions;
 
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::pin::pin;
+
 #[derive(Debug, Serialize)]
 #[serde(rename = "changeset")]
 struct ChangesetUpdate {
@@ -10,10 +16,71 @@ struct ChangesetUpdate {
     pub tags: Vec<types::Tag>,
 }
 
+#[derive(Debug, PartialEq, Deserialize)]
+pub(crate) struct ChangesetRaw {
+    #[serde(rename = "@id")]
+    pub id: u64,
+    #[serde(rename = "@user")]
+    pub user: String,
+    #[serde(rename = "@uid")]
+    pub uid: u64,
+    #[serde(rename = "@created_at")]
+    pub created_at: String,
+    #[serde(rename = "@closed_at")]
+    pub closed_at: Option<String>,
+    #[serde(rename = "@open")]
+    pub open: bool,
+    #[serde(rename = "@comments_count", default)]
+    pub comments_count: u64,
+    #[serde(rename = "@changes_count", default)]
+    pub changes_count: u64,
+    pub discussion: Option<types::Discussion>,
+    #[serde(rename = "tag", default)]
+    pub tags: Vec<types::Tag>,
+
+    // The bounding box attributes will be missing for an empty changeset
+    #[serde(rename = "@min_lon")]
+    pub min_lon: Option<f64>,
+    #[serde(rename = "@min_lat")]
+    pub min_lat: Option<f64>,
+    #[serde(rename = "@max_lon")]
+    pub max_lon: Option<f64>,
+    #[serde(rename = "@max_lat")]
+    pub max_lat: Option<f64>,
+}
+
+impl From<ChangesetRaw> for types::Changeset {
+    fn from(value: ChangesetRaw) -> types::Changeset {
+        let bbox = match (value.min_lon, value.min_lat, value.max_lon, value.max_lat) {
+            (Some(left), Some(bottom), Some(right), Some(top)) => Some(types::BoundingBox {
+                left,
+                bottom,
+                right,
+                top,
+            }),
+            _ => None,
+        };
+
+        types::Changeset {
+            id: value.id,
+            user: value.user,
+            uid: value.uid,
+            created_at: value.created_at,
+            closed_at: value.closed_at,
+            open: value.open,
+            comments_count: value.comments_count,
+            changes_count: value.changes_count,
+            bbox,
+            discussion: value.discussion,
+            tags: value.tags,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename = "osm")]
 struct Osm {
-    pub changeset: types::Changeset,
+    pub changeset: ChangesetRaw,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -73,7 +140,7 @@ impl Changeset {
         let changeset_id = self
             .client
             .request::<OsmCreate, u64>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 "changeset/create",
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -93,7 +160,7 @@ impl Changeset {
         let changeset = self
             .client
             .request::<OsmUpdate, Osm>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 &url,
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -101,7 +168,7 @@ impl Changeset {
             .await?
             .changeset;
 
-        Ok(changeset)
+        Ok(changeset.into())
     }
 
     #[inline]
@@ -131,7 +198,7 @@ impl Changeset {
         let changeset = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -139,7 +206,7 @@ impl Changeset {
             .await?
             .changeset;
 
-        Ok(changeset)
+        Ok(changeset.into())
     }
 
     pub async fn close(&self, changeset_id: u64) -> Result<(), OpenstreetmapError> {
@@ -148,7 +215,7 @@ impl Changeset {
         // Use Vec<u8> because `serde` cannot deserialise EOF when using Unit;
         self.client
             .request::<(), Vec<u8>>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -162,17 +229,36 @@ impl Changeset {
         &self,
         changeset_id: u64,
     ) -> Result<types::ChangesetChanges, OpenstreetmapError> {
+        let mut elements = pin!(self.download_stream(changeset_id).await?);
+        let mut changes = xml::ChangesBuilder::default();
+
+        while let Some((action, element)) = elements.try_next().await? {
+            changes.push(action, element);
+        }
+
+        Ok(changes.finish())
+    }
+
+    /// like `download()` but yields the elements, paired with their action, while the
+    /// response is downloaded
+    pub async fn download_stream(
+        &self,
+        changeset_id: u64,
+    ) -> Result<
+        impl Stream<Item = Result<(types::ChangeAction, types::Element), OpenstreetmapError>>,
+        OpenstreetmapError,
+    > {
         let url = format!("changeset/{changeset_id}/download");
 
         let changes = self
             .client
-            .request::<(), types::ChangesetChanges>(
-                reqwest::Method::GET,
+            .request_stream(
+                http::Method::GET,
                 &url,
-                types::RequestBody::None,
                 RequestOptions::new().with_version(),
             )
-            .await?;
+            .await?
+            .into_change_stream();
 
         Ok(changes)
     }
@@ -187,7 +273,7 @@ impl Changeset {
         let diffs = self
             .client
             .request::<types::ChangesetChanges, types::DiffResult>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::Xml(changeset_change),
                 RequestOptions::new().with_version().with_auth(),
@@ -197,6 +283,134 @@ impl Changeset {
         Ok(diffs)
     }
 
+    /// Computes the changes undoing the changeset: the elements it created are deleted, the
+    /// ones it modified or deleted are restored to their previous version. Elements edited
+    /// by a later changeset, and created elements which later edits added to other ways or
+    /// relations, are reported as conflicts instead of being reverted.
+    pub async fn revert_changes(
+        &self,
+        changeset_id: u64,
+        target_changeset_id: u64,
+    ) -> Result<types::RevertPlan, OpenstreetmapError> {
+        let changes = self.download(changeset_id).await?;
+        let touched = revert::touched_elements(&changes);
+
+        let histories = stream::iter(touched.iter())
+            .map(|element| async move {
+                let history = self.history(element.element_type, element.id).await?;
+
+                Ok::<_, OpenstreetmapError>(((element.element_type, element.id), history))
+            })
+            .buffered(self.client.concurrency)
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        // Only the created elements are deleted, the others are restored
+        let referrers = stream::iter(touched.iter().filter(|element| element.first_version == 1))
+            .map(|element| async move {
+                let referrers = self.referrers(element.element_type, element.id).await?;
+
+                Ok::<_, OpenstreetmapError>(((element.element_type, element.id), referrers))
+            })
+            .buffered(self.client.concurrency)
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        Ok(revert::plan(
+            touched,
+            histories,
+            referrers,
+            target_changeset_id,
+        ))
+    }
+
+    /// Reverts the changeset by uploading the result of `revert_changes()` into the open
+    /// target changeset, returning the conflicting elements which were left alone.
+    pub async fn revert(
+        &self,
+        changeset_id: u64,
+        target_changeset_id: u64,
+    ) -> Result<types::RevertResult, OpenstreetmapError> {
+        let plan = self
+            .revert_changes(changeset_id, target_changeset_id)
+            .await?;
+
+        let diff = if plan.changes.modifications.is_empty() && plan.changes.deletions.is_empty() {
+            None
+        } else {
+            Some(self.upload(target_changeset_id, plan.changes).await?)
+        };
+
+        Ok(types::RevertResult {
+            diff,
+            conflicts: plan.conflicts,
+        })
+    }
+
+    async fn history(
+        &self,
+        element_type: types::ElementType,
+        element_id: i64,
+    ) -> Result<Vec<types::Element>, OpenstreetmapError> {
+        let history = match element_type {
+            types::ElementType::Node => self
+                .client
+                .nodes()
+                .history(element_id)
+                .await?
+                .into_iter()
+                .map(types::Element::Node)
+                .collect(),
+            types::ElementType::Way => self
+                .client
+                .ways()
+                .history(element_id)
+                .await?
+                .into_iter()
+                .map(types::Element::Way)
+                .collect(),
+            types::ElementType::Relation => self
+                .client
+                .relations()
+                .history(element_id)
+                .await?
+                .into_iter()
+                .map(types::Element::Relation)
+                .collect(),
+        };
+
+        Ok(history)
+    }
+
+    // The current ways and relations using the element
+    async fn referrers(
+        &self,
+        element_type: types::ElementType,
+        element_id: i64,
+    ) -> Result<Vec<(types::ElementType, i64)>, OpenstreetmapError> {
+        let (ways, relations) = match element_type {
+            types::ElementType::Node => {
+                let nodes = self.client.nodes();
+
+                (
+                    nodes.ways(element_id).await?,
+                    nodes.relations(element_id).await?,
+                )
+            }
+            types::ElementType::Way => (vec![], self.client.ways().relations(element_id).await?),
+            types::ElementType::Relation => {
+                (vec![], self.client.relations().relations(element_id).await?)
+            }
+        };
+
+        let ways = ways.iter().map(|w| (types::ElementType::Way, w.id));
+        let relations = relations
+            .iter()
+            .map(|r| (types::ElementType::Relation, r.id));
+
+        Ok(ways.chain(relations).collect())
+    }
+
     pub async fn comment(
         &self,
         changeset_id: u64,
@@ -208,7 +422,7 @@ impl Changeset {
         // Use Vec<u8> because `serde` cannot deserialise EOF when using Unit;
         self.client
             .request::<Comment, Vec<u8>>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -227,7 +441,7 @@ impl Changeset {
         let changeset = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -235,7 +449,7 @@ impl Changeset {
             .await?
             .changeset;
 
-        Ok(changeset)
+        Ok(changeset.into())
     }
 
     pub async fn unsubscribe(
@@ -247,7 +461,7 @@ impl Changeset {
         let changeset = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -255,7 +469,7 @@ impl Changeset {
             .await?
             .changeset;
 
-        Ok(changeset)
+        Ok(changeset.into())
     }
 }
 
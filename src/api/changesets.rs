@@ -1,11 +1,20 @@
+use crate::dates;
 use crate::types;
 use crate::Openstreetmap;
 use crate::OpenstreetmapError;
 use crate::RequestOptions;
 
+use super::changeset::ChangesetRaw;
+use futures::stream::{self, Stream, StreamExt};
 use serde::ser::Serializer;
+use std::collections::HashSet;
 use std::fmt::Display;
 
+// Maximum number of changesets the server returns for a single query
+const MAX_LIMIT: u32 = 100;
+
+const EPOCH: &str = "1970-01-01T00:00:00Z";
+
 fn vec_to_string<T, S>(vector: &Option<Vec<T>>, serialiser: S) -> Result<S::Ok, S::Error>
 where
     T: Display,
@@ -37,6 +46,8 @@ struct RawQuery {
     pub closed: Option<bool>,
     #[serde(serialize_with = "vec_to_string")]
     pub changesets: Option<Vec<u64>>,
+    pub limit: Option<u32>,
+    pub order: Option<types::ChangesetOrder>,
 }
 
 impl From<types::ChangesetQueryParams> for RawQuery {
@@ -50,11 +61,15 @@ impl From<types::ChangesetQueryParams> for RawQuery {
             time: match (query.closed_after, query.created_before) {
                 (Some(t1), None) => Some(vec![t1]),
                 (Some(t1), Some(t2)) => Some(vec![t1, t2]),
-                _ => None,
+                // The API only accepts an upper bound together with a lower one
+                (None, Some(t2)) => Some(vec![EPOCH.into(), t2]),
+                (None, None) => None,
             },
             open: query.open,
             closed: query.closed,
             changesets: query.changeset_ids,
+            limit: query.limit,
+            order: query.order,
         }
     }
 }
@@ -63,7 +78,7 @@ impl From<types::ChangesetQueryParams> for RawQuery {
 #[serde(rename = "osm")]
 struct Osm {
     #[serde(rename = "changeset", default)]
-    pub changesets: Vec<types::Changeset>,
+    pub changesets: Vec<ChangesetRaw>,
 }
 
 pub struct Changesets {
@@ -94,7 +109,7 @@ impl Changesets {
         let changesets = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -102,11 +117,76 @@ impl Changesets {
             .await?
             .changesets;
 
-        Ok(changesets)
+        Ok(changesets.into_iter().map(types::Changeset::from).collect())
+    }
+
+    /// Follows the query backwards in time, newest changesets first, until all the matching
+    /// changesets have been returned. The `order` of the query is ignored and the `limit`, the
+    /// size of the pages, is capped at the server's maximum of 100. When more changesets than
+    /// a page holds were created within the same second, the ones not returned by the server
+    /// are skipped. The stream ends with `OpenstreetmapError::PagingStalled` when the server
+    /// keeps returning the same changesets whatever the time window.
+    pub fn get_stream(
+        self,
+        query: types::ChangesetQueryParams,
+    ) -> impl Stream<Item = Result<types::Changeset, OpenstreetmapError>> {
+        let limit = query.limit.unwrap_or(MAX_LIMIT).clamp(1, MAX_LIMIT);
+        let query = types::ChangesetQueryParams {
+            limit: Some(limit),
+            order: Some(types::ChangesetOrder::Newest),
+            ..query
+        };
+        let state = (self, query, HashSet::new(), false);
+
+        stream::unfold(
+            state,
+            move |(changesets, mut query, mut seen, done)| async move {
+                if done {
+                    return None;
+                }
+
+                let page = match changesets.get(query.clone()).await {
+                    Ok(page) => page,
+                    Err(error) => return Some((vec![Err(error)], (changesets, query, seen, true))),
+                };
+
+                // The upper bound of the time window is inclusive, so the changesets created in
+                // the same second as the oldest one are returned again by the next page
+                let is_last_page = page.len() < limit as usize;
+                let oldest = page.iter().map(|c| c.created_at.clone()).min();
+                let new_changesets = page
+                    .into_iter()
+                    .filter(|c| seen.insert(c.id))
+                    .map(Ok)
+                    .collect::<Vec<_>>();
+                // A full page of changesets already seen, all from the same second, would be
+                // returned forever, so the window moves past that second
+                let oldest = match oldest {
+                    Some(oldest) if new_changesets.is_empty() => {
+                        dates::parse_timestamp(&oldest).map(|t| dates::format_timestamp(t - 1))
+                    }
+                    oldest => oldest,
+                };
+                let done = is_last_page || oldest.is_none();
+
+                // A server ignoring the time window returns the same page whatever the bound
+                if !done && new_changesets.is_empty() && oldest == query.created_before {
+                    let error = Err(OpenstreetmapError::PagingStalled);
+
+                    return Some((vec![error], (changesets, query, seen, true)));
+                }
+
+                query.created_before = oldest;
+
+                Some((new_changesets, (changesets, query, seen, done)))
+            },
+        )
+        .flat_map(stream::iter)
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
@@ -134,6 +214,8 @@ mod tests {
             open: Some(true),
             closed: Some(false),
             changeset_ids: Some(vec![1, 2, 3]),
+            limit: Some(10),
+            order: Some(types::ChangesetOrder::Oldest),
         };
 
         // WHEN
@@ -151,11 +233,38 @@ mod tests {
             open: Some(true),
             closed: Some(false),
             changesets: Some(vec![1, 2, 3]),
+            limit: Some(10),
+            order: Some(types::ChangesetOrder::Oldest),
         };
 
         assert_eq!(raw_query, expected);
     }
 
+    #[test]
+    fn test_query_raw_from_created_before() {
+        /*
+        GIVEN a Query with only the upper bound of the time window
+        WHEN building
+        THEN the lower bound of the time window is the epoch
+        */
+        // GIVEN
+        let query = types::ChangesetQueryParams {
+            created_before: Some("2020-11-09T22:51:17Z".into()),
+            ..Default::default()
+        };
+
+        // WHEN
+        let raw_query: RawQuery = query.into();
+
+        // THEN
+        let expected = Some(vec![
+            "1970-01-01T00:00:00Z".to_string(),
+            "2020-11-09T22:51:17Z".to_string(),
+        ]);
+
+        assert_eq!(raw_query.time, expected);
+    }
+
     #[test]
     fn test_raw_query_url_encode() {
         /*
@@ -175,6 +284,8 @@ mod tests {
             open: Some(true),
             closed: Some(false),
             changesets: Some(vec![1, 2, 3]),
+            limit: Some(10),
+            order: Some(types::ChangesetOrder::Oldest),
         };
 
         // WHEN
@@ -189,6 +300,8 @@ mod tests {
             "open=true",
             "closed=false",
             "changesets=1%2C2%2C3",
+            "limit=10",
+            "order=oldest",
         ]
         .join("&");
 
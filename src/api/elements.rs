@@ -1,3 +1,5 @@
+use super::batch;
+use crate::diff;
 use crate::types;
 use crate::Openstreetmap;
 use crate::OpenstreetmapError;
@@ -5,15 +7,15 @@ use crate::RequestOptions;
 
 use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::marker::PhantomData;
 
-pub trait OpenstreetmapNode {
+pub trait OpenstreetmapNode: types::ElementVersion {
     fn base_url() -> &'static str;
     fn base_url_plural() -> &'static str;
     fn element_name() -> &'static str;
     fn element_name_plural() -> &'static str;
-    fn id(&self) -> u64;
 }
 
 impl OpenstreetmapNode for types::Node {
@@ -36,11 +38,6 @@ impl OpenstreetmapNode for types::Node {
     fn element_name_plural() -> &'static str {
         "nodes"
     }
-
-    #[inline]
-    fn id(&self) -> u64 {
-        self.id
-    }
 }
 
 impl OpenstreetmapNode for types::Way {
@@ -63,11 +60,6 @@ impl OpenstreetmapNode for types::Way {
     fn element_name_plural() -> &'static str {
         "ways"
     }
-
-    #[inline]
-    fn id(&self) -> u64 {
-        self.id
-    }
 }
 
 impl OpenstreetmapNode for types::Relation {
@@ -90,11 +82,6 @@ impl OpenstreetmapNode for types::Relation {
     fn element_name_plural() -> &'static str {
         "relations"
     }
-
-    #[inline]
-    fn id(&self) -> u64 {
-        self.id
-    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -411,7 +398,8 @@ where
                     }
                 }
 
-                let elements = value.ok_or_else(|| serde::de::Error::missing_field("element"))?;
+                // An element used nowhere has an empty list of ways or relations
+                let elements = value.unwrap_or_default();
 
                 Ok(OsmList { elements })
             }
@@ -444,14 +432,14 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         }
     }
 
-    pub async fn create(&self, element: E) -> Result<u64, OpenstreetmapError> {
+    pub async fn create(&self, element: E) -> Result<i64, OpenstreetmapError> {
         let url = format!("{}create", E::base_url());
         let body = types::RequestBody::Xml(OsmSingle::new(element));
 
         let element_id = self
             .client
-            .request::<OsmSingle<E>, u64>(
-                reqwest::Method::PUT,
+            .request::<OsmSingle<E>, i64>(
+                http::Method::PUT,
                 &url,
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -461,12 +449,12 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         Ok(element_id)
     }
 
-    pub async fn get(&self, element_id: u64) -> Result<E, OpenstreetmapError> {
+    pub async fn get(&self, element_id: i64) -> Result<E, OpenstreetmapError> {
         let url = format!("{}{}", E::base_url(), element_id);
         let element = self
             .client
             .request::<u64, OsmSingle<E>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -484,7 +472,7 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         let version = self
             .client
             .request::<OsmSingle<E>, u64>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 &url,
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -501,7 +489,7 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         let version = self
             .client
             .request::<OsmSingle<E>, u64>(
-                reqwest::Method::DELETE,
+                http::Method::DELETE,
                 &url,
                 body,
                 RequestOptions::new().with_version().with_auth(),
@@ -511,12 +499,12 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         Ok(version)
     }
 
-    pub async fn history(&self, element_id: u64) -> Result<Vec<E>, OpenstreetmapError> {
+    pub async fn history(&self, element_id: i64) -> Result<Vec<E>, OpenstreetmapError> {
         let url = format!("{}{}/history", E::base_url(), element_id);
         let elements = self
             .client
             .request::<u64, OsmList<E>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -527,15 +515,15 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         Ok(elements)
     }
 
-    pub async fn version(&self, element_id: u64, version_id: u64) -> Result<E, OpenstreetmapError> {
+    pub async fn version(&self, element_id: i64, version_id: u64) -> Result<E, OpenstreetmapError> {
         let url = format!("{}{}/{}", E::base_url(), element_id, version_id);
         let element = self
             .client
             .request::<u64, OsmSingle<E>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
-                RequestOptions::new().with_version(),
+                RequestOptions::new().with_version().with_immutable(),
             )
             .await?
             .element;
@@ -546,6 +534,89 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
     pub async fn multi_get(
         &self,
         element_id_params: Vec<types::ElementIdParam>,
+    ) -> Result<Vec<E>, OpenstreetmapError> {
+        let result = self.multi_get_partial(element_id_params).await?;
+
+        if !result.missing.is_empty() {
+            return Err(OpenstreetmapError::NotFound);
+        }
+
+        Ok(result.found)
+    }
+
+    /// Fetches the elements in batches which fit into the client's maximum URL length and
+    /// returns them in request order, reporting the ids the server could not find. An element
+    /// requested both with and without its latest version is returned once.
+    pub async fn multi_get_partial(
+        &self,
+        element_id_params: Vec<types::ElementIdParam>,
+    ) -> Result<types::MultiGetResult<E, types::ElementIdParam>, OpenstreetmapError> {
+        let mut seen = HashSet::new();
+        let requested = element_id_params
+            .into_iter()
+            .filter(|param| seen.insert(*param))
+            .collect::<Vec<types::ElementIdParam>>();
+
+        let prefix = format!("{}?{}=", E::base_url_plural(), E::element_name_plural());
+        let prefix_length = self
+            .client
+            .url(&prefix, &RequestOptions::new().with_version())?
+            .as_str()
+            .len();
+        let chunks = batch::chunk_ids(
+            &requested,
+            self.client.max_url_length.saturating_sub(prefix_length),
+        );
+
+        let elements =
+            batch::fetch_chunks(&self.client, chunks, |chunk| self.inner_multi_get(chunk)).await?;
+
+        let mut latest_versions: HashMap<i64, u64> = HashMap::new();
+        let mut by_version: HashMap<(i64, u64), E> = HashMap::new();
+
+        for element in elements {
+            let (id, version) = (element.id(), element.version());
+
+            latest_versions
+                .entry(id)
+                .and_modify(|latest| *latest = (*latest).max(version))
+                .or_insert(version);
+            by_version.insert((id, version), element);
+        }
+
+        let mut result = types::MultiGetResult {
+            found: vec![],
+            missing: vec![],
+        };
+
+        let mut returned = HashSet::new();
+
+        for param in requested {
+            let key = param
+                .version
+                .or_else(|| latest_versions.get(&param.id).copied())
+                .map(|version| (param.id, version));
+
+            // `1234` and `1234v2` are the same element when 2 is the latest version
+            if key.is_some_and(|key| returned.contains(&key)) {
+                continue;
+            }
+
+            match key.and_then(|key| by_version.remove(&key).map(|element| (key, element))) {
+                Some((key, element)) => {
+                    returned.insert(key);
+                    result.found.push(element);
+                }
+                None => result.missing.push(param),
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn inner_multi_get(
+        &self,
+        element_id_params: Vec<types::ElementIdParam>,
     ) -> Result<Vec<E>, OpenstreetmapError> {
         let element_id_params_raw = element_id_params
             .iter()
@@ -558,7 +629,7 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
         let elements = self
             .client
             .request::<u64, OsmList<E>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -571,13 +642,13 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
 
     pub async fn relations(
         &self,
-        element_id: u64,
+        element_id: i64,
     ) -> Result<Vec<types::Relation>, OpenstreetmapError> {
         let url = format!("{}{}/relations", E::base_url(), element_id);
         let elements = self
             .client
             .request::<u64, OsmList<types::Relation>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -587,15 +658,23 @@ impl<E: OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
 
         Ok(elements)
     }
+
+    /// Attributes every current tag of the element to the version, changeset and user which
+    /// last set its value
+    pub async fn blame(&self, element_id: i64) -> Result<Vec<diff::TagBlame>, OpenstreetmapError> {
+        let history = self.history(element_id).await?;
+
+        Ok(diff::blame(&history))
+    }
 }
 
 impl Elements<types::Node> {
-    pub async fn ways(&self, node_id: u64) -> Result<Vec<types::Way>, OpenstreetmapError> {
+    pub async fn ways(&self, node_id: i64) -> Result<Vec<types::Way>, OpenstreetmapError> {
         let url = format!("node/{node_id}/ways");
         let elements = self
             .client
             .request::<u64, OsmList<types::Way>>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -608,12 +687,12 @@ impl Elements<types::Node> {
 }
 
 impl Elements<types::Way> {
-    pub async fn full(&self, way_id: u64) -> Result<types::WayFull, OpenstreetmapError> {
+    pub async fn full(&self, way_id: i64) -> Result<types::WayFull, OpenstreetmapError> {
         let url = format!("way/{way_id}/full");
         let full = self
             .client
             .request::<u64, types::WayFull>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -625,12 +704,12 @@ impl Elements<types::Way> {
 }
 
 impl Elements<types::Relation> {
-    pub async fn full(&self, relation_id: u64) -> Result<types::RelationFull, OpenstreetmapError> {
+    pub async fn full(&self, relation_id: i64) -> Result<types::RelationFull, OpenstreetmapError> {
         let url = format!("relation/{relation_id}/full");
         let full = self
             .client
             .request::<u64, types::RelationFull>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -642,6 +721,7 @@ impl Elements<types::Relation> {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
     use pretty_assertions::assert_eq;
@@ -3,6 +3,16 @@ use crate::Openstreetmap;
 use crate::OpenstreetmapError;
 use crate::RequestOptions;
 
+use futures::stream::{self, Stream, StreamExt};
+use http::StatusCode;
+use std::collections::HashSet;
+
+// Tiles smaller than this (in degrees) are not split any further
+const MINIMUM_TILE_SIZE: f64 = 0.0001;
+
+// Messages of the 400 responses for a bounding box which is too large or has too many nodes
+const TOO_LARGE_MESSAGES: [&str; 2] = ["too large", "too many nodes"];
+
 #[derive(Debug, Deserialize)]
 struct Bounds {
     #[serde(rename = "@minlat")]
@@ -48,6 +58,103 @@ impl From<Osm> for types::Map {
     }
 }
 
+fn split_bbox(bbox: &types::BoundingBox) -> Vec<types::BoundingBox> {
+    let middle_lon = (bbox.left + bbox.right) / 2.0;
+    let middle_lat = (bbox.bottom + bbox.top) / 2.0;
+
+    vec![
+        types::BoundingBox {
+            left: bbox.left,
+            bottom: bbox.bottom,
+            right: middle_lon,
+            top: middle_lat,
+        },
+        types::BoundingBox {
+            left: middle_lon,
+            bottom: bbox.bottom,
+            right: bbox.right,
+            top: middle_lat,
+        },
+        types::BoundingBox {
+            left: bbox.left,
+            bottom: middle_lat,
+            right: middle_lon,
+            top: bbox.top,
+        },
+        types::BoundingBox {
+            left: middle_lon,
+            bottom: middle_lat,
+            right: bbox.right,
+            top: bbox.top,
+        },
+    ]
+}
+
+/// Splits the bounding box into a grid of tiles no larger than the maximum area
+fn grid_bbox(bbox: &types::BoundingBox, maximum_area: f64) -> Vec<types::BoundingBox> {
+    let width = bbox.right - bbox.left;
+    let height = bbox.top - bbox.bottom;
+
+    if maximum_area <= 0.0 || width * height <= maximum_area {
+        return vec![*bbox];
+    }
+
+    let side = maximum_area.sqrt();
+    let columns = (width / side).ceil().max(1.0) as usize;
+    let rows = (height / side).ceil().max(1.0) as usize;
+    let lon = |column: usize| match column {
+        column if column == columns => bbox.right,
+        column => bbox.left + width * column as f64 / columns as f64,
+    };
+    let lat = |row: usize| match row {
+        row if row == rows => bbox.top,
+        row => bbox.bottom + height * row as f64 / rows as f64,
+    };
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).map(move |column| types::BoundingBox {
+                left: lon(column),
+                bottom: lat(row),
+                right: lon(column + 1),
+                top: lat(row + 1),
+            })
+        })
+        .collect()
+}
+
+fn is_too_large(error: &str) -> bool {
+    TOO_LARGE_MESSAGES
+        .iter()
+        .any(|message| error.contains(message))
+}
+
+fn merge_tiles(bbox: &types::BoundingBox, tiles: Vec<types::Map>) -> types::Map {
+    let mut node_ids = HashSet::new();
+    let mut way_ids = HashSet::new();
+    let mut relation_ids = HashSet::new();
+    let mut map = types::Map {
+        bounds: *bbox,
+        nodes: vec![],
+        ways: vec![],
+        relations: vec![],
+    };
+
+    for tile in tiles {
+        map.nodes
+            .extend(tile.nodes.into_iter().filter(|n| node_ids.insert(n.id)));
+        map.ways
+            .extend(tile.ways.into_iter().filter(|w| way_ids.insert(w.id)));
+        map.relations.extend(
+            tile.relations
+                .into_iter()
+                .filter(|r| relation_ids.insert(r.id)),
+        );
+    }
+
+    map
+}
+
 pub struct Map {
     client: Openstreetmap,
 }
@@ -67,7 +174,7 @@ impl Map {
         let map = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -77,4 +184,74 @@ impl Map {
 
         Ok(map)
     }
+
+    pub async fn get_stream(
+        &self,
+        bbox: &types::BoundingBox,
+    ) -> Result<
+        (
+            Option<types::BoundingBox>,
+            impl Stream<Item = Result<types::Element, OpenstreetmapError>>,
+        ),
+        OpenstreetmapError,
+    > {
+        let url = format!(
+            "map?bbox={},{},{},{}",
+            bbox.left, bbox.bottom, bbox.right, bbox.top
+        );
+        let mut reader = self
+            .client
+            .request_stream(
+                http::Method::GET,
+                &url,
+                RequestOptions::new().with_version(),
+            )
+            .await?;
+
+        // The <bounds> come before the elements, so they are known once the first element is read
+        let first = reader.next_element().await.transpose()?;
+        let bounds = reader.bounds();
+        let elements = stream::iter(first.map(Ok)).chain(reader.into_stream());
+
+        Ok((bounds, elements))
+    }
+
+    /// Downloads the bounding box as tiles no larger than the maximum area of the server's
+    /// capabilities, splitting a tile into quadrants when the server rejects it for having too
+    /// many nodes; elements appearing in more than one tile are returned once.
+    pub async fn get_large(
+        &self,
+        bbox: &types::BoundingBox,
+    ) -> Result<types::Map, OpenstreetmapError> {
+        let maximum_area = self.client.capabilities().await?.capabilities.maximum_area;
+        let mut tiles = vec![];
+        let mut pending = grid_bbox(bbox, maximum_area);
+
+        while !pending.is_empty() {
+            let results = stream::iter(std::mem::take(&mut pending))
+                .map(|tile| async move { (tile, self.get(&tile).await) })
+                .buffered(self.client.concurrency)
+                .collect::<Vec<_>>()
+                .await;
+
+            for (tile, result) in results {
+                match result {
+                    Ok(map) => tiles.push(map),
+                    Err(OpenstreetmapError::Client {
+                        code: StatusCode::BAD_REQUEST,
+                        error,
+                    }) if is_too_large(&error)
+                        && tile.right - tile.left > MINIMUM_TILE_SIZE
+                        && tile.top - tile.bottom > MINIMUM_TILE_SIZE =>
+                    {
+                        debug!("splitting tile {:?}", tile);
+                        pending.extend(split_bbox(&tile));
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        Ok(merge_tiles(bbox, tiles))
+    }
 }
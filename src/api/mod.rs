@@ -1,3 +1,4 @@
+mod batch;
 pub mod capabilities;
 pub mod changeset;
 pub mod changesets;
@@ -5,5 +6,6 @@ pub mod elements;
 pub mod map;
 pub mod notes;
 pub mod permissions;
+mod revert;
 pub mod user;
 pub mod versions;
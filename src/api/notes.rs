@@ -1,10 +1,16 @@
+use crate::dates;
 use crate::errors::OpenstreetmapError;
 use crate::types;
 use crate::Openstreetmap;
 use crate::RequestOptions;
 
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::HashSet;
 use urlencoding::encode;
 
+// Number of notes the server returns when no limit is given
+const DEFAULT_LIMIT: u16 = 100;
+
 #[derive(Debug, Default, PartialEq, Eq, Deserialize)]
 pub struct CommentsRaw {
     #[serde(default, rename = "comment")]
@@ -23,7 +29,7 @@ pub struct NoteRaw {
     pub close_url: String,
     #[serde(rename = "date_created")]
     pub created_at: String,
-    pub status: String,
+    pub status: types::NoteStatus,
     pub comments: CommentsRaw,
 }
 
@@ -55,6 +61,58 @@ struct OsmSingle {
     note: NoteRaw,
 }
 
+// Namespaced elements such as `dc:creator` and `geo:lat` are matched by their local name
+#[derive(Debug, PartialEq, Deserialize)]
+struct FeedItemRaw {
+    pub title: String,
+    pub link: String,
+    pub description: String,
+    #[serde(rename = "creator")]
+    pub creator: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: String,
+    #[serde(rename = "lat")]
+    pub lat: f64,
+    #[serde(rename = "long")]
+    pub lon: f64,
+}
+
+impl From<FeedItemRaw> for types::NoteFeedItem {
+    fn from(value: FeedItemRaw) -> types::NoteFeedItem {
+        // The title starts with the action, e.g. "new comment (near Aberdeen, Scotland)"
+        let action = match value.title.split(" (").next().unwrap_or_default() {
+            "new note" => Some(types::CommentAction::Opened),
+            "new comment" => Some(types::CommentAction::Commented),
+            "closed note" => Some(types::CommentAction::Closed),
+            "reactivated note" => Some(types::CommentAction::Reopened),
+            "hidden note" => Some(types::CommentAction::Hidden),
+            _ => None,
+        };
+
+        types::NoteFeedItem {
+            action,
+            title: value.title,
+            link: value.link,
+            author: value.creator,
+            date: value.pub_date,
+            lat: value.lat,
+            lon: value.lon,
+            description: value.description,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct FeedChannel {
+    #[serde(default, rename = "item")]
+    items: Vec<FeedItemRaw>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Rss {
+    channel: FeedChannel,
+}
+
 pub struct Notes {
     client: Openstreetmap,
 }
@@ -70,7 +128,7 @@ impl Notes {
         &self,
         bbox: &types::BoundingBox,
         limit: Option<u16>,
-        closed: Option<i64>,
+        closed: Option<types::NoteClosedOption>,
     ) -> Result<Vec<types::Note>, OpenstreetmapError> {
         let mut url = format!(
             "notes?bbox={},{},{},{}",
@@ -82,13 +140,13 @@ impl Notes {
         }
 
         if let Some(closed_value) = closed {
-            url = format!("{url}&closed={closed_value}");
+            url = format!("{url}&closed={}", closed_value.as_days());
         }
 
         let notes = self
             .client
             .request::<(), OsmList>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -108,7 +166,7 @@ impl Notes {
         let note = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -133,7 +191,7 @@ impl Notes {
         let note = self
             .client
             .request::<types::NoteContent, OsmSingle>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -156,7 +214,7 @@ impl Notes {
         let note = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -175,7 +233,7 @@ impl Notes {
         let note = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -198,7 +256,7 @@ impl Notes {
         let note = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::POST,
+                http::Method::POST,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -220,7 +278,7 @@ impl Notes {
         let notes = self
             .client
             .request::<(), OsmList>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -234,30 +292,127 @@ impl Notes {
         Ok(notes)
     }
 
+    /// Pages through all the notes matching the search, oldest first, by moving the start
+    /// of the `from`/`to` date window forward. The `order` of the options is ignored. When
+    /// more notes than a page holds share the same date, the ones not returned by the server
+    /// are skipped. The stream ends with `OpenstreetmapError::PagingStalled` when the server
+    /// keeps returning the same notes whatever the date window.
+    pub fn search_stream(
+        self,
+        search_options: types::NoteSearchOptions,
+    ) -> impl Stream<Item = Result<types::Note, OpenstreetmapError>> {
+        let limit = search_options.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+        let sort = search_options
+            .sort
+            .unwrap_or(types::NoteSearchSortOption::CreatedAt);
+        let search_options = types::NoteSearchOptions {
+            limit: Some(limit),
+            sort: Some(sort),
+            order: Some(types::NoteSearchOrderOption::Oldest),
+            ..search_options
+        };
+        let state = (self, search_options, HashSet::new(), false);
+
+        stream::unfold(
+            state,
+            move |(notes, mut options, mut seen, done)| async move {
+                if done {
+                    return None;
+                }
+
+                let page = match notes.search(&options).await {
+                    Ok(page) => page,
+                    Err(error) => return Some((vec![Err(error)], (notes, options, seen, true))),
+                };
+
+                // The date window is inclusive, so the notes sharing the newest date of the page
+                // are returned again by the next one
+                let is_last_page = page.len() < limit as usize;
+                let newest = page.iter().map(|note| sort_date(note, sort)).max();
+                let new_notes = page
+                    .into_iter()
+                    .filter(|note| seen.insert(note.id))
+                    .map(Ok)
+                    .collect::<Vec<_>>();
+                // A full page of notes already seen, all from the same second, would be
+                // returned forever, so the window moves past that second
+                let newest = match newest {
+                    Some(newest) if new_notes.is_empty() => {
+                        dates::parse_timestamp(&newest).map(|t| dates::format_timestamp(t + 1))
+                    }
+                    newest => newest,
+                };
+                let done = is_last_page || newest.is_none();
+
+                // A server ignoring the date window returns the same page whatever the start
+                if !done && new_notes.is_empty() && newest == options.from {
+                    let error = Err(OpenstreetmapError::PagingStalled);
+
+                    return Some((vec![error], (notes, options, seen, true)));
+                }
+
+                options.from = newest;
+
+                Some((new_notes, (notes, options, seen, done)))
+            },
+        )
+        .flat_map(stream::iter)
+    }
+
+    /// like `get_by_bounding_box()` but pages through all the notes in the bounding box
+    pub fn get_by_bounding_box_stream(
+        self,
+        bbox: &types::BoundingBox,
+        closed: Option<types::NoteClosedOption>,
+    ) -> impl Stream<Item = Result<types::Note, OpenstreetmapError>> {
+        let search_options = types::NoteSearchOptions {
+            bbox: Some(*bbox),
+            closed,
+            ..Default::default()
+        };
+
+        self.search_stream(search_options)
+    }
+
     pub async fn feed_by_bounding_box(
         &self,
         bbox: &types::BoundingBox,
-    ) -> Result<Vec<types::Note>, OpenstreetmapError> {
+    ) -> Result<Vec<types::NoteFeedItem>, OpenstreetmapError> {
         let url = format!(
             "notes/feed?bbox={},{},{},{}",
             bbox.left, bbox.bottom, bbox.right, bbox.top
         );
 
-        let notes = self
+        let items = self
             .client
-            .request::<(), OsmList>(
-                reqwest::Method::GET,
+            .request::<(), Rss>(
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
             )
             .await?
-            .notes
+            .channel
+            .items
             .into_iter()
-            .map(|n| n.into())
+            .map(|i| i.into())
             .collect();
 
-        Ok(notes)
+        Ok(items)
+    }
+}
+
+// The date a note is sorted by; a note is updated every time it gets a comment
+fn sort_date(note: &types::Note, sort: types::NoteSearchSortOption) -> String {
+    match sort {
+        types::NoteSearchSortOption::CreatedAt => note.created_at.clone(),
+        types::NoteSearchSortOption::UpdatedAt => note
+            .comments
+            .iter()
+            .map(|c| c.date.clone())
+            .chain(std::iter::once(note.created_at.clone()))
+            .max()
+            .unwrap_or_default(),
     }
 }
 
@@ -299,17 +454,56 @@ mod test {
                 comment_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16659/comment".into(),
                 close_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16659/close".into(),
                 created_at: "2019-06-15 08:26:04 UTC".into(),
-                status: "open".into(),
+                status: types::NoteStatus::Open,
                 comments: vec![types::Comment {
-                    id: 1234,
+                    id: Some(1234),
                     date: "2019-06-15 08:26:04 UTC".into(),
-                    user: "userName".into(),
-                    user_url: "https://master.apis.dev.openstreetmap.org/user/userName".into(),
-                    action: "opened".into(),
+                    user: Some("userName".into()),
+                    user_url: Some("https://master.apis.dev.openstreetmap.org/user/userName".into()),
+                    action: types::CommentAction::Opened,
                     text: "ThisIsANote".into(),
                     html: "<p>ThisIsANote</p>".into()
                 }]
             }
+        ),
+        case(
+            r#"
+            <note lon="0.1000000" lat="51.0000000">
+                <id>16660</id>
+                <url>https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660</url>
+                <comment_url>https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660/comment</comment_url>
+                <close_url>https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660/close</close_url>
+                <date_created>2019-06-15 08:26:04 UTC</date_created>
+                <status>closed</status>
+                <comments>
+                    <comment>
+                        <date>2019-06-15 08:26:04 UTC</date>
+                        <action>closed</action>
+                        <text>Fixed</text>
+                        <html>&lt;p&gt;Fixed&lt;/p&gt;</html>
+                    </comment>
+                </comments>
+            </note>
+            "#,
+            types::Note {
+                id: 16660,
+                lat: 51.0000000,
+                lon: 0.1000000,
+                url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660".into(),
+                comment_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660/comment".into(),
+                close_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16660/close".into(),
+                created_at: "2019-06-15 08:26:04 UTC".into(),
+                status: types::NoteStatus::Closed,
+                comments: vec![types::Comment {
+                    id: None,
+                    date: "2019-06-15 08:26:04 UTC".into(),
+                    user: None,
+                    user_url: None,
+                    action: types::CommentAction::Closed,
+                    text: "Fixed".into(),
+                    html: "<p>Fixed</p>".into()
+                }]
+            }
         )
     )]
     fn test_note_raw_deserialise_into_note(data: &str, expected: types::Note) {
@@ -30,7 +30,7 @@ impl Permissions {
         let permissions = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 "permissions",
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
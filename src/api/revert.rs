@@ -0,0 +1,418 @@
+use crate::types::{self, ElementVersion};
+
+use std::collections::{HashMap, HashSet};
+
+/// An element written by the reverted changeset, with the first and last version it wrote
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Touched {
+    pub element_type: types::ElementType,
+    pub id: i64,
+    pub first_version: u64,
+    pub last_version: u64,
+}
+
+/// Lists the elements written by the changeset in document order, an element written more
+/// than once being listed at its first occurrence.
+pub(crate) fn touched_elements(changes: &types::ChangesetChanges) -> Vec<Touched> {
+    let mut touched: Vec<Touched> = vec![];
+    let mut positions: HashMap<(types::ElementType, i64), usize> = HashMap::new();
+
+    let creations = changes
+        .creations
+        .iter()
+        .flat_map(|c| block_versions(&c.nodes, &c.ways, &c.relations));
+    let modifications = changes
+        .modifications
+        .iter()
+        .flat_map(|m| block_versions(&m.nodes, &m.ways, &m.relations));
+    let deletions = changes
+        .deletions
+        .iter()
+        .flat_map(|d| block_versions(&d.nodes, &d.ways, &d.relations));
+
+    for (element_type, id, version) in creations.chain(modifications).chain(deletions) {
+        match positions.get(&(element_type, id)) {
+            Some(&position) => {
+                let element = &mut touched[position];
+                element.first_version = element.first_version.min(version);
+                element.last_version = element.last_version.max(version);
+            }
+            None => {
+                positions.insert((element_type, id), touched.len());
+                touched.push(Touched {
+                    element_type,
+                    id,
+                    first_version: version,
+                    last_version: version,
+                });
+            }
+        }
+    }
+
+    touched
+}
+
+fn block_versions<'a>(
+    nodes: &'a [types::Node],
+    ways: &'a [types::Way],
+    relations: &'a [types::Relation],
+) -> impl Iterator<Item = (types::ElementType, i64, u64)> + 'a {
+    let nodes = nodes
+        .iter()
+        .map(|n| (types::ElementType::Node, n.id, n.version));
+    let ways = ways
+        .iter()
+        .map(|w| (types::ElementType::Way, w.id, w.version));
+    let relations = relations
+        .iter()
+        .map(|r| (types::ElementType::Relation, r.id, r.version));
+
+    nodes.chain(ways).chain(relations)
+}
+
+/// Computes the changes undoing the touched elements from their full histories. Elements
+/// edited after the reverted changeset, or whose previous version is not available, are
+/// reported as conflicts and left alone, and so are the elements they still reference, which
+/// would otherwise be deleted from under them. Likewise, a created element is left alone when
+/// one of its current `referrers`, the ways and relations using it, is not reverted.
+pub(crate) fn plan(
+    touched: Vec<Touched>,
+    mut histories: HashMap<(types::ElementType, i64), Vec<types::Element>>,
+    referrers: HashMap<(types::ElementType, i64), Vec<(types::ElementType, i64)>>,
+    target_changeset_id: u64,
+) -> types::RevertPlan {
+    let reverted = touched
+        .iter()
+        .map(|element| (element.element_type, element.id))
+        .collect::<HashSet<_>>();
+    let mut restored = vec![];
+    let mut deleted = vec![];
+    let mut conflicts = vec![];
+    let mut kept = vec![];
+
+    for element in touched {
+        let mut history = histories
+            .remove(&(element.element_type, element.id))
+            .unwrap_or_default();
+        history.sort_by_key(|e| e.version());
+
+        let current = history.pop();
+        let current_version = current.as_ref().map(|e| e.version()).unwrap_or_default();
+
+        if current_version != element.last_version {
+            conflicts.push(conflict(&element, current_version));
+            kept.extend(current);
+            continue;
+        }
+
+        let current = current.filter(|e| e.visible());
+
+        // The element did not exist before the changeset, so it has to be deleted, unless a
+        // later edit started using it
+        if element.first_version == 1 {
+            let is_used = referrers
+                .get(&(element.element_type, element.id))
+                .is_some_and(|referrers| referrers.iter().any(|r| !reverted.contains(r)));
+
+            if is_used {
+                conflicts.push(conflict(&element, current_version));
+                kept.extend(current);
+            } else {
+                deleted.extend(current);
+            }
+
+            continue;
+        }
+
+        let previous = history
+            .into_iter()
+            .find(|e| e.version() == element.first_version - 1);
+
+        match previous {
+            // Restoring a deleted element is a modification of its latest version
+            Some(mut previous) if previous.visible() => {
+                previous.set_version(current_version);
+                restored.push(previous);
+            }
+            // The changeset undeleted the element
+            Some(_) => deleted.extend(current),
+            None => {
+                conflicts.push(conflict(&element, current_version));
+                kept.extend(current);
+            }
+        }
+    }
+
+    // An element left alone, or restored, keeps referencing its nodes and members, so they
+    // are left alone too, and in turn their own nodes and members
+    let mut pending = kept
+        .iter()
+        .chain(restored.iter())
+        .flat_map(references)
+        .collect::<Vec<_>>();
+
+    while let Some(reference) = pending.pop() {
+        let Some(position) = deleted
+            .iter()
+            .position(|e| (e.element_type(), e.id()) == reference)
+        else {
+            continue;
+        };
+        let element = deleted.remove(position);
+
+        conflicts.push(types::RevertConflict {
+            element_type: reference.0,
+            id: reference.1,
+            version: element.version(),
+            current_version: element.version(),
+        });
+        pending.extend(references(&element));
+    }
+
+    for element in restored.iter_mut().chain(deleted.iter_mut()) {
+        element.set_changeset(target_changeset_id);
+    }
+
+    types::RevertPlan {
+        changes: changes(restored, deleted),
+        conflicts,
+    }
+}
+
+fn conflict(element: &Touched, current_version: u64) -> types::RevertConflict {
+    types::RevertConflict {
+        element_type: element.element_type,
+        id: element.id,
+        version: element.last_version,
+        current_version,
+    }
+}
+
+// The restored nodes come before the ways and relations which may reference them again,
+// while the deleted elements go in the opposite order so nothing still references them.
+fn changes(restored: Vec<types::Element>, deleted: Vec<types::Element>) -> types::ChangesetChanges {
+    let mut modification = types::Modification {
+        nodes: vec![],
+        ways: vec![],
+        relations: vec![],
+    };
+
+    for element in restored {
+        match element {
+            types::Element::Node(node) => modification.nodes.push(node),
+            types::Element::Way(way) => modification.ways.push(way),
+            types::Element::Relation(relation) => modification.relations.push(relation),
+        }
+    }
+
+    let mut deleted_relations = vec![];
+    let mut deleted_ways = vec![];
+    let mut deleted_nodes = vec![];
+
+    for element in deleted {
+        match element {
+            types::Element::Node(node) => deleted_nodes.push(node),
+            types::Element::Way(way) => deleted_ways.push(way),
+            types::Element::Relation(relation) => deleted_relations.push(relation),
+        }
+    }
+
+    let deletions = [
+        types::Deletion {
+            if_unused: false,
+            nodes: vec![],
+            ways: vec![],
+            relations: deleted_relations,
+        },
+        types::Deletion {
+            if_unused: false,
+            nodes: vec![],
+            ways: deleted_ways,
+            relations: vec![],
+        },
+        types::Deletion {
+            if_unused: false,
+            nodes: deleted_nodes,
+            ways: vec![],
+            relations: vec![],
+        },
+    ];
+
+    types::ChangesetChanges {
+        modifications: [modification]
+            .into_iter()
+            .filter(|m| !(m.nodes.is_empty() && m.ways.is_empty() && m.relations.is_empty()))
+            .collect(),
+        creations: vec![],
+        deletions: deletions
+            .into_iter()
+            .filter(|d| !(d.nodes.is_empty() && d.ways.is_empty() && d.relations.is_empty()))
+            .collect(),
+    }
+}
+
+fn references(element: &types::Element) -> Vec<(types::ElementType, i64)> {
+    match element {
+        types::Element::Node(_) => vec![],
+        types::Element::Way(way) => way
+            .node_refs
+            .iter()
+            .map(|n| (types::ElementType::Node, n.node_id))
+            .collect(),
+        types::Element::Relation(relation) => relation
+            .members
+            .iter()
+            .filter_map(|m| Some((m.element_type()?, m.node_id)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn node(id: i64, version: u64, visible: bool, changeset: u64) -> types::Node {
+        types::Node {
+            id,
+            visible,
+            version,
+            changeset,
+            timestamp: "2020-12-09T22:51:17Z".into(),
+            user: None,
+            uid: None,
+            lat: visible.then_some(1.0),
+            lon: visible.then_some(2.0),
+            tags: vec![types::Tag::new("version", &version.to_string())],
+        }
+    }
+
+    fn changes(
+        created: Vec<types::Node>,
+        modified: Vec<types::Node>,
+        deleted: Vec<types::Node>,
+    ) -> types::ChangesetChanges {
+        types::ChangesetChanges {
+            modifications: vec![types::Modification {
+                nodes: modified,
+                ways: vec![],
+                relations: vec![],
+            }],
+            creations: vec![types::Creation {
+                nodes: created,
+                ways: vec![],
+                relations: vec![],
+            }],
+            deletions: vec![types::Deletion {
+                if_unused: false,
+                nodes: deleted,
+                ways: vec![],
+                relations: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_touched_elements() {
+        /*
+        GIVEN a changeset creating an element and modifying it afterwards
+        WHEN listing the touched elements
+        THEN the element is listed once with the first and last version written
+        */
+        // GIVEN
+        let changes = changes(
+            vec![node(1, 1, true, 10)],
+            vec![node(1, 2, true, 10), node(2, 5, true, 10)],
+            vec![],
+        );
+
+        // WHEN
+        let actual = touched_elements(&changes);
+
+        // THEN
+        let touched = |id, first_version, last_version| Touched {
+            element_type: types::ElementType::Node,
+            id,
+            first_version,
+            last_version,
+        };
+
+        assert_eq!(actual, vec![touched(1, 1, 2), touched(2, 5, 5)]);
+    }
+
+    #[test]
+    fn test_plan() {
+        /*
+        GIVEN a changeset creating, modifying and deleting nodes
+            AND a node modified again by a later changeset
+        WHEN planning its revert
+        THEN the created node is deleted
+            AND the modified and deleted nodes are restored with their current version
+            AND the node edited later is reported as a conflict
+        */
+        // GIVEN
+        let changes = changes(
+            vec![node(1, 1, true, 10)],
+            vec![node(2, 3, true, 10), node(4, 2, true, 10)],
+            vec![node(3, 2, false, 10)],
+        );
+        let histories = HashMap::from([
+            (
+                (types::ElementType::Node, 1),
+                vec![types::Element::Node(node(1, 1, true, 10))],
+            ),
+            (
+                (types::ElementType::Node, 2),
+                (1..=3)
+                    .map(|v| types::Element::Node(node(2, v, true, 8 + v)))
+                    .collect(),
+            ),
+            (
+                (types::ElementType::Node, 3),
+                vec![
+                    types::Element::Node(node(3, 1, true, 9)),
+                    types::Element::Node(node(3, 2, false, 10)),
+                ],
+            ),
+            (
+                (types::ElementType::Node, 4),
+                (1..=3)
+                    .map(|v| types::Element::Node(node(4, v, true, 8 + v)))
+                    .collect(),
+            ),
+        ]);
+
+        // WHEN
+        let actual = plan(touched_elements(&changes), histories, HashMap::new(), 20);
+
+        // THEN
+        let restored = |id, from_version, version| types::Node {
+            version,
+            ..node(id, from_version, true, 20)
+        };
+        let expected = types::RevertPlan {
+            changes: types::ChangesetChanges {
+                modifications: vec![types::Modification {
+                    nodes: vec![restored(2, 2, 3), restored(3, 1, 2)],
+                    ways: vec![],
+                    relations: vec![],
+                }],
+                creations: vec![],
+                deletions: vec![types::Deletion {
+                    if_unused: false,
+                    nodes: vec![node(1, 1, true, 20)],
+                    ways: vec![],
+                    relations: vec![],
+                }],
+            },
+            conflicts: vec![types::RevertConflict {
+                element_type: types::ElementType::Node,
+                id: 4,
+                version: 2,
+                current_version: 3,
+            }],
+        };
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,8 +1,11 @@
+use super::batch;
 use crate::errors::OpenstreetmapError;
 use crate::types;
 use crate::Openstreetmap;
 use crate::RequestOptions;
 
+use std::collections::{HashMap, HashSet};
+
 #[derive(Debug, PartialEq, Deserialize)]
 struct OsmSingle {
     pub user: UserRaw,
@@ -174,7 +177,7 @@ impl User {
         let user = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -186,7 +189,66 @@ impl User {
         Ok(user)
     }
 
+    /// Fetches the users like `users_partial`, failing with `NotFound` when any of them is
+    /// missing, as `multi_get` does for elements.
     pub async fn users(&self, user_ids: &[u64]) -> Result<Vec<types::User>, OpenstreetmapError> {
+        let result = self.users_partial(user_ids).await?;
+
+        if !result.missing.is_empty() {
+            return Err(OpenstreetmapError::NotFound);
+        }
+
+        Ok(result.found)
+    }
+
+    /// Fetches the users in batches which fit into the client's maximum URL length and
+    /// returns them in request order, reporting the ids the server did not return.
+    pub async fn users_partial(
+        &self,
+        user_ids: &[u64],
+    ) -> Result<types::MultiGetResult<types::User, u64>, OpenstreetmapError> {
+        let mut seen = HashSet::new();
+        let requested = user_ids
+            .iter()
+            .copied()
+            .filter(|user_id| seen.insert(*user_id))
+            .collect::<Vec<u64>>();
+
+        let prefix_length = self
+            .client
+            .url("users?users=", &RequestOptions::new().with_version())?
+            .as_str()
+            .len();
+        let chunks = batch::chunk_ids(
+            &requested,
+            self.client.max_url_length.saturating_sub(prefix_length),
+        );
+
+        let mut users = batch::fetch_chunks(&self.client, chunks, |chunk| self.inner_users(chunk))
+            .await?
+            .into_iter()
+            .map(|u: types::User| (u.id, u))
+            .collect::<HashMap<u64, types::User>>();
+
+        let mut result = types::MultiGetResult {
+            found: vec![],
+            missing: vec![],
+        };
+
+        for user_id in requested {
+            match users.remove(&user_id) {
+                Some(user) => result.found.push(user),
+                None => result.missing.push(user_id),
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn inner_users(
+        &self,
+        user_ids: Vec<u64>,
+    ) -> Result<Vec<types::User>, OpenstreetmapError> {
         let user_ids_raw = user_ids
             .iter()
             .map(|v| v.to_string())
@@ -198,7 +260,7 @@ impl User {
         let users = self
             .client
             .request::<(), OsmList>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version(),
@@ -216,7 +278,7 @@ impl User {
         let user = self
             .client
             .request::<(), OsmSingle>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 "user/details",
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -232,7 +294,7 @@ impl User {
         let user = self
             .client
             .request::<(), OsmPreferences>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 "user/preferences",
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -252,7 +314,7 @@ impl User {
         // Use Vec<u8> because `serde` cannot deserialise EOF when using Unit;
         self.client
             .request::<OsmPreferences, Vec<u8>>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 "user/preferences",
                 payload,
                 RequestOptions::new().with_version().with_auth(),
@@ -268,7 +330,7 @@ impl User {
         let user = self
             .client
             .request::<(), String>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -288,7 +350,7 @@ impl User {
 
         self.client
             .request::<String, Vec<u8>>(
-                reqwest::Method::PUT,
+                http::Method::PUT,
                 &url,
                 payload,
                 RequestOptions::new().with_version().with_auth(),
@@ -304,7 +366,7 @@ impl User {
         // Use Vec<u8> because `serde` cannot deserialise EOF when using Unit;
         self.client
             .request::<(), Vec<u8>>(
-                reqwest::Method::DELETE,
+                http::Method::DELETE,
                 &url,
                 types::RequestBody::None,
                 RequestOptions::new().with_version().with_auth(),
@@ -316,6 +378,7 @@ impl User {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
     use pretty_assertions::assert_eq;
@@ -29,7 +29,7 @@ impl Versions {
         let versions = self
             .client
             .request::<(), Osm>(
-                reqwest::Method::GET,
+                http::Method::GET,
                 "versions",
                 types::RequestBody::None,
                 RequestOptions::new(),
@@ -0,0 +1,500 @@
+//! A synchronous client mirroring `Openstreetmap`, sending its requests with
+//! `reqwest::blocking`. The endpoints share the request building and response parsing of
+//! the async client: every call drives the same future to completion on the current thread.
+//!
+//! Like `reqwest::blocking`, this client must not be used from within an async runtime.
+
+use crate::api;
+use crate::diff;
+use crate::errors::OpenstreetmapError;
+use crate::transport;
+use crate::types;
+
+use futures::executor::{block_on, block_on_stream};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+#[derive(Debug, Clone)]
+pub struct Openstreetmap {
+    inner: crate::Openstreetmap,
+}
+
+impl Openstreetmap {
+    pub fn new<T>(host: T, credentials: types::Credentials) -> Self
+    where
+        T: Into<String>,
+    {
+        Self::from_client(host, credentials, reqwest::blocking::Client::new())
+    }
+
+    /// creates a new instance of a blocking Openstreetmap client using a specified reqwest
+    /// blocking client
+    pub fn from_client<H>(
+        host: H,
+        credentials: types::Credentials,
+        client: reqwest::blocking::Client,
+    ) -> Self
+    where
+        H: Into<String>,
+    {
+        let transport = transport::BlockingReqwestTransport::new(client);
+
+        Openstreetmap {
+            inner: crate::Openstreetmap::from_transport(host, credentials, transport),
+        }
+    }
+
+    /// sets the maximum URL length used when splitting multi-element fetches into batches
+    pub fn with_max_url_length(mut self, max_url_length: usize) -> Self {
+        self.inner = self.inner.with_max_url_length(max_url_length);
+        self
+    }
+
+    /// sets how many batched requests are run at the same time. Every request blocks the
+    /// thread until its response arrives, so the batches are still sent one after another.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.inner = self.inner.with_concurrency(concurrency);
+        self
+    }
+
+    /// adds a middleware called around every request after the ones already added
+    pub fn with_middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: crate::middleware::Middleware + 'static,
+    {
+        self.inner = self.inner.with_middleware(middleware);
+        self
+    }
+
+    /// returns the metrics of the requests sent by this client and its clones
+    #[cfg(feature = "tracing")]
+    pub fn metrics(&self) -> &crate::metrics::Metrics {
+        self.inner.metrics()
+    }
+
+    #[inline]
+    pub fn versions(&self) -> Result<Vec<String>, OpenstreetmapError> {
+        block_on(self.inner.versions())
+    }
+
+    #[inline]
+    pub fn capabilities(&self) -> Result<types::CapabilitiesAndPolicy, OpenstreetmapError> {
+        block_on(self.inner.capabilities())
+    }
+
+    #[inline]
+    pub fn map(&self, bbox: &types::BoundingBox) -> Result<types::Map, OpenstreetmapError> {
+        block_on(self.inner.map(bbox))
+    }
+
+    #[inline]
+    pub fn map_large(&self, bbox: &types::BoundingBox) -> Result<types::Map, OpenstreetmapError> {
+        block_on(self.inner.map_large(bbox))
+    }
+
+    #[inline]
+    pub fn map_stream<'a>(
+        &'a self,
+        bbox: &'a types::BoundingBox,
+    ) -> Result<
+        (
+            Option<types::BoundingBox>,
+            impl Iterator<Item = Result<types::Element, OpenstreetmapError>> + 'a,
+        ),
+        OpenstreetmapError,
+    > {
+        let (bounds, elements) = block_on(self.inner.map_stream(bbox))?;
+
+        Ok((bounds, block_on_stream(Box::pin(elements))))
+    }
+
+    #[inline]
+    pub fn permissions(&self) -> Result<Vec<types::Permission>, OpenstreetmapError> {
+        block_on(self.inner.permissions())
+    }
+
+    #[inline]
+    pub fn changeset(&self) -> Changeset {
+        Changeset {
+            inner: self.inner.changeset(),
+        }
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> Elements<types::Node> {
+        Elements {
+            inner: self.inner.nodes(),
+        }
+    }
+
+    #[inline]
+    pub fn ways(&self) -> Elements<types::Way> {
+        Elements {
+            inner: self.inner.ways(),
+        }
+    }
+
+    #[inline]
+    pub fn relations(&self) -> Elements<types::Relation> {
+        Elements {
+            inner: self.inner.relations(),
+        }
+    }
+
+    #[inline]
+    pub fn user(&self) -> User {
+        User {
+            inner: self.inner.user(),
+        }
+    }
+
+    #[inline]
+    pub fn notes(&self) -> Notes {
+        Notes {
+            inner: self.inner.notes(),
+        }
+    }
+
+    #[inline]
+    pub fn changesets(
+        &self,
+        query: types::ChangesetQueryParams,
+    ) -> Result<Vec<types::Changeset>, OpenstreetmapError> {
+        block_on(self.inner.changesets(query))
+    }
+
+    #[inline]
+    pub fn changesets_stream(
+        &self,
+        query: types::ChangesetQueryParams,
+    ) -> impl Iterator<Item = Result<types::Changeset, OpenstreetmapError>> {
+        block_on_stream(Box::pin(self.inner.changesets_stream(query)))
+    }
+}
+
+pub struct Changeset {
+    inner: api::changeset::Changeset,
+}
+
+impl Changeset {
+    #[inline]
+    pub fn create(
+        &self,
+        changesets: Vec<types::ChangesetCreate>,
+    ) -> Result<u64, OpenstreetmapError> {
+        block_on(self.inner.create(changesets))
+    }
+
+    #[inline]
+    pub fn update_tags_on_changeset(
+        &self,
+        changeset_id: u64,
+        tags: Vec<types::Tag>,
+    ) -> Result<types::Changeset, OpenstreetmapError> {
+        block_on(self.inner.update_tags_on_changeset(changeset_id, tags))
+    }
+
+    #[inline]
+    pub fn get(&self, changeset_id: u64) -> Result<types::Changeset, OpenstreetmapError> {
+        block_on(self.inner.get(changeset_id))
+    }
+
+    #[inline]
+    pub fn get_with_discussion(
+        &self,
+        changeset_id: u64,
+    ) -> Result<types::Changeset, OpenstreetmapError> {
+        block_on(self.inner.get_with_discussion(changeset_id))
+    }
+
+    #[inline]
+    pub fn close(&self, changeset_id: u64) -> Result<(), OpenstreetmapError> {
+        block_on(self.inner.close(changeset_id))
+    }
+
+    #[inline]
+    pub fn download(
+        &self,
+        changeset_id: u64,
+    ) -> Result<types::ChangesetChanges, OpenstreetmapError> {
+        block_on(self.inner.download(changeset_id))
+    }
+
+    #[inline]
+    pub fn download_stream(
+        &self,
+        changeset_id: u64,
+    ) -> Result<
+        impl Iterator<Item = Result<(types::ChangeAction, types::Element), OpenstreetmapError>> + '_,
+        OpenstreetmapError,
+    > {
+        let changes = block_on(self.inner.download_stream(changeset_id))?;
+
+        Ok(block_on_stream(Box::pin(changes)))
+    }
+
+    #[inline]
+    pub fn upload(
+        &self,
+        changeset_id: u64,
+        changeset_change: types::ChangesetChanges,
+    ) -> Result<types::DiffResult, OpenstreetmapError> {
+        block_on(self.inner.upload(changeset_id, changeset_change))
+    }
+
+    #[inline]
+    pub fn revert_changes(
+        &self,
+        changeset_id: u64,
+        target_changeset_id: u64,
+    ) -> Result<types::RevertPlan, OpenstreetmapError> {
+        block_on(self.inner.revert_changes(changeset_id, target_changeset_id))
+    }
+
+    #[inline]
+    pub fn revert(
+        &self,
+        changeset_id: u64,
+        target_changeset_id: u64,
+    ) -> Result<types::RevertResult, OpenstreetmapError> {
+        block_on(self.inner.revert(changeset_id, target_changeset_id))
+    }
+
+    #[inline]
+    pub fn comment(&self, changeset_id: u64, comment: &str) -> Result<(), OpenstreetmapError> {
+        block_on(self.inner.comment(changeset_id, comment))
+    }
+
+    #[inline]
+    pub fn subscribe(&self, changeset_id: u64) -> Result<types::Changeset, OpenstreetmapError> {
+        block_on(self.inner.subscribe(changeset_id))
+    }
+
+    #[inline]
+    pub fn unsubscribe(&self, changeset_id: u64) -> Result<types::Changeset, OpenstreetmapError> {
+        block_on(self.inner.unsubscribe(changeset_id))
+    }
+}
+
+pub struct Elements<E: api::elements::OpenstreetmapNode + Serialize + DeserializeOwned> {
+    inner: api::elements::Elements<E>,
+}
+
+impl<E: api::elements::OpenstreetmapNode + Serialize + DeserializeOwned> Elements<E> {
+    #[inline]
+    pub fn create(&self, element: E) -> Result<i64, OpenstreetmapError> {
+        block_on(self.inner.create(element))
+    }
+
+    #[inline]
+    pub fn get(&self, element_id: i64) -> Result<E, OpenstreetmapError> {
+        block_on(self.inner.get(element_id))
+    }
+
+    #[inline]
+    pub fn update(&self, element: E) -> Result<u64, OpenstreetmapError> {
+        block_on(self.inner.update(element))
+    }
+
+    #[inline]
+    pub fn delete(&self, element: E) -> Result<u64, OpenstreetmapError> {
+        block_on(self.inner.delete(element))
+    }
+
+    #[inline]
+    pub fn history(&self, element_id: i64) -> Result<Vec<E>, OpenstreetmapError> {
+        block_on(self.inner.history(element_id))
+    }
+
+    #[inline]
+    pub fn version(&self, element_id: i64, version_id: u64) -> Result<E, OpenstreetmapError> {
+        block_on(self.inner.version(element_id, version_id))
+    }
+
+    #[inline]
+    pub fn multi_get(
+        &self,
+        element_id_params: Vec<types::ElementIdParam>,
+    ) -> Result<Vec<E>, OpenstreetmapError> {
+        block_on(self.inner.multi_get(element_id_params))
+    }
+
+    #[inline]
+    pub fn multi_get_partial(
+        &self,
+        element_id_params: Vec<types::ElementIdParam>,
+    ) -> Result<types::MultiGetResult<E, types::ElementIdParam>, OpenstreetmapError> {
+        block_on(self.inner.multi_get_partial(element_id_params))
+    }
+
+    #[inline]
+    pub fn relations(&self, element_id: i64) -> Result<Vec<types::Relation>, OpenstreetmapError> {
+        block_on(self.inner.relations(element_id))
+    }
+
+    #[inline]
+    pub fn blame(&self, element_id: i64) -> Result<Vec<diff::TagBlame>, OpenstreetmapError> {
+        block_on(self.inner.blame(element_id))
+    }
+}
+
+impl Elements<types::Node> {
+    #[inline]
+    pub fn ways(&self, node_id: i64) -> Result<Vec<types::Way>, OpenstreetmapError> {
+        block_on(self.inner.ways(node_id))
+    }
+}
+
+impl Elements<types::Way> {
+    #[inline]
+    pub fn full(&self, way_id: i64) -> Result<types::WayFull, OpenstreetmapError> {
+        block_on(self.inner.full(way_id))
+    }
+}
+
+impl Elements<types::Relation> {
+    #[inline]
+    pub fn full(&self, relation_id: i64) -> Result<types::RelationFull, OpenstreetmapError> {
+        block_on(self.inner.full(relation_id))
+    }
+}
+
+pub struct Notes {
+    inner: api::notes::Notes,
+}
+
+impl Notes {
+    #[inline]
+    pub fn get_by_bounding_box(
+        &self,
+        bbox: &types::BoundingBox,
+        limit: Option<u16>,
+        closed: Option<types::NoteClosedOption>,
+    ) -> Result<Vec<types::Note>, OpenstreetmapError> {
+        block_on(self.inner.get_by_bounding_box(bbox, limit, closed))
+    }
+
+    #[inline]
+    pub fn get(&self, note_id: u64) -> Result<types::Note, OpenstreetmapError> {
+        block_on(self.inner.get(note_id))
+    }
+
+    #[inline]
+    pub fn create(
+        &self,
+        note_content: types::NoteContent,
+    ) -> Result<types::Note, OpenstreetmapError> {
+        block_on(self.inner.create(note_content))
+    }
+
+    #[inline]
+    pub fn create_comment(
+        &self,
+        note_id: u64,
+        text: &str,
+    ) -> Result<types::Note, OpenstreetmapError> {
+        block_on(self.inner.create_comment(note_id, text))
+    }
+
+    #[inline]
+    pub fn close(&self, note_id: u64, text: &str) -> Result<types::Note, OpenstreetmapError> {
+        block_on(self.inner.close(note_id, text))
+    }
+
+    #[inline]
+    pub fn reopen(&self, note_id: u64, text: &str) -> Result<types::Note, OpenstreetmapError> {
+        block_on(self.inner.reopen(note_id, text))
+    }
+
+    #[inline]
+    pub fn search(
+        &self,
+        search_options: &types::NoteSearchOptions,
+    ) -> Result<Vec<types::Note>, OpenstreetmapError> {
+        block_on(self.inner.search(search_options))
+    }
+
+    #[inline]
+    pub fn search_stream(
+        self,
+        search_options: types::NoteSearchOptions,
+    ) -> impl Iterator<Item = Result<types::Note, OpenstreetmapError>> {
+        block_on_stream(Box::pin(self.inner.search_stream(search_options)))
+    }
+
+    #[inline]
+    pub fn get_by_bounding_box_stream(
+        self,
+        bbox: &types::BoundingBox,
+        closed: Option<types::NoteClosedOption>,
+    ) -> impl Iterator<Item = Result<types::Note, OpenstreetmapError>> {
+        block_on_stream(Box::pin(
+            self.inner.get_by_bounding_box_stream(bbox, closed),
+        ))
+    }
+
+    #[inline]
+    pub fn feed_by_bounding_box(
+        &self,
+        bbox: &types::BoundingBox,
+    ) -> Result<Vec<types::NoteFeedItem>, OpenstreetmapError> {
+        block_on(self.inner.feed_by_bounding_box(bbox))
+    }
+}
+
+pub struct User {
+    inner: api::user::User,
+}
+
+impl User {
+    #[inline]
+    pub fn get(&self, user_id: u64) -> Result<types::User, OpenstreetmapError> {
+        block_on(self.inner.get(user_id))
+    }
+
+    #[inline]
+    pub fn users(&self, user_ids: &[u64]) -> Result<Vec<types::User>, OpenstreetmapError> {
+        block_on(self.inner.users(user_ids))
+    }
+
+    #[inline]
+    pub fn users_partial(
+        &self,
+        user_ids: &[u64],
+    ) -> Result<types::MultiGetResult<types::User, u64>, OpenstreetmapError> {
+        block_on(self.inner.users_partial(user_ids))
+    }
+
+    #[inline]
+    pub fn details(&self) -> Result<types::User, OpenstreetmapError> {
+        block_on(self.inner.details())
+    }
+
+    #[inline]
+    pub fn preferences(&self) -> Result<types::UserPreferences, OpenstreetmapError> {
+        block_on(self.inner.preferences())
+    }
+
+    #[inline]
+    pub fn preferences_update(
+        &self,
+        preferences: &types::UserPreferences,
+    ) -> Result<(), OpenstreetmapError> {
+        block_on(self.inner.preferences_update(preferences))
+    }
+
+    #[inline]
+    pub fn preference(&self, key: &str) -> Result<String, OpenstreetmapError> {
+        block_on(self.inner.preference(key))
+    }
+
+    #[inline]
+    pub fn preference_update(&self, key: &str, value: &str) -> Result<(), OpenstreetmapError> {
+        block_on(self.inner.preference_update(key, value))
+    }
+
+    #[inline]
+    pub fn preference_delete(&self, key: &str) -> Result<(), OpenstreetmapError> {
+        block_on(self.inner.preference_delete(key))
+    }
+}
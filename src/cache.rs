@@ -0,0 +1,486 @@
+//! A cache of the API responses keyed by URL, added to a client as a middleware:
+//!
+//! ```no_run
+//! use openstreetmap_api::cache::{Cache, MemoryStore};
+//! use openstreetmap_api::{types, Openstreetmap};
+//!
+//! let client = Openstreetmap::new("https://api.openstreetmap.org", types::Credentials::None)
+//!     .with_middleware(Cache::new(MemoryStore::default()));
+//! ```
+//!
+//! Only the successful responses of `GET` requests without authentication are stored, and not
+//! the ones parsed while they are downloaded, like `Openstreetmap::map_stream()`. A
+//! response is served from the cache while it is fresh according to its `Cache-Control`
+//! `max-age`, then revalidated with `If-None-Match` when it has an `ETag`. The responses of
+//! immutable requests, like historical element versions, never expire.
+//!
+//! Any other request evicts the stored responses of the resource it changes, e.g. closing a
+//! note evicts every cached note. Writing an element or uploading a changeset evicts every
+//! cached response holding elements, like maps and the ways of a node, except for the
+//! historical versions.
+
+use crate::errors::OpenstreetmapError;
+use crate::middleware::{Middleware, Next, Request};
+use crate::transport::HttpResponse;
+
+use futures::future::{self, BoxFuture};
+use http::header::{HeaderMap, HeaderName, CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use http::{Method, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub etag: Option<String>,
+    /// when the response must be revalidated, `None` if it never changes
+    pub expires: Option<SystemTime>,
+    pub body: Vec<u8>,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self) -> bool {
+        self.expires
+            .is_none_or(|expires| SystemTime::now() < expires)
+    }
+}
+
+/// Where the cached responses are kept; errors of the store are not reported, the request
+/// is sent to the server instead. The methods are called from the async requests, so a store
+/// doing blocking I/O must move it off the executor.
+pub trait CacheStore: fmt::Debug + Send + Sync {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CacheEntry>>;
+
+    fn put<'a>(&'a self, url: &'a str, entry: CacheEntry) -> BoxFuture<'a, ()>;
+
+    /// removes the responses of every URL for which `matches` returns true
+    fn remove_matching<'a>(
+        &'a self,
+        matches: &'a (dyn Fn(&str) -> bool + Sync),
+    ) -> BoxFuture<'a, ()>;
+}
+
+/// Keeps the responses in memory for the lifetime of the store
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl CacheStore for MemoryStore {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CacheEntry>> {
+        let entry = self.entries.lock().unwrap().get(url).cloned();
+
+        Box::pin(future::ready(entry))
+    }
+
+    fn put<'a>(&'a self, url: &'a str, entry: CacheEntry) -> BoxFuture<'a, ()> {
+        self.entries.lock().unwrap().insert(url.into(), entry);
+
+        Box::pin(future::ready(()))
+    }
+
+    fn remove_matching<'a>(
+        &'a self,
+        matches: &'a (dyn Fn(&str) -> bool + Sync),
+    ) -> BoxFuture<'a, ()> {
+        self.entries.lock().unwrap().retain(|url, _| !matches(url));
+
+        Box::pin(future::ready(()))
+    }
+}
+
+/// Keeps the responses in a directory, one file per URL, so they survive the process. A file
+/// is written under a temporary name then renamed, so a reader never sees a partial response.
+/// The files are read and written on the blocking threads of the Tokio runtime.
+#[derive(Debug, Clone)]
+pub struct DiskStore {
+    directory: PathBuf,
+}
+
+/// The first line of a file of the disk store, followed by the body
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskEntryMeta {
+    url: String,
+    etag: Option<String>,
+    /// seconds since the Unix epoch
+    expires: Option<u64>,
+}
+
+/// Makes the temporary names unique between the writers of a process
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+impl DiskStore {
+    /// creates a store in the given directory, which is created on the first write
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.directory
+            .join(format!("{:016x}.entry", fnv1a(url.as_bytes())))
+    }
+
+    fn read(&self, url: &str) -> Result<Option<CacheEntry>, Box<dyn std::error::Error>> {
+        let path = self.path(url);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read(path)?;
+        let (meta, body) = content.split_at(
+            content
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .unwrap_or(content.len()),
+        );
+        let meta: DiskEntryMeta = serde_urlencoded::from_bytes(meta)?;
+
+        // different URLs with the same hash share the file
+        if meta.url != url {
+            return Ok(None);
+        }
+
+        Ok(Some(CacheEntry {
+            etag: meta.etag,
+            expires: meta
+                .expires
+                .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds)),
+            body: body.get(1..).unwrap_or_default().to_vec(),
+        }))
+    }
+
+    fn write(&self, url: &str, entry: CacheEntry) -> Result<(), Box<dyn std::error::Error>> {
+        let meta = DiskEntryMeta {
+            url: url.into(),
+            etag: entry.etag,
+            expires: entry.expires.map(|expires| {
+                expires
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            }),
+        };
+        let mut content = serde_urlencoded::to_string(&meta)?.into_bytes();
+
+        content.push(b'\n');
+        content.extend(entry.body);
+
+        let temporary = self.directory.join(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::create_dir_all(&self.directory)?;
+        fs::write(&temporary, content)?;
+
+        if let Err(error) = fs::rename(&temporary, self.path(url)) {
+            let _ = fs::remove_file(temporary);
+            return Err(error.into());
+        }
+
+        Ok(())
+    }
+
+    /// the files of the store with the URL on their first line
+    fn entries(&self) -> Vec<(PathBuf, String)> {
+        let Ok(files) = fs::read_dir(&self.directory) else {
+            return vec![];
+        };
+
+        files
+            .filter_map(|file| file.ok())
+            .map(|file| file.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "entry")
+            })
+            .filter_map(|path| Some((path.clone(), Self::url(&path)?)))
+            .collect()
+    }
+
+    /// the URL on the first line of a file of the store
+    fn url(path: &Path) -> Option<String> {
+        let mut line = String::new();
+
+        BufReader::new(fs::File::open(path).ok()?)
+            .read_line(&mut line)
+            .ok()?;
+
+        serde_urlencoded::from_str::<DiskEntryMeta>(line.trim_end())
+            .ok()
+            .map(|meta| meta.url)
+    }
+}
+
+impl CacheStore for DiskStore {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CacheEntry>> {
+        let store = self.clone();
+        let url = url.to_string();
+
+        Box::pin(async move {
+            let entry = blocking(move || {
+                store.read(&url).unwrap_or_else(|error| {
+                    warn!("cannot read the cached response of {url}: {error}");
+                    None
+                })
+            });
+
+            entry.await.flatten()
+        })
+    }
+
+    fn put<'a>(&'a self, url: &'a str, entry: CacheEntry) -> BoxFuture<'a, ()> {
+        let store = self.clone();
+        let url = url.to_string();
+
+        Box::pin(async move {
+            blocking(move || {
+                if let Err(error) = store.write(&url, entry) {
+                    warn!("cannot cache the response of {url}: {error}");
+                }
+            })
+            .await;
+        })
+    }
+
+    fn remove_matching<'a>(
+        &'a self,
+        matches: &'a (dyn Fn(&str) -> bool + Sync),
+    ) -> BoxFuture<'a, ()> {
+        let store = self.clone();
+
+        Box::pin(async move {
+            let entries = blocking(move || store.entries()).await.unwrap_or_default();
+            let paths = entries
+                .into_iter()
+                .filter(|(_, url)| matches(url))
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+
+            blocking(move || {
+                for path in paths {
+                    let _ = fs::remove_file(path);
+                }
+            })
+            .await;
+        })
+    }
+}
+
+/// Runs the file operations of the disk store on a blocking thread, `None` if it panicked
+async fn blocking<T, F>(operation: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation).await.ok()
+}
+
+/// The middleware serving and storing the responses
+#[derive(Debug)]
+pub struct Cache<S> {
+    store: S,
+}
+
+impl<S: CacheStore> Cache<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    async fn get(
+        &self,
+        mut request: Request,
+        next: Next<'_>,
+    ) -> Result<HttpResponse, OpenstreetmapError> {
+        let url = request.http.url.to_string();
+        let cached = self.store.get(&url).await;
+
+        if let Some(entry) = &cached {
+            if entry.is_fresh() {
+                debug!("cache hit -> {url}");
+                return Ok(cached_response(entry));
+            }
+
+            if let Some(etag) = entry.etag.as_deref().and_then(|etag| etag.parse().ok()) {
+                request.http.headers.insert(IF_NONE_MATCH, etag);
+            }
+        }
+
+        let immutable = request.options.immutable;
+        let res = next.run(request).await?;
+
+        match (res.status, cached) {
+            (StatusCode::NOT_MODIFIED, Some(mut entry)) => {
+                debug!("cache revalidated -> {url}");
+                entry.expires = expires(&res.headers, immutable);
+                self.store.put(&url, entry.clone()).await;
+
+                Ok(cached_response(&entry))
+            }
+            (StatusCode::OK, _) if is_storable(&res.headers, immutable) => {
+                let headers = res.headers.clone();
+                let entry = CacheEntry {
+                    etag: header(&headers, ETAG).map(String::from),
+                    expires: expires(&headers, immutable),
+                    body: res.bytes().await?,
+                };
+
+                self.store.put(&url, entry.clone()).await;
+
+                Ok(HttpResponse::new(StatusCode::OK, headers, entry.body))
+            }
+            _ => Ok(res),
+        }
+    }
+}
+
+impl<S: CacheStore> Middleware for Cache<S> {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>> {
+        if request.http.method != Method::GET {
+            let changed = api_path(&request.http.url);
+
+            return Box::pin(async move {
+                self.store
+                    .remove_matching(&|url| {
+                        Url::parse(url).is_ok_and(|url| is_changed_by(&api_path(&url), &changed))
+                    })
+                    .await;
+
+                next.run(request).await
+            });
+        }
+
+        // A streamed response is parsed while it is downloaded, which storing it would prevent
+        if request.options.use_auth || request.options.streamed {
+            return next.run(request);
+        }
+
+        Box::pin(self.get(request, next))
+    }
+}
+
+/// the segments of a URL after `api/` and the version, e.g. `["node", "1234", "history"]`
+fn api_path(url: &Url) -> Vec<String> {
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.collect())
+        .unwrap_or_default();
+    let start = segments
+        .iter()
+        .position(|segment| *segment == "api")
+        .map_or(0, |index| index + 1);
+    let path = &segments[start..];
+    let path = match path.first() {
+        Some(version) if version.starts_with(|c: char| c.is_ascii_digit()) => &path[1..],
+        _ => path,
+    };
+
+    path.iter().map(|segment| segment.to_string()).collect()
+}
+
+/// the resource of a path, the same for one and many of them, e.g. `note` for both
+/// `notes/1234` and `notes.json`
+fn resource(path: &[String]) -> Option<&str> {
+    let segment = path.first()?.split('.').next()?;
+
+    Some(segment.strip_suffix('s').unwrap_or(segment))
+}
+
+fn holds_elements(resource: &str) -> bool {
+    matches!(resource, "node" | "way" | "relation" | "map")
+}
+
+/// whether the cached response of a path is stale after a request changing another path
+fn is_changed_by(cached: &[String], changed: &[String]) -> bool {
+    let (Some(cached_resource), Some(changed_resource)) = (resource(cached), resource(changed))
+    else {
+        return cached == changed;
+    };
+    let is_upload =
+        changed_resource == "changeset" && changed.get(2).is_some_and(|s| s == "upload");
+    // `node/1234/2` is a version, which never changes
+    let is_version = cached.len() == 3 && cached[2].parse::<u64>().is_ok();
+
+    if holds_elements(cached_resource) && (holds_elements(changed_resource) || is_upload) {
+        return !is_version;
+    }
+
+    cached_resource == changed_resource
+}
+
+fn cached_response(entry: &CacheEntry) -> HttpResponse {
+    let mut headers = HeaderMap::new();
+
+    if let Some(etag) = entry.etag.as_deref().and_then(|etag| etag.parse().ok()) {
+        headers.insert(ETAG, etag);
+    }
+
+    HttpResponse::new(StatusCode::OK, headers, entry.body.clone())
+}
+
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn cache_control(headers: &HeaderMap) -> Vec<String> {
+    header(headers, CACHE_CONTROL)
+        .unwrap_or_default()
+        .split(',')
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// Immutable responses are always stored, the others only when they can be served again:
+/// either fresh for a while or revalidated with their `ETag`
+fn is_storable(headers: &HeaderMap, immutable: bool) -> bool {
+    let directives = cache_control(headers);
+
+    if directives.iter().any(|directive| directive == "no-store") {
+        return false;
+    }
+
+    immutable || headers.contains_key(ETAG) || max_age(&directives).is_some_and(|age| age > 0)
+}
+
+fn expires(headers: &HeaderMap, immutable: bool) -> Option<SystemTime> {
+    if immutable {
+        return None;
+    }
+
+    let directives = cache_control(headers);
+    let no_cache = directives.iter().any(|directive| directive == "no-cache");
+    let max_age = max_age(&directives).filter(|_| !no_cache).unwrap_or(0);
+
+    Some(SystemTime::now() + Duration::from_secs(max_age))
+}
+
+fn max_age(directives: &[String]) -> Option<u64> {
+    directives
+        .iter()
+        .find_map(|directive| directive.strip_prefix("max-age="))
+        .and_then(|age| age.parse().ok())
+}
+
+/// 64-bit FNV-1a, a hash stable across builds for the file names of the disk store
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
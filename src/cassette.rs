@@ -0,0 +1,291 @@
+//! A transport recording the requests and responses of a client into a JSON fixture file, a
+//! "cassette", and replaying them later without network:
+//!
+//! ```no_run
+//! use openstreetmap_api::cassette::Cassette;
+//! use openstreetmap_api::transport::ReqwestTransport;
+//! use openstreetmap_api::{types, Openstreetmap};
+//!
+//! # async fn run() -> Result<(), openstreetmap_api::errors::OpenstreetmapError> {
+//! // records the responses of the real API
+//! let cassette = Cassette::record("tests/fixtures/node.json", ReqwestTransport::default());
+//! let client = Openstreetmap::from_transport("https://api.openstreetmap.org", types::Credentials::None, cassette);
+//! let recorded = client.nodes().get(1234).await?;
+//! drop(client);
+//!
+//! // serves the recorded responses
+//! let cassette = Cassette::replay("tests/fixtures/node.json")?;
+//! let client = Openstreetmap::from_transport("https://api.openstreetmap.org", types::Credentials::None, cassette);
+//! assert_eq!(client.nodes().get(1234).await?, recorded);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are matched on method, path, query and body, so the host may differ between
+//! recording and replay. When the same request is recorded more than once the responses are
+//! replayed in the recorded order. The request headers, and the cookies and authentication
+//! headers of the responses, are not recorded to keep the credentials out of the fixtures.
+//!
+//! A recording cassette writes its file once, when it is dropped with the last clone of its
+//! client.
+
+use crate::errors::OpenstreetmapError;
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use futures::future::BoxFuture;
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION, SET_COOKIE, WWW_AUTHENTICATE,
+};
+use http::StatusCode;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(flatten)]
+    body: Option<RecordedBody>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    body: Option<RecordedBody>,
+}
+
+/// Text bodies are kept readable in the fixture, the others are encoded in base64
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedBody {
+    Body(String),
+    BodyBase64(String),
+}
+
+impl RecordedBody {
+    fn new(bytes: Vec<u8>) -> Option<Self> {
+        match String::from_utf8(bytes) {
+            Ok(text) if text.is_empty() => None,
+            Ok(text) => Some(RecordedBody::Body(text)),
+            Err(error) => Some(RecordedBody::BodyBase64(
+                BASE64_STANDARD.encode(error.into_bytes()),
+            )),
+        }
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>, OpenstreetmapError> {
+        match self {
+            RecordedBody::Body(text) => Ok(text.clone().into_bytes()),
+            RecordedBody::BodyBase64(encoded) => BASE64_STANDARD
+                .decode(encoded)
+                .map_err(|error| OpenstreetmapError::Transport(error.into())),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(Arc<dyn Transport>),
+    Replay,
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    /// the interactions with whether they have been replayed
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// sends the requests with the given transport and writes the interactions to the
+    /// cassette file when dropped, replacing its previous content
+    pub fn record<P, T>(path: P, transport: T) -> Self
+    where
+        P: Into<PathBuf>,
+        T: Transport + 'static,
+    {
+        Self {
+            path: path.into(),
+            mode: Mode::Record(Arc::new(transport)),
+            interactions: Mutex::default(),
+        }
+    }
+
+    /// serves the responses recorded in the cassette file, requests without a matching
+    /// response fail with `OpenstreetmapError::Transport`
+    pub fn replay<P>(path: P) -> Result<Self, OpenstreetmapError>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|error| OpenstreetmapError::Transport(error.into()))?;
+
+        Ok(Self {
+            path,
+            mode: Mode::Replay,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        })
+    }
+
+    async fn record_send(
+        &self,
+        transport: &dyn Transport,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, OpenstreetmapError> {
+        let recorded_request = RecordedRequest {
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            body: request.body.clone().and_then(RecordedBody::new),
+        };
+        let res = transport.send(request).await?;
+        let status = res.status;
+        let headers = res.headers.clone();
+        let body = res.bytes().await?;
+
+        let interaction = Interaction {
+            request: recorded_request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter(|(name, _)| !is_secret(name))
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                body: RecordedBody::new(body.clone()),
+            },
+        };
+
+        self.interactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((interaction, true));
+
+        Ok(HttpResponse::new(status, headers, body))
+    }
+
+    fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // a poisoned lock still holds the interactions recorded before the panic
+        let interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let recorded = interactions.iter().map(|(i, _)| i).collect::<Vec<_>>();
+        let json = serde_json::to_vec_pretty(&recorded)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, json)?;
+
+        Ok(())
+    }
+
+    fn replay_send(&self, request: &HttpRequest) -> Result<HttpResponse, OpenstreetmapError> {
+        let body = request.body.clone().and_then(RecordedBody::new);
+        let mut interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (interaction, replayed) = interactions
+            .iter_mut()
+            .find(|(interaction, replayed)| {
+                !replayed
+                    && interaction.request.method == request.method.as_str()
+                    && same_resource(&interaction.request.url, &request.url)
+                    && interaction.request.body == body
+            })
+            .ok_or_else(|| {
+                OpenstreetmapError::Transport(
+                    format!(
+                        "no recorded response for {} {} in {}",
+                        request.method,
+                        request.url,
+                        self.path.display()
+                    )
+                    .into(),
+                )
+            })?;
+
+        *replayed = true;
+
+        let response = &interaction.response;
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &response.headers {
+            if let (Ok(name), Ok(value)) =
+                (name.parse::<HeaderName>(), HeaderValue::from_str(value))
+            {
+                headers.append(name, value);
+            }
+        }
+
+        let status = StatusCode::from_u16(response.status)
+            .map_err(|error| OpenstreetmapError::Transport(error.into()))?;
+        let body = match &response.body {
+            Some(body) => body.bytes()?,
+            None => vec![],
+        };
+
+        Ok(HttpResponse::new(status, headers, body))
+    }
+}
+
+impl Transport for Cassette {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> BoxFuture<'_, Result<HttpResponse, OpenstreetmapError>> {
+        match &self.mode {
+            Mode::Record(transport) => Box::pin(self.record_send(transport.as_ref(), request)),
+            Mode::Replay => {
+                let response = self.replay_send(&request);
+
+                Box::pin(async move { response })
+            }
+        }
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Mode::Record(_) = self.mode {
+            if let Err(error) = self.write() {
+                warn!("cannot write the cassette {}: {error}", self.path.display());
+            }
+        }
+    }
+}
+
+/// the response headers holding credentials or session cookies
+fn is_secret(name: &HeaderName) -> bool {
+    [
+        SET_COOKIE,
+        COOKIE,
+        AUTHORIZATION,
+        PROXY_AUTHORIZATION,
+        WWW_AUTHENTICATE,
+        PROXY_AUTHENTICATE,
+    ]
+    .contains(name)
+}
+
+fn same_resource(recorded: &str, url: &Url) -> bool {
+    Url::parse(recorded)
+        .is_ok_and(|recorded| recorded.path() == url.path() && recorded.query() == url.query())
+}
@@ -0,0 +1,314 @@
+//! Loads the host and the credentials of a client from a TOML configuration file with named
+//! profiles, `$XDG_CONFIG_HOME/openstreetmap/config.toml` by default
+//! (`~/.config/openstreetmap/config.toml`):
+//!
+//! ```toml
+//! default_profile = "dev"
+//!
+//! [profiles.dev]
+//! host = "https://master.apis.dev.openstreetmap.org"
+//! user = "mapper"
+//! password = "secret"
+//!
+//! [profiles.local]
+//! host = "http://localhost:3000"
+//! ```
+//!
+//! The profile used is the one requested, then `OPENSTREETMAP_PROFILE`, then `default_profile`,
+//! then `dev`, so nothing is written to the live map without asking for `production`. The
+//! `production`, `dev` and `local` profiles have a default host, so they work without an entry
+//! in the file. `OPENSTREETMAP_HOST`, `OPENSTREETMAP_USER`, `OPENSTREETMAP_PASSWORD` and
+//! `OPENSTREETMAP_TOKEN` take precedence over the file, except that a requested profile keeps
+//! its own host and credentials, and `OPENSTREETMAP_CONFIG` replaces the path of the file.
+//!
+//! OAuth 2.0 access tokens are kept out of the configuration in `tokens.toml`, next to it, which
+//! is only readable by its owner:
+//!
+//! ```no_run
+//! use openstreetmap_api::config::Config;
+//! use openstreetmap_api::Openstreetmap;
+//!
+//! # async fn run() -> Result<(), openstreetmap_api::errors::OpenstreetmapError> {
+//! let config = Config::load()?;
+//!
+//! config.tokens().save("dev", "access-token")?;
+//!
+//! let client = Openstreetmap::from_config(Some("dev"))?;
+//! let user = client.user().details().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! When several credentials are available the environment wins over the files, and a token
+//! wins over a user and password from the same source.
+
+use crate::errors::OpenstreetmapError;
+use crate::types::Credentials;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// the profile used when none is requested, the development server rather than the live map
+pub const DEFAULT_PROFILE: &str = "dev";
+
+/// profiles available without an entry in the configuration file, with their host
+const BUILTIN_PROFILES: [(&str, &str); 3] = [
+    ("production", "https://api.openstreetmap.org"),
+    ("dev", "https://master.apis.dev.openstreetmap.org"),
+    ("local", "http://localhost:3000"),
+];
+
+/// An entry of the `profiles` table of the configuration file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub host: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+/// The configuration file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// the token file, next to the configuration file
+    #[serde(skip)]
+    tokens_path: PathBuf,
+}
+
+/// The host and the credentials of a profile, after the environment overrides
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub host: String,
+    pub credentials: Credentials,
+}
+
+/// The OAuth 2.0 access tokens of the profiles, stored in a TOML file readable by its owner only
+#[derive(Debug, Clone)]
+pub struct TokenStore {
+    path: PathBuf,
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// `openstreetmap` in the XDG configuration directory
+fn config_directory() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|directory| !directory.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|directory| directory.join("openstreetmap"))
+}
+
+impl Config {
+    /// the path of the configuration file, `OPENSTREETMAP_CONFIG` or `config.toml` in the XDG
+    /// configuration directory
+    pub fn default_path() -> Option<PathBuf> {
+        env_var("OPENSTREETMAP_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| config_directory().map(|directory| directory.join("config.toml")))
+    }
+
+    /// reads the configuration file at the default path, an empty configuration is returned
+    /// when the file does not exist
+    pub fn load() -> Result<Self, OpenstreetmapError> {
+        let path = Self::default_path().ok_or_else(|| {
+            OpenstreetmapError::Config("no configuration directory, set HOME".into())
+        })?;
+
+        match Self::from_file(&path) {
+            Err(OpenstreetmapError::Io(error)) if error.kind() == ErrorKind::NotFound => {
+                Ok(Self::empty(&path))
+            }
+            result => result,
+        }
+    }
+
+    /// reads the configuration file, the token file is `tokens.toml` in the same directory
+    pub fn from_file<P>(path: P) -> Result<Self, OpenstreetmapError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&content).map_err(|error| {
+            OpenstreetmapError::Config(format!("invalid {}: {error}", path.display()))
+        })?;
+
+        Ok(Self {
+            tokens_path: Self::empty(path).tokens_path,
+            ..config
+        })
+    }
+
+    fn empty(path: &Path) -> Self {
+        Self {
+            tokens_path: path.with_file_name("tokens.toml"),
+            ..Default::default()
+        }
+    }
+
+    /// the token file of the configuration
+    pub fn tokens(&self) -> TokenStore {
+        TokenStore::new(&self.tokens_path)
+    }
+
+    /// resolves the requested profile, or the default one, applying the environment overrides
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, OpenstreetmapError> {
+        let requested = name.is_some();
+        let name = name
+            .map(String::from)
+            .or_else(|| env_var("OPENSTREETMAP_PROFILE"))
+            .or_else(|| self.default_profile.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.into());
+        let builtin_host = BUILTIN_PROFILES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, host)| String::from(*host));
+        let entry = match (self.profiles.get(&name), &builtin_host) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(_)) => ProfileConfig::default(),
+            (None, None) => {
+                return Err(OpenstreetmapError::Config(format!(
+                    "unknown profile {name}"
+                )))
+            }
+        };
+        let profile_host = entry.host.or(builtin_host);
+        let env_host = env_var("OPENSTREETMAP_HOST");
+        // the host of a requested profile wins over the one of the environment
+        let host_from_env = env_host.is_some() && (!requested || profile_host.is_none());
+        let host = if host_from_env {
+            env_host
+        } else {
+            profile_host
+        }
+        .ok_or_else(|| OpenstreetmapError::Config(format!("profile {name} has no host")))?;
+        // the credentials of the environment are not sent to the host of a requested profile
+        let env_credentials = |variable| env_var(variable).filter(|_| !requested || host_from_env);
+        let env_basic =
+            env_credentials("OPENSTREETMAP_USER").zip(env_credentials("OPENSTREETMAP_PASSWORD"));
+        let credentials = if let Some(token) = env_credentials("OPENSTREETMAP_TOKEN") {
+            Credentials::Bearer(token)
+        } else if let Some((user, password)) = env_basic {
+            Credentials::Basic(user, password)
+        } else if let Some(token) = self.tokens().get(&name)? {
+            Credentials::Bearer(token)
+        } else if let Some((user, password)) = entry.user.zip(entry.password) {
+            Credentials::Basic(user, password)
+        } else {
+            Credentials::None
+        };
+
+        Ok(Profile {
+            name,
+            host,
+            credentials,
+        })
+    }
+}
+
+impl TokenStore {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { path: path.into() }
+    }
+
+    /// the token of the profile, `None` when the profile or the file do not exist
+    pub fn get(&self, profile: &str) -> Result<Option<String>, OpenstreetmapError> {
+        Ok(self.read()?.remove(profile))
+    }
+
+    /// stores the token of the profile, replacing the previous one
+    pub fn save(&self, profile: &str, token: &str) -> Result<(), OpenstreetmapError> {
+        let mut tokens = self.read()?;
+
+        tokens.insert(profile.into(), token.into());
+        self.write(&tokens)
+    }
+
+    /// removes the token of the profile, returning whether there was one
+    pub fn remove(&self, profile: &str) -> Result<bool, OpenstreetmapError> {
+        let mut tokens = self.read()?;
+        let removed = tokens.remove(profile).is_some();
+
+        if removed {
+            self.write(&tokens)?;
+        }
+
+        Ok(removed)
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>, OpenstreetmapError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&self.path)?.permissions().mode();
+
+            if mode & 0o077 != 0 {
+                warn!(
+                    "{} is readable by other users, its mode should be 600",
+                    self.path.display()
+                );
+            }
+        }
+
+        toml::from_str(&content).map_err(|error| {
+            OpenstreetmapError::Config(format!("invalid {}: {error}", self.path.display()))
+        })
+    }
+
+    /// replaces the file through a temporary file created readable by its owner only, so the
+    /// tokens are never exposed, even partially written
+    fn write(&self, tokens: &BTreeMap<String, String>) -> Result<(), OpenstreetmapError> {
+        let content = toml::to_string(tokens)
+            .map_err(|error| OpenstreetmapError::Config(error.to_string()))?;
+        let temporary = self.path.with_extension("toml.tmp");
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let mut options = fs::OpenOptions::new();
+
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            options.mode(0o600);
+
+            // the mode only applies to new files
+            if temporary.exists() {
+                fs::set_permissions(&temporary, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(&temporary)?;
+
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+
+        Ok(())
+    }
+}
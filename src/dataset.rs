@@ -0,0 +1,224 @@
+use crate::types;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// In-memory OSM data indexed by element id, with reverse lookups from nodes to the ways
+/// using them and from elements to the relations they are members of.
+#[derive(Debug, Default, PartialEq)]
+pub struct Dataset {
+    nodes: BTreeMap<i64, types::Node>,
+    ways: BTreeMap<i64, types::Way>,
+    relations: BTreeMap<i64, types::Relation>,
+    node_ways: BTreeMap<i64, BTreeSet<i64>>,
+    member_relations: BTreeMap<(types::ElementType, i64), BTreeSet<i64>>,
+}
+
+fn has_tag(tags: &[types::Tag], key: &str, value: Option<&str>) -> bool {
+    tags.iter()
+        .any(|tag| tag.k == key && value.is_none_or(|v| tag.v == v))
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn node(&self, node_id: i64) -> Option<&types::Node> {
+        self.nodes.get(&node_id)
+    }
+
+    #[inline]
+    pub fn way(&self, way_id: i64) -> Option<&types::Way> {
+        self.ways.get(&way_id)
+    }
+
+    #[inline]
+    pub fn relation(&self, relation_id: i64) -> Option<&types::Relation> {
+        self.relations.get(&relation_id)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &types::Node> {
+        self.nodes.values()
+    }
+
+    pub fn ways(&self) -> impl Iterator<Item = &types::Way> {
+        self.ways.values()
+    }
+
+    pub fn relations(&self) -> impl Iterator<Item = &types::Relation> {
+        self.relations.values()
+    }
+
+    /// ways referencing the given node
+    pub fn ways_for_node(&self, node_id: i64) -> Vec<&types::Way> {
+        self.node_ways
+            .get(&node_id)
+            .map(|ids| ids.iter().filter_map(|id| self.ways.get(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// relations having the given element as a member
+    pub fn relations_for_member(
+        &self,
+        element_type: types::ElementType,
+        element_id: i64,
+    ) -> Vec<&types::Relation> {
+        self.member_relations
+            .get(&(element_type, element_id))
+            .map(|ids| ids.iter().filter_map(|id| self.relations.get(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// nodes tagged with `key`, and with `value` if given
+    pub fn nodes_with_tag<'a>(
+        &'a self,
+        key: &'a str,
+        value: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a types::Node> {
+        self.nodes().filter(move |n| has_tag(&n.tags, key, value))
+    }
+
+    /// ways tagged with `key`, and with `value` if given
+    pub fn ways_with_tag<'a>(
+        &'a self,
+        key: &'a str,
+        value: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a types::Way> {
+        self.ways().filter(move |w| has_tag(&w.tags, key, value))
+    }
+
+    /// relations tagged with `key`, and with `value` if given
+    pub fn relations_with_tag<'a>(
+        &'a self,
+        key: &'a str,
+        value: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a types::Relation> {
+        self.relations()
+            .filter(move |r| has_tag(&r.tags, key, value))
+    }
+
+    /// Adds the node unless the dataset already holds the same or a newer version of it.
+    /// Returns whether the node was stored.
+    pub fn insert_node(&mut self, node: types::Node) -> bool {
+        if self
+            .nodes
+            .get(&node.id)
+            .is_some_and(|n| n.version >= node.version)
+        {
+            return false;
+        }
+
+        self.nodes.insert(node.id, node);
+
+        true
+    }
+
+    /// Adds the way unless the dataset already holds the same or a newer version of it.
+    /// Returns whether the way was stored.
+    pub fn insert_way(&mut self, way: types::Way) -> bool {
+        if self
+            .ways
+            .get(&way.id)
+            .is_some_and(|w| w.version >= way.version)
+        {
+            return false;
+        }
+
+        if let Some(previous) = self.ways.remove(&way.id) {
+            for node_ref in previous.node_refs {
+                if let Some(ids) = self.node_ways.get_mut(&node_ref.node_id) {
+                    ids.remove(&previous.id);
+                }
+            }
+        }
+
+        for node_ref in way.node_refs.iter() {
+            self.node_ways
+                .entry(node_ref.node_id)
+                .or_default()
+                .insert(way.id);
+        }
+
+        self.ways.insert(way.id, way);
+
+        true
+    }
+
+    /// Adds the relation unless the dataset already holds the same or a newer version of it.
+    /// Returns whether the relation was stored.
+    pub fn insert_relation(&mut self, relation: types::Relation) -> bool {
+        if self
+            .relations
+            .get(&relation.id)
+            .is_some_and(|r| r.version >= relation.version)
+        {
+            return false;
+        }
+
+        if let Some(previous) = self.relations.remove(&relation.id) {
+            for member in previous.members {
+                if let Some(element_type) = member.element_type() {
+                    if let Some(ids) = self
+                        .member_relations
+                        .get_mut(&(element_type, member.node_id))
+                    {
+                        ids.remove(&previous.id);
+                    }
+                }
+            }
+        }
+
+        for member in relation.members.iter() {
+            if let Some(element_type) = member.element_type() {
+                self.member_relations
+                    .entry((element_type, member.node_id))
+                    .or_default()
+                    .insert(relation.id);
+            }
+        }
+
+        self.relations.insert(relation.id, relation);
+
+        true
+    }
+
+    pub fn merge_map(&mut self, map: types::Map) {
+        for node in map.nodes {
+            self.insert_node(node);
+        }
+        for way in map.ways {
+            self.insert_way(way);
+        }
+        for relation in map.relations {
+            self.insert_relation(relation);
+        }
+    }
+
+    pub fn merge_way_full(&mut self, way_full: types::WayFull) {
+        for node in way_full.nodes {
+            self.insert_node(node);
+        }
+        self.insert_way(way_full.way);
+    }
+
+    pub fn merge_relation_full(&mut self, relation_full: types::RelationFull) {
+        for node in relation_full.nodes {
+            self.insert_node(node);
+        }
+        for way in relation_full.ways {
+            self.insert_way(way);
+        }
+        self.insert_relation(relation_full.relation);
+    }
+}
+
+impl From<types::Map> for Dataset {
+    fn from(map: types::Map) -> Self {
+        let mut dataset = Dataset::new();
+
+        dataset.merge_map(map);
+
+        dataset
+    }
+}
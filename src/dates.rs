@@ -0,0 +1,108 @@
+//! Conversions between the seconds since the Unix epoch and the UTC dates of the API, using
+//! the algorithms from http://howardhinnant.github.io/date_algorithms.html
+
+/// the date and time of the seconds since the Unix epoch
+fn civil(seconds: i64) -> (i64, i64, i64, i64, i64, i64) {
+    let days = seconds.div_euclid(86400) + 719468;
+    let time = seconds.rem_euclid(86400);
+
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// the days since the Unix epoch of the date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// formats the timestamp of elements and changesets, e.g. `2024-05-01T12:30:00Z`
+pub(crate) fn format_timestamp(seconds: i64) -> String {
+    let (year, month, day, hour, minute, second) = civil(seconds);
+
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// formats the dates of notes, e.g. `2024-05-01 12:30:00 UTC`
+#[cfg(feature = "test-server")]
+pub(crate) fn format_note_date(seconds: i64) -> String {
+    let (year, month, day, hour, minute, second) = civil(seconds);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} UTC")
+}
+
+/// parses the timestamps of elements and changesets, e.g. `2024-05-01T12:30:00Z`, and the
+/// dates of notes, e.g. `2024-05-01 12:30:00 UTC`, into seconds since the Unix epoch
+pub(crate) fn parse_timestamp(value: &str) -> Option<i64> {
+    let (date, time) = value.split_at_checked(10)?;
+    let (time, zone) = time.split_at_checked(9)?;
+
+    if !matches!(zone, "Z" | " UTC") || !matches!(time.as_bytes()[0], b'T' | b' ') {
+        return None;
+    }
+
+    let number = |field: &str| field.parse::<i64>().ok();
+    let date = date.split('-').map(number).collect::<Option<Vec<i64>>>()?;
+    let time = time[1..]
+        .split(':')
+        .map(number)
+        .collect::<Option<Vec<i64>>>()?;
+
+    match (date.as_slice(), time.as_slice()) {
+        ([year, month, day], [hour, minute, second]) => {
+            Some(days_from_civil(*year, *month, *day) * 86400 + hour * 3600 + minute * 60 + second)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1049522828), "2003-04-05T06:07:08Z");
+        assert_eq!(format_timestamp(1709210096), "2024-02-29T12:34:56Z");
+        assert_eq!(format_timestamp(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[cfg(feature = "test-server")]
+    #[test]
+    fn test_format_note_date() {
+        assert_eq!(format_note_date(951782400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(
+            parse_timestamp(&format_note_date(1709210096)),
+            Some(1709210096)
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2024-02-29T12:34:56Z"), Some(1709210096));
+        assert_eq!(parse_timestamp("2000-02-29 00:00:00 UTC"), Some(951782400));
+        assert_eq!(parse_timestamp("2000-02-29 00:00:00 +0100"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::types::{self, ElementVersion};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A version of a node, way or relation, as returned by `Elements::history()`, which can be
+/// compared with another version
+pub trait Diffable: ElementVersion {
+    /// fills in the changes specific to the element type: moves, node lists and members
+    fn diff_geometry(&self, newer: &Self, diff: &mut ElementDiff);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TagChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Modified {
+        key: String,
+        old_value: String,
+        new_value: String,
+    },
+}
+
+/// A node moved from one `(lon, lat)` position to another
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Move {
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+}
+
+/// The changes of a way's node list or a relation's member list
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListChange<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    /// whether the items kept by both versions are in a different order
+    pub reordered: bool,
+}
+
+/// The differences between two versions of an element
+#[derive(Debug, Default, PartialEq)]
+pub struct ElementDiff {
+    pub from_version: u64,
+    pub to_version: u64,
+    /// the new visibility if the element was deleted or undeleted
+    pub visible: Option<bool>,
+    /// sorted by key
+    pub tags: Vec<TagChange>,
+    pub moved: Option<Move>,
+    pub node_refs: Option<ListChange<i64>>,
+    pub members: Option<ListChange<types::Member>>,
+}
+
+impl ElementDiff {
+    pub fn is_empty(&self) -> bool {
+        self.visible.is_none()
+            && self.tags.is_empty()
+            && self.moved.is_none()
+            && self.node_refs.is_none()
+            && self.members.is_none()
+    }
+}
+
+/// The version which last set the value of a tag
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TagBlame {
+    pub key: String,
+    pub value: String,
+    pub version: u64,
+    pub changeset: u64,
+    pub timestamp: String,
+    pub user: Option<String>,
+}
+
+impl Diffable for types::Node {
+    fn diff_geometry(&self, newer: &Self, diff: &mut ElementDiff) {
+        let position = |node: &types::Node| node.lon.zip(node.lat);
+
+        if let (Some(from), Some(to)) = (position(self), position(newer)) {
+            if from != to {
+                diff.moved = Some(Move { from, to });
+            }
+        }
+    }
+}
+
+impl Diffable for types::Way {
+    fn diff_geometry(&self, newer: &Self, diff: &mut ElementDiff) {
+        let node_ids = |way: &types::Way| way.node_refs.iter().map(|n| n.node_id).collect();
+
+        diff.node_refs = list_change(node_ids(self), node_ids(newer));
+    }
+}
+
+impl Diffable for types::Relation {
+    fn diff_geometry(&self, newer: &Self, diff: &mut ElementDiff) {
+        diff.members = list_change(self.members.clone(), newer.members.clone());
+    }
+}
+
+/// Compares two versions of the same element
+pub fn diff<E: Diffable>(old: &E, new: &E) -> ElementDiff {
+    let old_tags = tag_map(old.tags());
+    let new_tags = tag_map(new.tags());
+
+    let mut tags = vec![];
+
+    for (key, old_value) in &old_tags {
+        match new_tags.get(key) {
+            None => tags.push(TagChange::Removed {
+                key: key.to_string(),
+                value: old_value.to_string(),
+            }),
+            Some(new_value) if new_value != old_value => tags.push(TagChange::Modified {
+                key: key.to_string(),
+                old_value: old_value.to_string(),
+                new_value: new_value.to_string(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (key, new_value) in &new_tags {
+        if !old_tags.contains_key(key) {
+            tags.push(TagChange::Added {
+                key: key.to_string(),
+                value: new_value.to_string(),
+            });
+        }
+    }
+
+    tags.sort_by(|a, b| tag_change_key(a).cmp(tag_change_key(b)));
+
+    let mut diff = ElementDiff {
+        from_version: old.version(),
+        to_version: new.version(),
+        visible: (old.visible() != new.visible()).then_some(new.visible()),
+        tags,
+        ..Default::default()
+    };
+
+    old.diff_geometry(new, &mut diff);
+
+    diff
+}
+
+/// Attributes every tag of the latest version in the history to the version which last set
+/// its current value. The history does not need to be sorted.
+pub fn blame<E: ElementVersion>(history: &[E]) -> Vec<TagBlame> {
+    let mut versions = history.iter().collect::<Vec<&E>>();
+    versions.sort_by_key(|element| element.version());
+
+    let mut origins: HashMap<&str, (&str, &E)> = HashMap::new();
+
+    for element in &versions {
+        let tags = tag_map(element.tags());
+
+        origins.retain(|key, _| tags.contains_key(key));
+
+        for (key, value) in tags {
+            if origins
+                .get(key)
+                .is_none_or(|(old_value, _)| *old_value != value)
+            {
+                origins.insert(key, (value, element));
+            }
+        }
+    }
+
+    let Some(latest) = versions.last() else {
+        return vec![];
+    };
+
+    latest
+        .tags()
+        .iter()
+        .filter_map(|tag| origins.get(tag.k.as_str()).map(|origin| (tag, origin.1)))
+        .map(|(tag, origin)| TagBlame {
+            key: tag.k.clone(),
+            value: tag.v.clone(),
+            version: origin.version(),
+            changeset: origin.changeset(),
+            timestamp: origin.timestamp().into(),
+            user: origin.user().map(String::from),
+        })
+        .collect()
+}
+
+fn tag_map(tags: &[types::Tag]) -> BTreeMap<&str, &str> {
+    tags.iter()
+        .map(|tag| (tag.k.as_str(), tag.v.as_str()))
+        .collect()
+}
+
+fn tag_change_key(change: &TagChange) -> &str {
+    match change {
+        TagChange::Added { key, .. } => key,
+        TagChange::Removed { key, .. } => key,
+        TagChange::Modified { key, .. } => key,
+    }
+}
+
+// Items are matched as a multiset, so a node appearing twice in a closed way only counts
+// as removed when both occurrences are gone
+fn list_change<T: PartialEq>(old: Vec<T>, new: Vec<T>) -> Option<ListChange<T>> {
+    if old == new {
+        return None;
+    }
+
+    let mut unmatched_new = new.iter().map(Some).collect::<Vec<Option<&T>>>();
+    let mut kept_old = vec![];
+    let mut removed_indices = vec![];
+
+    for (index, item) in old.iter().enumerate() {
+        match unmatched_new.iter().position(|n| *n == Some(item)) {
+            Some(position) => {
+                unmatched_new[position] = None;
+                kept_old.push(item);
+            }
+            None => removed_indices.push(index),
+        }
+    }
+
+    let added_indices = unmatched_new
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| item.map(|_| index))
+        .collect::<Vec<usize>>();
+    let kept_new = new
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !added_indices.contains(index))
+        .map(|(_, item)| item)
+        .collect::<Vec<&T>>();
+    let reordered = kept_old != kept_new;
+
+    let removed = take_indices(old, &removed_indices);
+    let added = take_indices(new, &added_indices);
+
+    Some(ListChange {
+        added,
+        removed,
+        reordered,
+    })
+}
+
+fn take_indices<T>(items: Vec<T>, indices: &[usize]) -> Vec<T> {
+    let indices = indices.iter().collect::<HashSet<_>>();
+
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| indices.contains(index))
+        .map(|(_, item)| item)
+        .collect()
+}
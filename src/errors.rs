@@ -1,23 +1,48 @@
 use std::error;
 use std::fmt;
 
+/// Variants only available with some features are added as the features are enabled, so the
+/// enum is non-exhaustive to keep matches compiling across feature sets
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum OpenstreetmapError {
     /// error associated with http request
+    #[cfg(feature = "reqwest")]
     Http(reqwest::Error),
 
+    /// error raised by a custom transport
+    Transport(Box<dyn error::Error + Send + Sync>),
+
     /// error caused by invalid URLs
     Url(url::ParseError),
 
     /// error associated with parsing or serializing
     Serde(quick_xml::de::DeError),
 
+    /// error associated with reading or writing XML documents
+    Xml(quick_xml::Error),
+
+    /// error associated with reading or writing files
+    Io(std::io::Error),
+
+    /// error associated with decoding PBF files
+    #[cfg(feature = "pbf")]
+    Pbf(osmpbf::Error),
+
+    /// element of a PBF file without the version, changeset or timestamp the crate types
+    /// require, as written by tools exporting no metadata
+    #[cfg(feature = "pbf")]
+    PbfMissingMetadata {
+        element_type: crate::types::ElementType,
+        id: i64,
+    },
+
     /// error associated with parsing or serializing query strings
     UrlEncode(serde_urlencoded::ser::Error),
 
     /// client request errors
     Client {
-        code: reqwest::StatusCode,
+        code: http::StatusCode,
         error: String,
     },
 
@@ -32,6 +57,13 @@ pub enum OpenstreetmapError {
 
     /// Missing credentials for operation that requires authentication
     CredentialsNeeded,
+
+    /// invalid configuration file or unknown profile
+    Config(String),
+
+    /// a paged query got a page which neither returned anything new nor moved the next page,
+    /// as from a server ignoring the paging parameters, so following it would never end
+    PagingStalled,
 }
 
 impl error::Error for OpenstreetmapError {}
@@ -42,6 +74,7 @@ impl fmt::Display for OpenstreetmapError {
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl From<reqwest::Error> for OpenstreetmapError {
     fn from(error: reqwest::Error) -> Self {
         OpenstreetmapError::Http(error)
@@ -60,8 +93,75 @@ impl From<quick_xml::de::DeError> for OpenstreetmapError {
     }
 }
 
+impl From<quick_xml::Error> for OpenstreetmapError {
+    fn from(error: quick_xml::Error) -> Self {
+        OpenstreetmapError::Xml(error)
+    }
+}
+
+impl From<std::io::Error> for OpenstreetmapError {
+    fn from(error: std::io::Error) -> Self {
+        OpenstreetmapError::Io(error)
+    }
+}
+
+#[cfg(feature = "pbf")]
+impl From<osmpbf::Error> for OpenstreetmapError {
+    fn from(error: osmpbf::Error) -> Self {
+        OpenstreetmapError::Pbf(error)
+    }
+}
+
 impl From<serde_urlencoded::ser::Error> for OpenstreetmapError {
     fn from(error: serde_urlencoded::ser::Error) -> Self {
         OpenstreetmapError::UrlEncode(error)
     }
 }
+
+#[cfg(feature = "geo-types")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum GeometryError {
+    /// a way or relation references a node which is not available
+    MissingNode(i64),
+
+    /// a node has no coordinates, e.g. because it has been deleted
+    MissingCoordinates(i64),
+
+    /// a relation references a way which is not available
+    MissingWay(i64),
+
+    /// the ways of a multipolygon relation cannot be joined into closed rings
+    UnclosedRing { relation_id: i64 },
+
+    /// an inner ring of a multipolygon relation is not inside any outer ring
+    InnerRingOutside { relation_id: i64 },
+
+    /// the relation is not a multipolygon or a boundary
+    UnsupportedRelation(i64),
+}
+
+#[cfg(feature = "geo-types")]
+impl error::Error for GeometryError {}
+
+#[cfg(feature = "geo-types")]
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::MissingNode(id) => write!(f, "node {id} is missing"),
+            GeometryError::MissingCoordinates(id) => write!(f, "node {id} has no coordinates"),
+            GeometryError::MissingWay(id) => write!(f, "way {id} is missing"),
+            GeometryError::UnclosedRing { relation_id } => {
+                write!(f, "relation {relation_id} has unclosed rings")
+            }
+            GeometryError::InnerRingOutside { relation_id } => {
+                write!(
+                    f,
+                    "relation {relation_id} has an inner ring outside its outer rings"
+                )
+            }
+            GeometryError::UnsupportedRelation(id) => {
+                write!(f, "relation {id} is not a multipolygon")
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::errors::GeometryError;
+use crate::geometry::GeometryBuilder;
+use crate::types;
+
+use ::geojson::{feature::Id, Feature, FeatureCollection, Geometry, JsonObject, Value};
+use serde_json::json;
+use std::collections::HashSet;
+
+fn tags_to_properties(tags: &[types::Tag]) -> JsonObject {
+    tags.iter()
+        .map(|tag| (tag.k.clone(), json!(tag.v)))
+        .collect()
+}
+
+fn element_feature(
+    element_type: types::ElementType,
+    id: i64,
+    geometry: geo_types::Geometry,
+    tags: &[types::Tag],
+) -> Feature {
+    Feature {
+        bbox: None,
+        geometry: Some(Geometry::new(Value::from(&geometry))),
+        id: Some(Id::String(format!("{element_type}/{id}"))),
+        properties: Some(tags_to_properties(tags)),
+        foreign_members: None,
+    }
+}
+
+fn feature_collection(features: Vec<Feature>) -> FeatureCollection {
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+/// A point feature with id `node/<id>` and the node's tags as properties
+pub fn node_to_feature(node: &types::Node) -> Result<Feature, GeometryError> {
+    let point = match (node.lon, node.lat) {
+        (Some(lon), Some(lat)) => geo_types::Point::new(lon, lat),
+        _ => return Err(GeometryError::MissingCoordinates(node.id)),
+    };
+
+    Ok(element_feature(
+        types::ElementType::Node,
+        node.id,
+        point.into(),
+        &node.tags,
+    ))
+}
+
+/// A linestring or polygon feature with id `way/<id>` and the way's tags as properties
+pub fn way_to_feature(
+    builder: &GeometryBuilder,
+    way: &types::Way,
+) -> Result<Feature, GeometryError> {
+    Ok(element_feature(
+        types::ElementType::Way,
+        way.id,
+        builder.way(way)?,
+        &way.tags,
+    ))
+}
+
+/// A multipolygon feature with id `relation/<id>` and the relation's tags as properties
+pub fn relation_to_feature(
+    builder: &GeometryBuilder,
+    relation: &types::Relation,
+) -> Result<Feature, GeometryError> {
+    Ok(element_feature(
+        types::ElementType::Relation,
+        relation.id,
+        builder.relation(relation)?.into(),
+        &relation.tags,
+    ))
+}
+
+/// Converts the map's elements into features. Nodes are only exported when tagged or not
+/// part of any way; elements without a valid geometry, such as relations other than
+/// multipolygons, are left out and returned with the reason alongside the features.
+pub fn map_to_feature_collection(map: &types::Map) -> (FeatureCollection, Vec<GeometryError>) {
+    let builder = GeometryBuilder::from(map);
+    let way_node_ids = map
+        .ways
+        .iter()
+        .flat_map(|w| w.node_refs.iter().map(|n| n.node_id))
+        .collect::<HashSet<i64>>();
+
+    let nodes = map
+        .nodes
+        .iter()
+        .filter(|n| !n.tags.is_empty() || !way_node_ids.contains(&n.id))
+        .map(node_to_feature);
+    let ways = map.ways.iter().map(|w| way_to_feature(&builder, w));
+    let relations = map
+        .relations
+        .iter()
+        .map(|r| relation_to_feature(&builder, r));
+
+    let mut features = vec![];
+    let mut errors = vec![];
+
+    for feature in nodes.chain(ways).chain(relations) {
+        match feature {
+            Ok(feature) => features.push(feature),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    (feature_collection(features), errors)
+}
+
+/// Converts the notes into point features with the note's id as feature id
+pub fn notes_to_feature_collection(notes: &[types::Note]) -> FeatureCollection {
+    let features = notes
+        .iter()
+        .map(|note| {
+            let mut properties = JsonObject::new();
+
+            properties.insert("status".into(), json!(note.status));
+            properties.insert("url".into(), json!(note.url));
+            properties.insert("created_at".into(), json!(note.created_at));
+            properties.insert(
+                "comments".into(),
+                json!(note
+                    .comments
+                    .iter()
+                    .map(|c| json!({
+                        "date": c.date,
+                        "user": c.user,
+                        "action": c.action,
+                        "text": c.text,
+                    }))
+                    .collect::<Vec<_>>()),
+            );
+
+            Feature {
+                bbox: None,
+                geometry: Some(Geometry::new(Value::Point(vec![note.lon, note.lat]))),
+                id: Some(Id::Number(note.id.into())),
+                properties: Some(properties),
+                foreign_members: None,
+            }
+        })
+        .collect();
+
+    feature_collection(features)
+}
+
+/// Converts the changesets into features with their bounding box as polygon; empty
+/// changesets have no bounding box and get a `null` geometry. The tags are nested under
+/// the `tags` property so they cannot clash with the changeset's metadata.
+pub fn changesets_to_feature_collection(changesets: &[types::Changeset]) -> FeatureCollection {
+    let features = changesets
+        .iter()
+        .map(|changeset| {
+            let geometry = changeset.bbox.map(|bbox| {
+                Geometry::new(Value::Polygon(vec![vec![
+                    vec![bbox.left, bbox.bottom],
+                    vec![bbox.right, bbox.bottom],
+                    vec![bbox.right, bbox.top],
+                    vec![bbox.left, bbox.top],
+                    vec![bbox.left, bbox.bottom],
+                ]]))
+            });
+
+            let mut properties = JsonObject::new();
+
+            properties.insert("user".into(), json!(changeset.user));
+            properties.insert("uid".into(), json!(changeset.uid));
+            properties.insert("created_at".into(), json!(changeset.created_at));
+            properties.insert("closed_at".into(), json!(changeset.closed_at));
+            properties.insert("open".into(), json!(changeset.open));
+            properties.insert("comments_count".into(), json!(changeset.comments_count));
+            properties.insert("changes_count".into(), json!(changeset.changes_count));
+            properties.insert("tags".into(), tags_to_properties(&changeset.tags).into());
+
+            Feature {
+                bbox: None,
+                geometry,
+                id: Some(Id::Number(changeset.id.into())),
+                properties: Some(properties),
+                foreign_members: None,
+            }
+        })
+        .collect();
+
+    feature_collection(features)
+}
@@ -0,0 +1,305 @@
+use crate::dataset::Dataset;
+use crate::errors::GeometryError;
+use crate::types;
+
+use geo_types::{Coord, Geometry, LineString, MultiPolygon, Point, Polygon};
+use std::collections::HashMap;
+
+enum AreaValues {
+    All,
+    Except(&'static [&'static str]),
+    Only(&'static [&'static str]),
+}
+
+// Keys which turn a closed way into an area, following the tagging conventions used by the
+// main OSM renderers and editors
+const AREA_KEYS: &[(&str, AreaValues)] = &[
+    (
+        "aeroway",
+        AreaValues::Except(&["jet_bridge", "parking_position", "runway", "taxiway"]),
+    ),
+    ("amenity", AreaValues::All),
+    ("area:highway", AreaValues::All),
+    ("building", AreaValues::All),
+    ("building:part", AreaValues::All),
+    ("craft", AreaValues::All),
+    ("healthcare", AreaValues::All),
+    ("historic", AreaValues::All),
+    ("landuse", AreaValues::All),
+    ("leisure", AreaValues::Except(&["slipway", "track"])),
+    (
+        "man_made",
+        AreaValues::Except(&[
+            "breakwater",
+            "cutline",
+            "dyke",
+            "embankment",
+            "groyne",
+            "pipeline",
+        ]),
+    ),
+    ("military", AreaValues::All),
+    (
+        "natural",
+        AreaValues::Except(&["arete", "cliff", "coastline", "ridge", "tree_row", "valley"]),
+    ),
+    ("office", AreaValues::All),
+    ("place", AreaValues::All),
+    (
+        "power",
+        AreaValues::Only(&["generator", "plant", "substation"]),
+    ),
+    (
+        "public_transport",
+        AreaValues::Only(&["platform", "station"]),
+    ),
+    ("shop", AreaValues::All),
+    ("tourism", AreaValues::All),
+    ("water", AreaValues::All),
+    (
+        "waterway",
+        AreaValues::Only(&["boatyard", "dam", "dock", "riverbank"]),
+    ),
+];
+
+/// Whether a closed way represents an area rather than a closed line, based on its tags
+pub fn is_area(way: &types::Way) -> bool {
+    let is_closed = way.node_refs.len() >= 4
+        && way.node_refs.first().map(|n| n.node_id) == way.node_refs.last().map(|n| n.node_id);
+
+    if !is_closed {
+        return false;
+    }
+
+    if let Some(area) = way.tags.iter().find(|t| t.k == "area") {
+        return area.v != "no";
+    }
+
+    way.tags.iter().any(|tag| {
+        AREA_KEYS.iter().any(|(key, values)| {
+            tag.k == *key
+                && tag.v != "no"
+                && match values {
+                    AreaValues::All => true,
+                    AreaValues::Except(excluded) => !excluded.contains(&tag.v.as_str()),
+                    AreaValues::Only(included) => included.contains(&tag.v.as_str()),
+                }
+        })
+    })
+}
+
+/// Builds `geo-types` geometries out of OSM elements, resolving the node and way references
+/// against the elements it has been created from.
+pub struct GeometryBuilder<'a> {
+    nodes: HashMap<i64, &'a types::Node>,
+    ways: HashMap<i64, &'a types::Way>,
+}
+
+impl<'a> GeometryBuilder<'a> {
+    fn new(
+        nodes: impl Iterator<Item = &'a types::Node>,
+        ways: impl Iterator<Item = &'a types::Way>,
+    ) -> Self {
+        Self {
+            nodes: nodes.map(|n| (n.id, n)).collect(),
+            ways: ways.map(|w| (w.id, w)).collect(),
+        }
+    }
+
+    pub fn node(&self, node: &types::Node) -> Result<Point, GeometryError> {
+        match (node.lon, node.lat) {
+            (Some(lon), Some(lat)) => Ok(Point::new(lon, lat)),
+            _ => Err(GeometryError::MissingCoordinates(node.id)),
+        }
+    }
+
+    /// A polygon if the way is an area, a linestring otherwise
+    pub fn way(&self, way: &types::Way) -> Result<Geometry, GeometryError> {
+        let node_ids = way
+            .node_refs
+            .iter()
+            .map(|n| n.node_id)
+            .collect::<Vec<i64>>();
+        let line = self.line(&node_ids)?;
+
+        if is_area(way) {
+            Ok(Geometry::Polygon(Polygon::new(line, vec![])))
+        } else {
+            Ok(Geometry::LineString(line))
+        }
+    }
+
+    /// Assembles the outer and inner member ways of a `type=multipolygon` or `type=boundary`
+    /// relation into polygons
+    pub fn relation(&self, relation: &types::Relation) -> Result<MultiPolygon, GeometryError> {
+        let is_supported = relation
+            .tags
+            .iter()
+            .any(|t| t.k == "type" && (t.v == "multipolygon" || t.v == "boundary"));
+
+        if !is_supported {
+            return Err(GeometryError::UnsupportedRelation(relation.id));
+        }
+
+        let mut outer_segments = vec![];
+        let mut inner_segments = vec![];
+
+        for member in relation.members.iter() {
+            if member.element_type() != Some(types::ElementType::Way) {
+                continue;
+            }
+
+            let segment = self
+                .ways
+                .get(&member.node_id)
+                .ok_or(GeometryError::MissingWay(member.node_id))?
+                .node_refs
+                .iter()
+                .map(|n| n.node_id)
+                .collect::<Vec<i64>>();
+
+            match member.role.as_str() {
+                "inner" => inner_segments.push(segment),
+                "outer" | "" => outer_segments.push(segment),
+                _ => {}
+            }
+        }
+
+        let unclosed = || GeometryError::UnclosedRing {
+            relation_id: relation.id,
+        };
+        let outers = assemble_rings(outer_segments)
+            .ok_or_else(unclosed)?
+            .iter()
+            .map(|ring| self.line(ring))
+            .collect::<Result<Vec<LineString>, GeometryError>>()?;
+        let inners = assemble_rings(inner_segments)
+            .ok_or_else(unclosed)?
+            .iter()
+            .map(|ring| self.line(ring))
+            .collect::<Result<Vec<LineString>, GeometryError>>()?;
+
+        let mut holes: Vec<Vec<LineString>> = vec![vec![]; outers.len()];
+
+        for inner in inners {
+            // Nested outers are possible, so the smallest outer containing the ring wins
+            let outer_index = inner
+                .0
+                .first()
+                .and_then(|point| {
+                    outers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, outer)| contains(outer, point))
+                        .min_by(|(_, a), (_, b)| area(a).total_cmp(&area(b)))
+                })
+                .map(|(index, _)| index)
+                .ok_or(GeometryError::InnerRingOutside {
+                    relation_id: relation.id,
+                })?;
+
+            holes[outer_index].push(inner);
+        }
+
+        Ok(MultiPolygon::new(
+            outers
+                .into_iter()
+                .zip(holes)
+                .map(|(outer, inners)| Polygon::new(outer, inners))
+                .collect(),
+        ))
+    }
+
+    fn line(&self, node_ids: &[i64]) -> Result<LineString, GeometryError> {
+        node_ids
+            .iter()
+            .map(|node_id| {
+                let node = self
+                    .nodes
+                    .get(node_id)
+                    .ok_or(GeometryError::MissingNode(*node_id))?;
+
+                self.node(node).map(Coord::from)
+            })
+            .collect::<Result<Vec<Coord>, GeometryError>>()
+            .map(LineString::new)
+    }
+}
+
+// Joins the segments end to end into closed rings, reversing them where needed. Returns
+// `None` if any of the rings cannot be closed.
+fn assemble_rings(mut segments: Vec<Vec<i64>>) -> Option<Vec<Vec<i64>>> {
+    segments.retain(|s| !s.is_empty());
+
+    let mut rings = vec![];
+
+    while let Some(mut ring) = segments.pop() {
+        while ring.first() != ring.last() || ring.len() < 4 {
+            let last = *ring.last()?;
+            let index = segments
+                .iter()
+                .position(|s| s.first() == Some(&last) || s.last() == Some(&last))?;
+            let mut segment = segments.swap_remove(index);
+
+            if segment.first() != Some(&last) {
+                segment.reverse();
+            }
+
+            ring.extend(segment.into_iter().skip(1));
+        }
+
+        rings.push(ring);
+    }
+
+    Some(rings)
+}
+
+// Ray casting point-in-polygon test
+fn contains(ring: &LineString, point: &Coord) -> bool {
+    let mut inside = false;
+
+    for line in ring.lines() {
+        let (a, b) = (line.start, line.end);
+
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+// Unsigned area using the shoelace formula
+fn area(ring: &LineString) -> f64 {
+    ring.lines()
+        .map(|line| line.start.x * line.end.y - line.end.x * line.start.y)
+        .sum::<f64>()
+        .abs()
+        / 2.0
+}
+
+impl<'a> From<&'a types::Map> for GeometryBuilder<'a> {
+    fn from(map: &'a types::Map) -> Self {
+        Self::new(map.nodes.iter(), map.ways.iter())
+    }
+}
+
+impl<'a> From<&'a types::WayFull> for GeometryBuilder<'a> {
+    fn from(way_full: &'a types::WayFull) -> Self {
+        Self::new(way_full.nodes.iter(), std::iter::once(&way_full.way))
+    }
+}
+
+impl<'a> From<&'a types::RelationFull> for GeometryBuilder<'a> {
+    fn from(relation_full: &'a types::RelationFull) -> Self {
+        Self::new(relation_full.nodes.iter(), relation_full.ways.iter())
+    }
+}
+
+impl<'a> From<&'a Dataset> for GeometryBuilder<'a> {
+    fn from(dataset: &'a Dataset) -> Self {
+        Self::new(dataset.nodes(), dataset.ways())
+    }
+}
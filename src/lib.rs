@@ -4,40 +4,82 @@ extern crate log;
 extern crate serde_derive;
 
 mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "cassette")]
+pub mod cassette;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod dataset;
+mod dates;
+pub mod diff;
 pub mod errors;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+#[cfg(feature = "geo-types")]
+pub mod geometry;
+#[cfg(feature = "tracing")]
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "pbf")]
+pub mod pbf;
+mod telemetry;
+#[cfg(feature = "test-server")]
+pub mod test_server;
+pub mod transport;
 pub mod types;
+pub mod xml;
 
+use base64::prelude::{Engine, BASE64_STANDARD};
 use errors::OpenstreetmapError;
+use futures::stream::Stream;
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use http::StatusCode;
 use quick_xml::de::from_reader;
 use quick_xml::se::to_string;
-use reqwest::header::CONTENT_TYPE;
-use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
+use std::sync::Arc;
+use tokio::io::AsyncBufRead;
+use tokio_util::io::StreamReader;
 use url::Url;
 
 pub const DEFAULT_VERSION: &str = "0.6";
 
+/// Maximum length of a request URL before multi-element fetches are split into batches
+pub const DEFAULT_MAX_URL_LENGTH: usize = 8000;
+
+/// Number of requests a batched operation runs at the same time
+pub const DEFAULT_CONCURRENCY: usize = 1;
+
 #[derive(Debug, Clone)]
 pub struct Openstreetmap {
     pub host: String,
     api_version: String,
     credentials: types::Credentials,
-    client: reqwest::Client,
+    transport: Arc<dyn transport::Transport>,
+    max_url_length: usize,
+    concurrency: usize,
+    middlewares: Vec<Arc<dyn middleware::Middleware>>,
+    telemetry: telemetry::Telemetry,
 }
 
-#[derive(Debug, Clone)]
-struct RequestOptions {
+/// How the request of an endpoint is built, passed to the middlewares
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
     pub use_version: bool,
     pub use_auth: bool,
+    /// the response never changes, e.g. a historical version of an element
+    pub immutable: bool,
+    /// the response is parsed while it is downloaded, so its body must not be buffered
+    pub streamed: bool,
 }
 
 impl RequestOptions {
     pub fn new() -> Self {
-        Self {
-            use_version: false,
-            use_auth: false,
-        }
+        Self::default()
     }
     pub fn with_version(mut self) -> Self {
         self.use_version = true;
@@ -47,34 +89,89 @@ impl RequestOptions {
         self.use_auth = true;
         self
     }
+    pub fn with_immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+    pub fn with_streamed(mut self) -> Self {
+        self.streamed = true;
+        self
+    }
 }
 
 impl Openstreetmap {
+    #[cfg(feature = "reqwest")]
     pub fn new<T>(host: T, credentials: types::Credentials) -> Self
     where
         T: Into<String>,
     {
-        Openstreetmap {
-            host: host.into(),
-            api_version: DEFAULT_VERSION.into(),
-            credentials,
-            client: reqwest::Client::new(),
-        }
+        Self::from_client(host, credentials, reqwest::Client::new())
     }
 
     /// creates a new instance of a Openstreetmap client using a specified reqwest client
+    #[cfg(feature = "reqwest")]
     pub fn from_client<H>(host: H, credentials: types::Credentials, client: reqwest::Client) -> Self
     where
         H: Into<String>,
+    {
+        Self::from_transport(host, credentials, transport::ReqwestTransport::new(client))
+    }
+
+    /// creates a new instance of a Openstreetmap client with the host and the credentials of a
+    /// profile of the configuration file, see `config`
+    #[cfg(all(feature = "config", feature = "reqwest"))]
+    pub fn from_config(profile: Option<&str>) -> Result<Self, OpenstreetmapError> {
+        let profile = config::Config::load()?.profile(profile)?;
+
+        Ok(Self::new(profile.host, profile.credentials))
+    }
+
+    /// creates a new instance of a Openstreetmap client sending its requests with a
+    /// custom transport
+    pub fn from_transport<H, T>(host: H, credentials: types::Credentials, transport: T) -> Self
+    where
+        H: Into<String>,
+        T: transport::Transport + 'static,
     {
         Openstreetmap {
             host: host.into(),
             api_version: DEFAULT_VERSION.into(),
             credentials,
-            client,
+            transport: Arc::new(transport),
+            max_url_length: DEFAULT_MAX_URL_LENGTH,
+            concurrency: DEFAULT_CONCURRENCY,
+            middlewares: vec![],
+            telemetry: telemetry::Telemetry::default(),
         }
     }
 
+    /// sets the maximum URL length used when splitting multi-element fetches into batches
+    pub fn with_max_url_length(mut self, max_url_length: usize) -> Self {
+        self.max_url_length = max_url_length;
+        self
+    }
+
+    /// sets how many batched requests are run at the same time
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// adds a middleware called around every request after the ones already added
+    pub fn with_middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: middleware::Middleware + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// returns the metrics of the requests sent by this client and its clones
+    #[cfg(feature = "tracing")]
+    pub fn metrics(&self) -> &metrics::Metrics {
+        self.telemetry.metrics()
+    }
+
     #[inline]
     pub async fn versions(&self) -> Result<Vec<String>, OpenstreetmapError> {
         api::versions::Versions::new(self).get().await
@@ -90,6 +187,34 @@ impl Openstreetmap {
         api::map::Map::new(self).get(bbox).await
     }
 
+    /// like `map()` but splits the bounding box into tiles within the maximum area of the
+    /// capabilities, and further when the server rejects a tile for having too many nodes,
+    /// merging the tiles into a single map
+    #[inline]
+    pub async fn map_large(
+        &self,
+        bbox: &types::BoundingBox,
+    ) -> Result<types::Map, OpenstreetmapError> {
+        api::map::Map::new(self).get_large(bbox).await
+    }
+
+    /// like `map()` but yields the nodes, ways and relations while the response is
+    /// downloaded, without holding the whole map in memory; the `<bounds>` of the response,
+    /// if any, are returned alongside the stream
+    #[inline]
+    pub async fn map_stream(
+        &self,
+        bbox: &types::BoundingBox,
+    ) -> Result<
+        (
+            Option<types::BoundingBox>,
+            impl Stream<Item = Result<types::Element, OpenstreetmapError>>,
+        ),
+        OpenstreetmapError,
+    > {
+        api::map::Map::new(self).get_stream(bbox).await
+    }
+
     #[inline]
     pub async fn permissions(&self) -> Result<Vec<types::Permission>, OpenstreetmapError> {
         api::permissions::Permissions::new(self).get().await
@@ -133,9 +258,31 @@ impl Openstreetmap {
         api::changesets::Changesets::new(self).get(query).await
     }
 
+    /// like `changesets()` but pages through all the changesets matching the query, newest
+    /// first, instead of returning only the first page
+    #[inline]
+    pub fn changesets_stream(
+        &self,
+        query: types::ChangesetQueryParams,
+    ) -> impl Stream<Item = Result<types::Changeset, OpenstreetmapError>> {
+        api::changesets::Changesets::new(self).get_stream(query)
+    }
+
+    fn url(&self, endpoint: &str, options: &RequestOptions) -> Result<Url, OpenstreetmapError> {
+        let mut url = Url::parse(&self.host)?.join("api/")?;
+
+        if options.use_version {
+            let version_path = format!("{}/", self.api_version);
+
+            url = url.join(&version_path)?;
+        }
+
+        Ok(url.join(endpoint)?)
+    }
+
     async fn request<S, D>(
         &self,
-        method: reqwest::Method,
+        method: http::Method,
         endpoint: &str,
         body: types::RequestBody<S>,
         options: RequestOptions,
@@ -144,50 +291,122 @@ impl Openstreetmap {
         S: Serialize,
         D: DeserializeOwned,
     {
-        let mut url = Url::parse(&self.host)?.join("api/")?;
+        let call = self.telemetry.start(&method, endpoint);
+        let result = call
+            .instrument(async {
+                let res = self.send(method, endpoint, body, options, &call).await?;
+                let bytes = res.bytes().await?;
 
-        if options.use_version {
-            let version_path = format!("{}/", self.api_version);
+                call.received(bytes.len());
 
-            url = url.join(&version_path)?;
-        }
+                Ok(from_reader(bytes.as_slice())?)
+            })
+            .await;
 
-        url = url.join(endpoint)?;
+        call.finish(&result);
+        result
+    }
+
+    /// like `request()` but parses the elements of the response while it is downloaded
+    /// instead of buffering the whole body
+    async fn request_stream(
+        &self,
+        method: http::Method,
+        endpoint: &str,
+        options: RequestOptions,
+    ) -> Result<xml::AsyncElementReader<impl AsyncBufRead + Unpin>, OpenstreetmapError> {
+        let call = self.telemetry.start(&method, endpoint);
+        let options = options.with_streamed();
+        let result = call
+            .instrument(self.send::<()>(method, endpoint, types::RequestBody::None, options, &call))
+            .await;
+
+        call.finish(&result);
+
+        let body = call.count_bytes(result?.body);
+        Ok(xml::AsyncElementReader::new(StreamReader::new(body)))
+    }
+
+    async fn send<S>(
+        &self,
+        method: http::Method,
+        endpoint: &str,
+        body: types::RequestBody<S>,
+        options: RequestOptions,
+        call: &telemetry::Call,
+    ) -> Result<transport::HttpResponse, OpenstreetmapError>
+    where
+        S: Serialize,
+    {
+        let url = self.url(endpoint, &options)?;
         debug!("url -> {:?}", url);
 
-        let mut builder = self.client.request(method, url);
+        let mut headers = HeaderMap::new();
 
         if options.use_auth {
-            builder = match self.credentials {
+            let authorization = match self.credentials {
                 types::Credentials::Basic(ref user, ref pass) => {
-                    builder.basic_auth(user, Some(pass))
+                    let token = BASE64_STANDARD.encode(format!("{user}:{pass}"));
+
+                    format!("Basic {token}")
                 }
+                types::Credentials::Bearer(ref token) => format!("Bearer {token}"),
                 types::Credentials::None => return Err(OpenstreetmapError::CredentialsNeeded),
             };
+            let mut value = HeaderValue::try_from(authorization)
+                .map_err(|_| OpenstreetmapError::CredentialsNeeded)?;
+
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
         }
 
-        builder = match body {
-            types::RequestBody::Xml(payload) => builder
-                .body(to_string(&payload)?.into_bytes())
-                .header(CONTENT_TYPE, "text/xml"),
-            types::RequestBody::Form(payload) => builder.form(&payload),
-            types::RequestBody::RawForm(payload) => builder
-                .body(payload)
-                .header(CONTENT_TYPE, "application/x-www-form-urlencoded"),
-            types::RequestBody::None => builder,
+        let body = match body {
+            types::RequestBody::Xml(payload) => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/xml"));
+                Some(to_string(&payload)?.into_bytes())
+            }
+            types::RequestBody::Form(payload) => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+                Some(serde_urlencoded::to_string(&payload)?.into_bytes())
+            }
+            types::RequestBody::RawForm(payload) => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+                Some(payload)
+            }
+            types::RequestBody::None => None,
+        };
+
+        let request = middleware::Request {
+            endpoint: endpoint.into(),
+            options,
+            http: transport::HttpRequest {
+                method,
+                url,
+                headers,
+                body,
+            },
         };
+        let res = middleware::Next::new(&self.middlewares, self.transport.as_ref())
+            .run(request)
+            .await?;
 
-        let res = builder.send().await?;
+        call.response(res.status);
 
-        match res.status() {
+        match res.status {
             StatusCode::UNAUTHORIZED => Err(OpenstreetmapError::Unauthorized),
             StatusCode::METHOD_NOT_ALLOWED => Err(OpenstreetmapError::MethodNotAllowed),
             StatusCode::NOT_FOUND => Err(OpenstreetmapError::NotFound),
             client_err if client_err.is_client_error() => Err(OpenstreetmapError::Client {
-                code: res.status(),
+                code: client_err,
                 error: res.text().await?,
             }),
-            _ => Ok(from_reader(res.text().await?.as_bytes())?),
+            _ => Ok(res),
         }
     }
 }
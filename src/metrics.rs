@@ -0,0 +1,244 @@
+//! Counters and latency histograms of the API calls, grouped by operation. An operation is
+//! the endpoint path with the ids replaced by `{id}`, e.g. `node/{id}/history`, so the calls
+//! to the same endpoint are aggregated whatever element they target.
+
+use crate::errors::OpenstreetmapError;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, the last bucket has no upper bound
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    operations: Mutex<HashMap<String, OperationMetrics>>,
+}
+
+impl Metrics {
+    /// returns the metrics of every operation called so far
+    pub fn snapshot(&self) -> HashMap<String, OperationMetrics> {
+        self.operations().clone()
+    }
+
+    /// returns the metrics of an operation, e.g. `node/{id}`
+    pub fn operation(&self, operation: &str) -> Option<OperationMetrics> {
+        self.operations().get(operation).cloned()
+    }
+
+    pub(crate) fn record_request(&self, operation: &str, latency: Duration) {
+        self.update(operation, |metrics| {
+            metrics.requests += 1;
+            metrics.latency.observe(latency);
+        });
+    }
+
+    pub(crate) fn record_error(&self, operation: &str, error: &OpenstreetmapError) {
+        self.update(operation, |metrics| {
+            *metrics.errors.entry(error_kind(error).into()).or_default() += 1;
+        });
+    }
+
+    pub(crate) fn record_bytes(&self, operation: &str, bytes: usize) {
+        self.update(operation, |metrics| {
+            metrics.bytes_received += bytes as u64;
+        });
+    }
+
+    // A panic while the lock was held can only have interrupted a counter update, so the
+    // metrics stay usable rather than poisoning every later call
+    fn operations(&self) -> MutexGuard<'_, HashMap<String, OperationMetrics>> {
+        self.operations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update<F>(&self, operation: &str, update: F)
+    where
+        F: FnOnce(&mut OperationMetrics),
+    {
+        let mut operations = self.operations();
+
+        match operations.get_mut(operation) {
+            Some(metrics) => update(metrics),
+            None => update(operations.entry(operation.into()).or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationMetrics {
+    pub requests: u64,
+    /// number of failed requests by `OpenstreetmapError` variant, e.g. `NotFound`
+    pub errors: HashMap<String, u64>,
+    pub bytes_received: u64,
+    pub latency: Histogram,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: Duration,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, value: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.counts[bucket] += 1;
+        self.sum += value;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(self.sum / count as u32),
+        }
+    }
+
+    /// returns the upper bound of each bucket with the number of values in it, `None` for the
+    /// last bucket
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain([None])
+            .zip(self.counts.iter().copied())
+    }
+
+    /// returns the upper bound of the bucket containing the given quantile, `None` when the
+    /// histogram is empty or the quantile falls in the last bucket
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        self.buckets()
+            .find(|(_, bucket_count)| {
+                seen += bucket_count;
+                seen >= rank
+            })
+            .and_then(|(bound, _)| bound)
+    }
+}
+
+/// The operation of an endpoint: the path without the query, with the numeric segments
+/// replaced by `{id}`
+pub(crate) fn operation(endpoint: &str) -> String {
+    let path = endpoint.split('?').next().unwrap_or_default();
+
+    path.split('/')
+        .map(|segment| match is_id(segment) {
+            true => "{id}",
+            false => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The ids targeted by an endpoint: the first numeric segment of the path, or the ids of a
+/// multi-element fetch like `nodes?nodes=1,2`
+pub(crate) fn ids(endpoint: &str) -> String {
+    let (path, query) = endpoint.split_once('?').unwrap_or((endpoint, ""));
+
+    if let Some(id) = path.split('/').find(|segment| is_id(segment)) {
+        return id.into();
+    }
+
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| matches!(key.as_ref(), "nodes" | "ways" | "relations"))
+        .map(|(_, value)| value.into_owned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn error_kind(error: &OpenstreetmapError) -> &'static str {
+    match error {
+        #[cfg(feature = "reqwest")]
+        OpenstreetmapError::Http(_) => "Http",
+        OpenstreetmapError::Transport(_) => "Transport",
+        OpenstreetmapError::Url(_) => "Url",
+        OpenstreetmapError::Serde(_) => "Serde",
+        OpenstreetmapError::Xml(_) => "Xml",
+        OpenstreetmapError::Io(_) => "Io",
+        #[cfg(feature = "pbf")]
+        OpenstreetmapError::Pbf(_) => "Pbf",
+        #[cfg(feature = "pbf")]
+        OpenstreetmapError::PbfMissingMetadata { .. } => "PbfMissingMetadata",
+        OpenstreetmapError::UrlEncode(_) => "UrlEncode",
+        OpenstreetmapError::Client { .. } => "Client",
+        OpenstreetmapError::Unauthorized => "Unauthorized",
+        OpenstreetmapError::MethodNotAllowed => "MethodNotAllowed",
+        OpenstreetmapError::NotFound => "NotFound",
+        OpenstreetmapError::CredentialsNeeded => "CredentialsNeeded",
+        OpenstreetmapError::Config(_) => "Config",
+        OpenstreetmapError::PagingStalled => "PagingStalled",
+    }
+}
+
+fn is_id(segment: &str) -> bool {
+    !segment.is_empty() && segment.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation() {
+        assert_eq!(operation("node/1234"), "node/{id}");
+        assert_eq!(operation("node/1234/2"), "node/{id}/{id}");
+        assert_eq!(operation("nodes?nodes=1,2"), "nodes");
+        assert_eq!(operation("changeset/42/upload"), "changeset/{id}/upload");
+    }
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(ids("node/1234/history"), "1234");
+        assert_eq!(ids("nodes?nodes=1%2C2"), "1,2");
+        assert_eq!(ids("capabilities"), "");
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_millis(80));
+        histogram.observe(Duration::from_millis(90));
+        histogram.observe(Duration::from_secs(30));
+
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(7_543_750)));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(100)));
+        assert_eq!(histogram.quantile(0.25), Some(Duration::from_millis(10)));
+        assert_eq!(histogram.quantile(1.0), None);
+    }
+}
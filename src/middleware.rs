@@ -0,0 +1,63 @@
+//! Hooks around every request sent by the client. Middlewares are called in the order they
+//! are added with `Openstreetmap::with_middleware()`; each one can change the request, call
+//! the rest of the chain through `Next::run()` and change the response, or answer without
+//! calling the chain at all, e.g. from a cache.
+
+use crate::errors::OpenstreetmapError;
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use crate::RequestOptions;
+
+use futures::future::BoxFuture;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// the endpoint path relative to the API root, e.g. `node/1234/history`
+    pub endpoint: String,
+    pub options: RequestOptions,
+    pub http: HttpRequest,
+}
+
+pub trait Middleware: fmt::Debug + Send + Sync {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>>;
+}
+
+/// The rest of the chain after a middleware, ending with the transport
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn Transport,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middlewares: &'a [Arc<dyn Middleware>],
+        transport: &'a dyn Transport,
+    ) -> Self {
+        Self {
+            middlewares,
+            transport,
+        }
+    }
+
+    pub fn run(self, request: Request) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>> {
+        match self.middlewares.split_first() {
+            Some((middleware, middlewares)) => {
+                middleware.handle(request, Next::new(middlewares, self.transport))
+            }
+            None => self.transport.send(request.http),
+        }
+    }
+}
+
+impl fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next")
+            .field("middlewares", &self.middlewares)
+            .finish_non_exhaustive()
+    }
+}
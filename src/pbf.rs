@@ -0,0 +1,232 @@
+use crate::dates::format_timestamp;
+use crate::errors::OpenstreetmapError;
+use crate::types;
+
+use osmpbf::{BlobDecode, BlobReader, Element, RelMemberType};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Streams the nodes, ways and relations of an `.osm.pbf` file. Only one block of the file,
+/// at most 8000 elements, is decoded at a time. Files written without the version, changeset
+/// and timestamp of the elements fail with [`OpenstreetmapError::PbfMissingMetadata`].
+pub struct PbfReader<R: Read + Send> {
+    blobs: BlobReader<R>,
+    elements: VecDeque<types::Element>,
+}
+
+impl<R: Read + Send> PbfReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            blobs: BlobReader::new(reader),
+            elements: VecDeque::new(),
+        }
+    }
+}
+
+impl PbfReader<BufReader<File>> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, OpenstreetmapError> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: Read + Send> Iterator for PbfReader<R> {
+    type Item = Result<types::Element, OpenstreetmapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.elements.is_empty() {
+            let blob = match self.blobs.next()? {
+                Ok(blob) => blob,
+                Err(error) => return Some(Err(error.into())),
+            };
+
+            match blob.decode() {
+                Ok(BlobDecode::OsmData(block)) => {
+                    for element in block.elements() {
+                        match convert_element(element) {
+                            Ok(element) => self.elements.push_back(element),
+                            Err(error) => return Some(Err(error)),
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+
+        self.elements.pop_front().map(Ok)
+    }
+}
+
+fn convert_element(element: Element) -> Result<types::Element, OpenstreetmapError> {
+    let element = match element {
+        Element::Node(node) => {
+            let info = node.info();
+            let (version, changeset, timestamp) = metadata(
+                types::ElementType::Node,
+                node.id(),
+                info.version(),
+                info.changeset(),
+                info.milli_timestamp(),
+            )?;
+
+            types::Element::Node(types::Node {
+                id: node.id(),
+                visible: info.visible(),
+                version,
+                changeset,
+                timestamp,
+                user: info.user().transpose()?.map(String::from),
+                uid: info.uid().map(|uid| uid as u64),
+                lat: Some(coordinate(node.decimicro_lat())),
+                lon: Some(coordinate(node.decimicro_lon())),
+                tags: node.tags().map(|(k, v)| types::Tag::new(k, v)).collect(),
+            })
+        }
+        Element::DenseNode(node) => {
+            let info = node.info();
+            let (version, changeset, timestamp) = metadata(
+                types::ElementType::Node,
+                node.id(),
+                info.map(|i| i.version()),
+                info.map(|i| i.changeset()),
+                info.map(|i| i.milli_timestamp()),
+            )?;
+
+            types::Element::Node(types::Node {
+                id: node.id(),
+                visible: info.is_none_or(|i| i.visible()),
+                version,
+                changeset,
+                timestamp,
+                user: info.map(|i| i.user()).transpose()?.map(String::from),
+                uid: info.map(|i| i.uid() as u64),
+                lat: Some(coordinate(node.decimicro_lat())),
+                lon: Some(coordinate(node.decimicro_lon())),
+                tags: node.tags().map(|(k, v)| types::Tag::new(k, v)).collect(),
+            })
+        }
+        Element::Way(way) => {
+            let info = way.info();
+            let (version, changeset, timestamp) = metadata(
+                types::ElementType::Way,
+                way.id(),
+                info.version(),
+                info.changeset(),
+                info.milli_timestamp(),
+            )?;
+
+            types::Element::Way(types::Way {
+                id: way.id(),
+                visible: info.visible(),
+                version,
+                changeset,
+                timestamp,
+                user: info.user().transpose()?.unwrap_or_default().into(),
+                uid: info.uid().unwrap_or_default() as u64,
+                node_refs: way
+                    .refs()
+                    .map(|node_id| types::NodeRef { node_id })
+                    .collect(),
+                tags: way.tags().map(|(k, v)| types::Tag::new(k, v)).collect(),
+            })
+        }
+        Element::Relation(relation) => {
+            let info = relation.info();
+            let (version, changeset, timestamp) = metadata(
+                types::ElementType::Relation,
+                relation.id(),
+                info.version(),
+                info.changeset(),
+                info.milli_timestamp(),
+            )?;
+
+            types::Element::Relation(types::Relation {
+                id: relation.id(),
+                visible: info.visible(),
+                version,
+                changeset,
+                timestamp,
+                user: info.user().transpose()?.unwrap_or_default().into(),
+                uid: info.uid().unwrap_or_default() as u64,
+                members: relation
+                    .members()
+                    .map(|member| {
+                        let member_type = match member.member_type {
+                            RelMemberType::Node => types::ElementType::Node,
+                            RelMemberType::Way => types::ElementType::Way,
+                            RelMemberType::Relation => types::ElementType::Relation,
+                        };
+
+                        Ok(types::Member {
+                            member_type: member_type.to_string(),
+                            node_id: member.member_id,
+                            role: member.role()?.into(),
+                        })
+                    })
+                    .collect::<Result<Vec<types::Member>, OpenstreetmapError>>()?,
+                tags: relation
+                    .tags()
+                    .map(|(k, v)| types::Tag::new(k, v))
+                    .collect(),
+            })
+        }
+    };
+
+    Ok(element)
+}
+
+// The crate types require the version, changeset and timestamp, which PBF files written
+// without metadata leave out; such elements are rejected rather than given made-up values
+fn metadata(
+    element_type: types::ElementType,
+    id: i64,
+    version: Option<i32>,
+    changeset: Option<i64>,
+    milli_timestamp: Option<i64>,
+) -> Result<(u64, u64, String), OpenstreetmapError> {
+    match (version, changeset, milli_timestamp) {
+        (Some(version), Some(changeset), Some(milli_timestamp))
+            if version >= 0 && changeset >= 0 =>
+        {
+            Ok((
+                version as u64,
+                changeset as u64,
+                format_timestamp(milli_timestamp.div_euclid(1000)),
+            ))
+        }
+        _ => Err(OpenstreetmapError::PbfMissingMetadata { element_type, id }),
+    }
+}
+
+// Coordinates are stored with 7 decimal digits, the same precision returned by the API
+fn coordinate(decimicro: i32) -> f64 {
+    decimicro as f64 / 10_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::metadata;
+    use crate::errors::OpenstreetmapError;
+    use crate::types::ElementType;
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(
+            metadata(ElementType::Way, 7, Some(2), Some(3), Some(0)).unwrap(),
+            (2, 3, "1970-01-01T00:00:00Z".to_string())
+        );
+        assert_eq!(
+            metadata(ElementType::Node, 1, Some(1), Some(1), Some(1709164800999)).unwrap(),
+            (1, 1, "2024-02-29T00:00:00Z".to_string())
+        );
+        assert!(matches!(
+            metadata(ElementType::Way, -7, Some(2), None, Some(0)),
+            Err(OpenstreetmapError::PbfMissingMetadata {
+                element_type: ElementType::Way,
+                id: -7
+            })
+        ));
+    }
+}
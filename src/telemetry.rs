@@ -0,0 +1,136 @@
+//! Instrumentation of a single API call: a `tracing` span with the endpoint, method, ids,
+//! status and latency, and the matching `metrics::Metrics` updates. Without the `tracing`
+//! feature every method is a no-op.
+
+use crate::errors::OpenstreetmapError;
+use crate::transport::BodyStream;
+
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+use crate::metrics::{self, Metrics};
+#[cfg(feature = "tracing")]
+use futures::stream::TryStreamExt;
+#[cfg(feature = "tracing")]
+use std::sync::Arc;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use tracing::{field, Instrument};
+
+pub(crate) struct Call {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    operation: String,
+    #[cfg(feature = "tracing")]
+    metrics: Arc<Metrics>,
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+/// The metrics shared by the clones of a client
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Telemetry {
+    #[cfg(feature = "tracing")]
+    metrics: Arc<Metrics>,
+}
+
+impl Telemetry {
+    #[cfg(feature = "tracing")]
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn start(&self, method: &http::Method, endpoint: &str) -> Call {
+        let operation = metrics::operation(endpoint);
+        let span = tracing::info_span!(
+            "osm_request",
+            operation = %operation,
+            endpoint,
+            method = %method,
+            ids = %metrics::ids(endpoint),
+            status = field::Empty,
+            latency_ms = field::Empty,
+            bytes = field::Empty,
+            error = field::Empty,
+        );
+
+        Call {
+            span,
+            operation,
+            metrics: self.metrics.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub fn start(&self, _method: &http::Method, _endpoint: &str) -> Call {
+        Call {}
+    }
+}
+
+impl Call {
+    /// runs the future of the call inside its span
+    pub fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        let future = future.instrument(self.span.clone());
+
+        future
+    }
+
+    pub fn response(&self, _status: http::StatusCode) {
+        #[cfg(feature = "tracing")]
+        self.span.record("status", _status.as_u16());
+    }
+
+    pub fn received(&self, _bytes: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("bytes", _bytes);
+            self.metrics.record_bytes(&self.operation, _bytes);
+        }
+    }
+
+    /// counts the bytes of a streamed body while it is read, after the call is finished; the
+    /// `bytes` field of the span holds the total read so far
+    pub fn count_bytes(&self, body: BodyStream) -> BodyStream {
+        #[cfg(feature = "tracing")]
+        {
+            let operation = self.operation.clone();
+            let metrics = self.metrics.clone();
+            let span = self.span.clone();
+            let mut total = 0;
+
+            Box::pin(body.inspect_ok(move |chunk| {
+                total += chunk.len();
+                span.record("bytes", total);
+                metrics.record_bytes(&operation, chunk.len());
+            }))
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        body
+    }
+
+    /// records the latency and the outcome of the call, the latency of streamed responses
+    /// stops when the headers are received
+    pub fn finish<T>(&self, result: &Result<T, OpenstreetmapError>) {
+        #[cfg(feature = "tracing")]
+        {
+            let latency = self.started.elapsed();
+
+            self.span.record("latency_ms", latency.as_millis() as u64);
+            self.metrics.record_request(&self.operation, latency);
+
+            if let Err(error) = result {
+                self.span.record("error", metrics::error_kind(error));
+                self.metrics.record_error(&self.operation, error);
+            }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = result;
+    }
+}
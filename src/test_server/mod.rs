@@ -0,0 +1,178 @@
+//! An in-memory fake of the API 0.6 endpoints called by this crate, served on localhost so
+//! integration tests run without network:
+//!
+//! ```no_run
+//! use openstreetmap_api::test_server::TestServer;
+//! use openstreetmap_api::{types, Openstreetmap};
+//!
+//! # async fn run() -> Result<(), openstreetmap_api::errors::OpenstreetmapError> {
+//! let server = TestServer::start();
+//! server.add_user("mapper", "secret");
+//!
+//! let credentials = types::Credentials::Basic("mapper".into(), "secret".into());
+//! let client = Openstreetmap::new(server.uri(), credentials);
+//! let user = client.user().details().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The server keeps every version of the elements, the changesets, the notes and the users
+//! added with `add_user()`, which authenticate with HTTP Basic credentials. Edits follow the
+//! rules of the real API: elements are written in an open changeset of the user, the
+//! version of a modified or deleted element must be the current one, otherwise the server
+//! answers `409 Conflict`, and an upload is applied entirely or not at all. The elements of
+//! a `create` block get new ids, the ids of the document, usually negative, are placeholders
+//! resolved in the rest of the upload and reported in the `diffResult`.
+
+mod routes;
+mod state;
+
+use futures::channel::oneshot;
+use futures::future::{self, Either};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// A fake API server listening on a random port of localhost, stopped when dropped
+#[derive(Debug)]
+pub struct TestServer {
+    address: SocketAddr,
+    state: Arc<Mutex<state::State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// starts an empty server in a background thread, panicking if it cannot listen
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("cannot bind the test server");
+        let address = listener
+            .local_addr()
+            .expect("cannot read the test server address");
+        let state = Arc::new(Mutex::new(state::State::default()));
+        let (shutdown, stopped) = oneshot::channel();
+
+        listener
+            .set_nonblocking(true)
+            .expect("cannot configure the test server");
+
+        let thread = thread::spawn({
+            let state = state.clone();
+
+            move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_io()
+                    .build()
+                    .expect("cannot start the test server runtime")
+                    .block_on(serve(listener, state, stopped))
+            }
+        });
+
+        Self {
+            address,
+            state,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        }
+    }
+
+    /// the host to create clients with, e.g. `http://127.0.0.1:41234`
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// adds a user authenticating with the display name and password, returning its id
+    pub fn add_user(&self, display_name: &str, password: &str) -> u64 {
+        self.state.lock().unwrap().add_user(display_name, password)
+    }
+
+    /// moves the clock of the server forward, so the changesets, elements and notes written
+    /// afterwards are dated later, e.g. to page through them by date
+    pub fn advance_clock(&self, seconds: u64) {
+        self.state.lock().unwrap().clock += seconds as i64;
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    state: Arc<Mutex<state::State>>,
+    stopped: oneshot::Receiver<()>,
+) {
+    let listener = match tokio::net::TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(error) => {
+            warn!("cannot listen with the test server: {error}");
+            return;
+        }
+    };
+
+    let accept = async {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    warn!("cannot accept a connection to the test server: {error}");
+                    continue;
+                }
+            };
+            let state = state.clone();
+
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let state = state.clone();
+
+                    async move { Ok::<_, Infallible>(respond(&state, request).await) }
+                });
+
+                if let Err(error) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!("test server connection closed -> {error}");
+                }
+            });
+        }
+    };
+
+    futures::pin_mut!(accept);
+
+    if let Either::Right(_) = future::select(accept, stopped).await {
+        debug!("test server stopped");
+    }
+}
+
+async fn respond(
+    state: &Mutex<state::State>,
+    request: hyper::Request<Incoming>,
+) -> hyper::Response<Full<Bytes>> {
+    let (parts, body) = request.into_parts();
+    let body = match body.collect().await {
+        Ok(body) => body.to_bytes().to_vec(),
+        Err(error) => {
+            return routes::reply(Err(state::ApiError::bad_request(error.to_string())));
+        }
+    };
+    let request = routes::ApiRequest::new(parts.method, &parts.uri, &parts.headers, body);
+
+    debug!("test server -> {} {}", request.method, parts.uri);
+
+    routes::reply(routes::handle(&mut state.lock().unwrap(), &request))
+}
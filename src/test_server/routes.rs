@@ -0,0 +1,1034 @@
+use super::state::{self, ApiError, ElementInput, NoteQuery, State, User, Version};
+use crate::dates::{format_note_date, format_timestamp, parse_timestamp};
+use crate::types::{self, ElementType};
+use crate::xml::ElementWriter;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use http::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE};
+use http::{Method, StatusCode, Uri};
+use http_body_util::Full;
+use hyper::body::Bytes;
+use quick_xml::de::from_reader;
+use quick_xml::escape::escape;
+use std::collections::HashMap;
+
+const GENERATOR: &str = "openstreetmap-api test server";
+
+/// Largest area, in square degrees, of a notes request
+const MAXIMUM_NOTE_AREA: f64 = 25.0;
+
+/// Number of changesets returned when the request has no limit
+const DEFAULT_CHANGESET_LIMIT: usize = 100;
+
+const PERMISSIONS: [&str; 6] = [
+    "allow_read_prefs",
+    "allow_write_prefs",
+    "allow_write_diary",
+    "allow_write_api",
+    "allow_read_gpx",
+    "allow_write_notes",
+];
+
+#[derive(Debug)]
+pub(super) struct ApiRequest {
+    pub method: Method,
+    /// the path after `/api/`, without the version and the trailing slash
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl ApiRequest {
+    pub fn new(method: Method, uri: &Uri, headers: &HeaderMap, body: Vec<u8>) -> Self {
+        let path = uri.path().trim_start_matches("/api/");
+        let path = path.strip_prefix(crate::DEFAULT_VERSION).unwrap_or(path);
+
+        Self {
+            method,
+            path: path.trim_matches('/').into(),
+            query: serde_urlencoded::from_str(uri.query().unwrap_or_default()).unwrap_or_default(),
+            authorization: headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            body,
+        }
+    }
+
+    fn query<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, ApiError> {
+        self.query
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ApiError::bad_request(format!("Invalid {name}: {value}")))
+            })
+            .transpose()
+    }
+
+    fn required_query<T: std::str::FromStr>(&self, name: &str) -> Result<T, ApiError> {
+        self.query(name)?
+            .ok_or_else(|| ApiError::bad_request(format!("No {name} was given")))
+    }
+
+    /// a date parameter, either `2024-05-01T12:30:00Z` or `2024-05-01 12:30:00 UTC`
+    fn date(&self, name: &str) -> Result<Option<i64>, ApiError> {
+        self.query
+            .get(name)
+            .map(|value| {
+                parse_timestamp(value)
+                    .ok_or_else(|| ApiError::bad_request("Date is in a wrong format"))
+            })
+            .transpose()
+    }
+
+    fn bbox(&self) -> Result<Option<types::BoundingBox>, ApiError> {
+        let Some(bbox) = self.query.get("bbox") else {
+            return Ok(None);
+        };
+        let coordinates = bbox
+            .split(',')
+            .map(|coordinate| coordinate.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>();
+
+        match coordinates.as_deref() {
+            Ok([left, bottom, right, top]) => Ok(Some(types::BoundingBox {
+                left: *left,
+                bottom: *bottom,
+                right: *right,
+                top: *top,
+            })),
+            _ => Err(ApiError::bad_request(
+                "The parameter bbox is required, and must be of the form min_lon,min_lat,max_lon,max_lat.",
+            )),
+        }
+    }
+
+    /// the user of the Basic credentials, `None` without credentials
+    fn user(&self, state: &State) -> Result<Option<User>, ApiError> {
+        let Some(authorization) = &self.authorization else {
+            return Ok(None);
+        };
+        let credentials = authorization
+            .strip_prefix("Basic ")
+            .and_then(|token| BASE64_STANDARD.decode(token).ok())
+            .and_then(|token| String::from_utf8(token).ok())
+            .ok_or_else(ApiError::unauthorized)?;
+        let (display_name, password) = credentials
+            .split_once(':')
+            .ok_or_else(ApiError::unauthorized)?;
+
+        state
+            .authenticate(display_name, password)
+            .cloned()
+            .map(Some)
+            .ok_or_else(ApiError::unauthorized)
+    }
+
+    fn required_user(&self, state: &State) -> Result<User, ApiError> {
+        self.user(state)?.ok_or_else(ApiError::unauthorized)
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Reply {
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn xml(body: String) -> Self {
+        Self {
+            content_type: "application/xml; charset=utf-8",
+            body,
+        }
+    }
+
+    fn text<T: ToString>(body: T) -> Self {
+        Self {
+            content_type: "text/plain; charset=utf-8",
+            body: body.to_string(),
+        }
+    }
+}
+
+pub(super) fn reply(result: Result<Reply, ApiError>) -> hyper::Response<Full<Bytes>> {
+    let (status, reply) = match result {
+        Ok(reply) => (StatusCode::OK, reply),
+        Err(error) => {
+            debug!("test server error -> {} {}", error.status, error.message);
+            (error.status, Reply::text(error.message))
+        }
+    };
+    let mut response = hyper::Response::new(Full::new(Bytes::from(reply.body)));
+
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        http::HeaderValue::from_static(reply.content_type),
+    );
+    response
+}
+
+pub(super) fn handle(state: &mut State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let segments = request.path.split('/').collect::<Vec<&str>>();
+
+    match (&request.method, segments.as_slice()) {
+        (&Method::GET, ["versions"]) => Ok(versions()),
+        (&Method::GET, ["capabilities"]) => Ok(capabilities()),
+        (&Method::GET, ["permissions"]) => permissions(state, request),
+        (&Method::GET, ["map"]) => map(state, request),
+        (&Method::GET, ["changesets"]) => changesets(state, request),
+        (&Method::PUT, ["changeset", "create"]) => create_changeset(state, request),
+        (&Method::GET, ["changeset", id]) => changeset(state, parse_id(id)?),
+        (&Method::PUT, ["changeset", id]) => update_changeset(state, request, parse_id(id)?),
+        (&Method::PUT, ["changeset", id, "close"]) => {
+            close_changeset(state, request, parse_id(id)?)
+        }
+        (&Method::GET, ["changeset", id, "download"]) => download(state, parse_id(id)?),
+        (&Method::POST, ["changeset", id, "upload"]) => upload(state, request, parse_id(id)?),
+        (&Method::GET, ["notes"]) => notes_by_bbox(state, request),
+        (&Method::POST, ["notes"]) => create_note(state, request),
+        (&Method::GET, ["notes", "search"]) => search_notes(state, request),
+        (&Method::GET, ["notes", id]) => Ok(Reply::xml(osm(&note_xml(state.note(parse_id(id)?)?)))),
+        (&Method::POST, ["notes", id, action]) => {
+            update_note(state, request, parse_id(id)?, action)
+        }
+        (&Method::GET, ["user", "details"]) => user_details(&request.required_user(state)?, state),
+        (&Method::GET, ["user", id]) => {
+            let user = state.user(parse_id(id)?).ok_or_else(ApiError::not_found)?;
+
+            user_details(user, state)
+        }
+        (&Method::GET, ["nodes" | "ways" | "relations"]) => multi_get(state, request),
+        (_, [name, rest @ ..]) => match element_type(name) {
+            Some(element_type) => elements(state, request, element_type, rest),
+            None => Err(ApiError::not_found()),
+        },
+        _ => Err(ApiError::not_found()),
+    }
+}
+
+fn elements(
+    state: &mut State,
+    request: &ApiRequest,
+    element_type: ElementType,
+    segments: &[&str],
+) -> Result<Reply, ApiError> {
+    match (&request.method, segments) {
+        (&Method::PUT, ["create"]) => {
+            let user = request.required_user(state)?;
+            let element = single_input(request, element_type)?;
+            let diff = state.create(&user, element, &mut HashMap::new())?;
+
+            Ok(Reply::text(diff.new_id.unwrap_or_default()))
+        }
+        (&Method::GET, [id]) => {
+            let version = state.visible(element_type, parse_id(id)?)?;
+
+            Ok(Reply::xml(write_elements(None, [version])?))
+        }
+        (&Method::PUT, [id]) => {
+            let user = request.required_user(state)?;
+            let element = single_input(request, element_type)?;
+
+            check_same_id(parse_id(id)?, &element)?;
+
+            let diff = state.modify(&user, element, &HashMap::new())?;
+
+            Ok(Reply::text(diff.new_version.unwrap_or_default()))
+        }
+        (&Method::DELETE, [id]) => {
+            let user = request.required_user(state)?;
+            let id = parse_id(id)?;
+            let element = single_input(request, element_type)?;
+
+            check_same_id(id, &element)?;
+            state.delete(&user, element, false, &HashMap::new())?;
+
+            let version = state
+                .current(element_type, id)
+                .map(|version| version.version);
+
+            Ok(Reply::text(version.unwrap_or_default()))
+        }
+        (&Method::GET, [id, "history"]) => {
+            let versions = state
+                .history(element_type, parse_id(id)?)
+                .ok_or_else(ApiError::not_found)?;
+
+            Ok(Reply::xml(write_elements(None, versions)?))
+        }
+        (&Method::GET, [id, "relations"]) => {
+            let id = parse_id::<i64>(id)?;
+            let relations = state.current_elements().filter(|version| {
+                version.element_type == ElementType::Relation
+                    && version.members.iter().any(|member| {
+                        member.element_type() == Some(element_type) && member.node_id == id
+                    })
+            });
+
+            Ok(Reply::xml(write_elements(None, relations)?))
+        }
+        (&Method::GET, [id, "ways"]) if element_type == ElementType::Node => {
+            let id = parse_id(id)?;
+            let ways = state.current_elements().filter(|version| {
+                version.element_type == ElementType::Way && version.node_refs.contains(&id)
+            });
+
+            Ok(Reply::xml(write_elements(None, ways)?))
+        }
+        (&Method::GET, [id, "full"]) if element_type != ElementType::Node => {
+            full(state, element_type, parse_id(id)?)
+        }
+        (&Method::GET, [id, version]) => {
+            let version = parse_id::<u64>(version)?;
+            let found = state
+                .history(element_type, parse_id(id)?)
+                .and_then(|versions| versions.iter().find(|v| v.version == version))
+                .ok_or_else(ApiError::not_found)?;
+
+            Ok(Reply::xml(write_elements(None, [found])?))
+        }
+        _ => Err(ApiError::not_found()),
+    }
+}
+
+/// The element with the elements it references: the nodes of a way, the node and way
+/// members of a relation with the nodes of those ways
+fn full(state: &State, element_type: ElementType, id: i64) -> Result<Reply, ApiError> {
+    let element = state.visible(element_type, id)?;
+    let mut node_ids = element.node_refs.clone();
+    let mut ways = vec![];
+
+    for member in &element.members {
+        match member.element_type() {
+            Some(ElementType::Node) => node_ids.push(member.node_id),
+            Some(ElementType::Way) => {
+                let way = state.visible(ElementType::Way, member.node_id)?;
+
+                node_ids.extend(way.node_refs.iter().copied());
+                ways.push(way);
+            }
+            _ => {}
+        }
+    }
+
+    node_ids.sort_unstable();
+    node_ids.dedup();
+    ways.sort_by_key(|way| way.id);
+    ways.dedup_by_key(|way| way.id);
+
+    let nodes = node_ids
+        .iter()
+        .map(|node_id| state.visible(ElementType::Node, *node_id))
+        .collect::<Result<Vec<&Version>, ApiError>>()?;
+
+    Ok(Reply::xml(write_elements(
+        None,
+        nodes.into_iter().chain(ways).chain([element]),
+    )?))
+}
+
+/// The elements of the `nodes`, `ways` or `relations` parameter, either the current
+/// version or the requested one, e.g. `1,2v3`
+fn multi_get(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let element_type =
+        element_type(request.path.trim_end_matches('s')).ok_or_else(ApiError::not_found)?;
+    let ids = request
+        .query
+        .get(&request.path)
+        .filter(|ids| !ids.is_empty())
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "The parameter {} is required, and must be of the form {}=id[,id[,id...]].",
+                request.path, request.path
+            ))
+        })?;
+    let mut versions = vec![];
+
+    for id in ids.split(',') {
+        let found = match id.split_once('v') {
+            Some((id, version)) => {
+                let version = parse_id::<u64>(version)?;
+
+                state
+                    .history(element_type, parse_id(id)?)
+                    .and_then(|versions| versions.iter().find(|v| v.version == version))
+            }
+            None => state.current(element_type, parse_id(id)?),
+        };
+
+        versions.push(found.ok_or_else(ApiError::not_found)?);
+    }
+
+    Ok(Reply::xml(write_elements(None, versions)?))
+}
+
+fn map(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let bbox = request
+        .bbox()?
+        .ok_or_else(|| ApiError::bad_request("The parameter bbox is required"))?;
+    let versions = state.map(&bbox)?;
+
+    Ok(Reply::xml(write_elements(Some(&bbox), versions)?))
+}
+
+fn changesets(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let uid = request.query::<u64>("user")?;
+    let display_name = request.query.get("display_name");
+    let open = request.query::<bool>("open")?.unwrap_or_default();
+    let closed = request.query::<bool>("closed")?.unwrap_or_default();
+    let ids = request
+        .query
+        .get("changesets")
+        .map(|ids| {
+            ids.split(',')
+                .map(parse_id)
+                .collect::<Result<Vec<u64>, _>>()
+        })
+        .transpose()?;
+    let limit = request
+        .query::<usize>("limit")?
+        .unwrap_or(DEFAULT_CHANGESET_LIMIT);
+    // closed after the first time and, with a second one, created before it, both inclusive
+    let (closed_after, created_before) = match request.query.get("time") {
+        Some(time) => {
+            let times = time
+                .split(',')
+                .map(parse_timestamp)
+                .collect::<Option<Vec<i64>>>();
+
+            match times.as_deref() {
+                Some([closed_after]) => (Some(*closed_after), None),
+                Some([closed_after, created_before]) => {
+                    (Some(*closed_after), Some(*created_before))
+                }
+                _ => return Err(ApiError::bad_request("bad time range")),
+            }
+        }
+        None => (None, None),
+    };
+    let oldest_first = request
+        .query
+        .get("order")
+        .is_some_and(|order| order == "oldest");
+
+    let mut changesets = state
+        .changesets
+        .values()
+        .filter(|changeset| uid.is_none_or(|uid| changeset.uid == uid))
+        .filter(|changeset| display_name.is_none_or(|name| &changeset.user == name))
+        .filter(|changeset| !open || changeset.closed_at.is_none())
+        .filter(|changeset| !closed || changeset.closed_at.is_some())
+        .filter(|changeset| ids.as_ref().is_none_or(|ids| ids.contains(&changeset.id)))
+        // an open changeset is not closed yet, so it is closed after any time
+        .filter(|changeset| {
+            closed_after.is_none_or(|time| changeset.closed_at.is_none_or(|closed| closed >= time))
+        })
+        .filter(|changeset| created_before.is_none_or(|time| changeset.created_at <= time))
+        .collect::<Vec<_>>();
+
+    changesets.sort_by_key(|changeset| (changeset.created_at, changeset.id));
+
+    if !oldest_first {
+        changesets.reverse();
+    }
+
+    let changesets = changesets
+        .into_iter()
+        .take(limit)
+        .map(changeset_xml)
+        .collect::<String>();
+
+    Ok(Reply::xml(osm(&changesets)))
+}
+
+fn create_changeset(state: &mut State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let user = request.required_user(state)?;
+    let changeset: ChangesetInput = parse(&request.body)?;
+
+    Ok(Reply::text(
+        state.create_changeset(&user, changeset.changeset.tags),
+    ))
+}
+
+fn changeset(state: &State, changeset_id: u64) -> Result<Reply, ApiError> {
+    let changeset = state
+        .changesets
+        .get(&changeset_id)
+        .ok_or_else(ApiError::not_found)?;
+
+    Ok(Reply::xml(osm(&changeset_xml(changeset))))
+}
+
+fn update_changeset(
+    state: &mut State,
+    request: &ApiRequest,
+    changeset_id: u64,
+) -> Result<Reply, ApiError> {
+    let user = request.required_user(state)?;
+    let changeset: ChangesetInput = parse(&request.body)?;
+
+    state.update_changeset(&user, changeset_id, changeset.changeset.tags)?;
+
+    self::changeset(state, changeset_id)
+}
+
+fn close_changeset(
+    state: &mut State,
+    request: &ApiRequest,
+    changeset_id: u64,
+) -> Result<Reply, ApiError> {
+    let user = request.required_user(state)?;
+
+    state.close_changeset(&user, changeset_id)?;
+
+    Ok(Reply::text(""))
+}
+
+/// The osmChange document of the changeset, one block per action
+fn download(state: &State, changeset_id: u64) -> Result<Reply, ApiError> {
+    let changeset = state
+        .changesets
+        .get(&changeset_id)
+        .ok_or_else(ApiError::not_found)?;
+    let mut changes = types::ChangesetChanges {
+        modifications: vec![],
+        creations: vec![],
+        deletions: vec![],
+    };
+    let mut creation = types::Creation {
+        nodes: vec![],
+        ways: vec![],
+        relations: vec![],
+    };
+    let mut modification = types::Modification {
+        nodes: vec![],
+        ways: vec![],
+        relations: vec![],
+    };
+    let mut deletion = types::Deletion {
+        if_unused: false,
+        nodes: vec![],
+        ways: vec![],
+        relations: vec![],
+    };
+
+    for (action, element_type, id, version) in &changeset.changes {
+        let element = state
+            .history(*element_type, *id)
+            .and_then(|versions| versions.iter().find(|v| v.version == *version))
+            .ok_or_else(ApiError::not_found)?
+            .to_element();
+        let (nodes, ways, relations) = match action {
+            types::ChangeAction::Create => (
+                &mut creation.nodes,
+                &mut creation.ways,
+                &mut creation.relations,
+            ),
+            types::ChangeAction::Modify => (
+                &mut modification.nodes,
+                &mut modification.ways,
+                &mut modification.relations,
+            ),
+            types::ChangeAction::Delete => (
+                &mut deletion.nodes,
+                &mut deletion.ways,
+                &mut deletion.relations,
+            ),
+        };
+
+        match element {
+            types::Element::Node(node) => nodes.push(node),
+            types::Element::Way(way) => ways.push(way),
+            types::Element::Relation(relation) => relations.push(relation),
+        }
+    }
+
+    changes.creations.push(creation);
+    changes.modifications.push(modification);
+    changes.deletions.push(deletion);
+
+    let body = crate::xml::write_changes(vec![], &changes, GENERATOR).map_err(internal_error)?;
+
+    Ok(Reply::xml(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn upload(state: &mut State, request: &ApiRequest, changeset_id: u64) -> Result<Reply, ApiError> {
+    let user = request.required_user(state)?;
+    let change: OsmChangeInput = parse(&request.body)?;
+    let blocks = change
+        .blocks
+        .into_iter()
+        .map(|block| {
+            let (action, elements) = match block {
+                BlockInput::Create(elements) => (types::ChangeAction::Create, elements),
+                BlockInput::Modify(elements) => (types::ChangeAction::Modify, elements),
+                BlockInput::Delete(elements) => (types::ChangeAction::Delete, elements),
+            };
+            let items = elements.items.into_iter().map(ElementInput::from).collect();
+
+            (action, elements.if_unused.is_some(), items)
+        })
+        .collect();
+    let diffs = state.upload(&user, changeset_id, blocks)?;
+
+    let mut body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<diffResult version=\"{}\" generator=\"{GENERATOR}\">\n",
+        crate::DEFAULT_VERSION
+    );
+
+    for diff in diffs {
+        match (diff.new_id, diff.new_version) {
+            (Some(new_id), Some(new_version)) => body.push_str(&format!(
+                "<{} old_id=\"{}\" new_id=\"{new_id}\" new_version=\"{new_version}\"/>\n",
+                diff.element_type, diff.old_id
+            )),
+            _ => body.push_str(&format!(
+                "<{} old_id=\"{}\"/>\n",
+                diff.element_type, diff.old_id
+            )),
+        }
+    }
+
+    body.push_str("</diffResult>\n");
+
+    Ok(Reply::xml(body))
+}
+
+fn notes_by_bbox(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let bbox = request
+        .bbox()?
+        .ok_or_else(|| ApiError::bad_request("The parameter bbox is required"))?;
+
+    if (bbox.right - bbox.left) * (bbox.top - bbox.bottom) > MAXIMUM_NOTE_AREA {
+        return Err(ApiError::bad_request(format!(
+            "The maximum bbox size is {MAXIMUM_NOTE_AREA}, and your request was too large. Please request a smaller area."
+        )));
+    }
+
+    // Unlike the search, the notes of an area are always the most recently updated first
+    let query = NoteQuery {
+        bbox: Some(bbox),
+        limit: request.query("limit")?,
+        closed: request.query("closed")?,
+        ..Default::default()
+    };
+
+    Ok(Reply::xml(notes_xml(state.search_notes(&query))))
+}
+
+fn search_notes(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let from = request.date("from")?;
+    let to = request.date("to")?;
+    let query = NoteQuery {
+        bbox: request.bbox()?,
+        text: request.query.get("q").cloned(),
+        limit: request.query("limit")?,
+        closed: request.query("closed")?,
+        user: request.query("user")?,
+        display_name: request.query.get("display_name").cloned(),
+        from,
+        // the window only ends before now when it has a start
+        to: from.map(|_| to.unwrap_or_else(|| state.now())),
+        sort_by_creation: request
+            .query
+            .get("sort")
+            .is_some_and(|sort| sort == "created_at"),
+        oldest_first: request
+            .query
+            .get("order")
+            .is_some_and(|order| order == "oldest"),
+    };
+
+    Ok(Reply::xml(notes_xml(state.search_notes(&query))))
+}
+
+fn create_note(state: &mut State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let user = request.user(state)?;
+    let lat = request.required_query::<f64>("lat")?;
+    let lon = request.required_query::<f64>("lon")?;
+    let text = request.required_query::<String>("text")?;
+    let note_id = state.create_note(lat, lon, &text, user.as_ref());
+
+    Ok(Reply::xml(osm(&note_xml(state.note(note_id)?))))
+}
+
+fn update_note(
+    state: &mut State,
+    request: &ApiRequest,
+    note_id: u64,
+    action: &str,
+) -> Result<Reply, ApiError> {
+    let action = match action {
+        "comment" => types::CommentAction::Commented,
+        "close" => types::CommentAction::Closed,
+        "reopen" => types::CommentAction::Reopened,
+        _ => return Err(ApiError::not_found()),
+    };
+    let user = request.required_user(state)?;
+    let text = request.query.get("text").cloned().unwrap_or_default();
+
+    if action == types::CommentAction::Commented && text.is_empty() {
+        return Err(ApiError::bad_request("No text was given"));
+    }
+
+    state.update_note(note_id, action, &text, &user)?;
+
+    Ok(Reply::xml(osm(&note_xml(state.note(note_id)?))))
+}
+
+fn user_details(user: &User, state: &State) -> Result<Reply, ApiError> {
+    let changesets = state
+        .changesets
+        .values()
+        .filter(|changeset| changeset.uid == user.id)
+        .count();
+
+    Ok(Reply::xml(osm(&format!(
+        concat!(
+            "<user id=\"{}\" display_name=\"{}\" account_created=\"{}\">\n",
+            "<description></description>\n",
+            "<contributor-terms agreed=\"true\" pd=\"false\"/>\n",
+            "<roles/>\n",
+            "<changesets count=\"{}\"/>\n",
+            "<traces count=\"0\"/>\n",
+            "<blocks><received count=\"0\" active=\"0\"/></blocks>\n",
+            "</user>\n"
+        ),
+        user.id,
+        escape(&user.display_name),
+        format_timestamp(user.account_created),
+        changesets
+    ))))
+}
+
+fn permissions(state: &State, request: &ApiRequest) -> Result<Reply, ApiError> {
+    let permissions = match request.user(state)? {
+        Some(_) => PERMISSIONS
+            .iter()
+            .map(|name| format!("<permission name=\"{name}\"/>\n"))
+            .collect(),
+        None => String::new(),
+    };
+
+    Ok(Reply::xml(osm(&format!(
+        "<permissions>\n{permissions}</permissions>\n"
+    ))))
+}
+
+fn versions() -> Reply {
+    Reply::xml(osm(&format!(
+        "<api>\n<version>{}</version>\n</api>\n",
+        crate::DEFAULT_VERSION
+    )))
+}
+
+fn capabilities() -> Reply {
+    Reply::xml(osm(&format!(
+        concat!(
+            "<api>\n",
+            "<version minimum=\"{version}\" maximum=\"{version}\"/>\n",
+            "<area maximum=\"{area}\"/>\n",
+            "<note_area maximum=\"{note_area}\"/>\n",
+            "<tracepoints per_page=\"5000\"/>\n",
+            "<waynodes maximum=\"{waynodes}\"/>\n",
+            "<relationmembers maximum=\"32000\"/>\n",
+            "<changesets maximum_elements=\"10000\" default_query_limit=\"{changesets}\" maximum_query_limit=\"{changesets}\"/>\n",
+            "<notes default_query_limit=\"100\" maximum_query_limit=\"10000\"/>\n",
+            "<timeout seconds=\"300\"/>\n",
+            "<status database=\"online\" api=\"online\" gpx=\"online\"/>\n",
+            "</api>\n",
+            "<policy>\n",
+            "<imagery>\n",
+            "<blacklist regex=\".*\\.google(apis)?\\..*/.*\"/>\n",
+            "</imagery>\n",
+            "</policy>\n"
+        ),
+        version = crate::DEFAULT_VERSION,
+        area = state::MAXIMUM_AREA,
+        note_area = MAXIMUM_NOTE_AREA,
+        waynodes = state::MAXIMUM_WAYNODES,
+        changesets = DEFAULT_CHANGESET_LIMIT,
+    )))
+}
+
+fn osm(content: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<osm version=\"{}\" generator=\"{GENERATOR}\">\n{content}</osm>\n",
+        crate::DEFAULT_VERSION
+    )
+}
+
+fn tags_xml(tags: &[types::Tag]) -> String {
+    tags.iter()
+        .map(|tag| format!("<tag k=\"{}\" v=\"{}\"/>\n", escape(&tag.k), escape(&tag.v)))
+        .collect()
+}
+
+fn changeset_xml(changeset: &state::Changeset) -> String {
+    let mut attributes = format!(
+        "id=\"{}\" user=\"{}\" uid=\"{}\" created_at=\"{}\" open=\"{}\" comments_count=\"0\" changes_count=\"{}\"",
+        changeset.id,
+        escape(&changeset.user),
+        changeset.uid,
+        format_timestamp(changeset.created_at),
+        changeset.closed_at.is_none(),
+        changeset.changes.len()
+    );
+
+    if let Some(closed_at) = changeset.closed_at {
+        attributes.push_str(&format!(" closed_at=\"{}\"", format_timestamp(closed_at)));
+    }
+
+    if let Some(bbox) = changeset.bbox {
+        attributes.push_str(&format!(
+            " min_lat=\"{}\" min_lon=\"{}\" max_lat=\"{}\" max_lon=\"{}\"",
+            bbox.bottom, bbox.left, bbox.top, bbox.right
+        ));
+    }
+
+    format!(
+        "<changeset {attributes}>\n{}</changeset>\n",
+        tags_xml(&changeset.tags)
+    )
+}
+
+fn note_xml(note: &state::Note) -> String {
+    let url = format!("https://www.openstreetmap.org/api/0.6/notes/{}", note.id);
+    let comments = note
+        .comments
+        .iter()
+        .map(|comment| {
+            let user = match &comment.user {
+                Some((uid, name)) => format!(
+                    "<uid>{uid}</uid>\n<user>{}</user>\n<user_url>https://www.openstreetmap.org/user/{}</user_url>\n",
+                    escape(name),
+                    escape(name)
+                ),
+                None => String::new(),
+            };
+            let action = match comment.action {
+                types::CommentAction::Opened => "opened",
+                types::CommentAction::Commented => "commented",
+                types::CommentAction::Closed => "closed",
+                types::CommentAction::Reopened => "reopened",
+                types::CommentAction::Hidden => "hidden",
+                types::CommentAction::Unknown => "unknown",
+            };
+
+            format!(
+                "<comment>\n<date>{}</date>\n{user}<action>{action}</action>\n<text>{}</text>\n<html>&lt;p&gt;{}&lt;/p&gt;</html>\n</comment>\n",
+                format_note_date(comment.date),
+                escape(&comment.text),
+                escape(&escape(&comment.text)),
+            )
+        })
+        .collect::<String>();
+    let status = match note.status {
+        types::NoteStatus::Open => "open",
+        types::NoteStatus::Closed => "closed",
+        types::NoteStatus::Hidden => "hidden",
+        types::NoteStatus::Unknown => "unknown",
+    };
+    let closed_at = match note.closed_at {
+        Some(closed_at) => format!(
+            "<date_closed>{}</date_closed>\n",
+            format_note_date(closed_at)
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        "<note lon=\"{}\" lat=\"{}\">\n<id>{}</id>\n<url>{url}</url>\n<comment_url>{url}/comment</comment_url>\n<close_url>{url}/close</close_url>\n<date_created>{}</date_created>\n<status>{status}</status>\n{closed_at}<comments>\n{comments}</comments>\n</note>\n",
+        note.lon,
+        note.lat,
+        note.id,
+        format_note_date(note.created_at),
+    )
+}
+
+fn notes_xml(notes: Vec<&state::Note>) -> String {
+    osm(&notes.into_iter().map(note_xml).collect::<String>())
+}
+
+fn write_elements<'a, I>(bbox: Option<&types::BoundingBox>, versions: I) -> Result<String, ApiError>
+where
+    I: IntoIterator<Item = &'a Version>,
+{
+    let mut writer = ElementWriter::new(vec![], GENERATOR, bbox).map_err(internal_error)?;
+
+    for version in versions {
+        writer
+            .write(&version.to_element())
+            .map_err(internal_error)?;
+    }
+
+    let body = writer.finish().map_err(internal_error)?;
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn internal_error<E: ToString>(error: E) -> ApiError {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+}
+
+fn element_type(name: &str) -> Option<ElementType> {
+    match name {
+        "node" => Some(ElementType::Node),
+        "way" => Some(ElementType::Way),
+        "relation" => Some(ElementType::Relation),
+        _ => None,
+    }
+}
+
+fn parse_id<T: std::str::FromStr>(id: &str) -> Result<T, ApiError> {
+    id.parse()
+        .map_err(|_| ApiError::bad_request(format!("Invalid id: {id}")))
+}
+
+fn parse<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    from_reader(body).map_err(|error| {
+        ApiError::bad_request(format!("Cannot parse valid XML from the request: {error}"))
+    })
+}
+
+/// the only element of an `osm` document, which must be of the type of the endpoint
+fn single_input(request: &ApiRequest, element_type: ElementType) -> Result<ElementInput, ApiError> {
+    let document: OsmInput = parse(&request.body)?;
+    let element = document
+        .items
+        .into_iter()
+        .next()
+        .map(ElementInput::from)
+        .filter(|element| element.element_type == element_type)
+        .ok_or_else(|| {
+            ApiError::bad_request(format!("Cannot parse valid {element_type} from xml string"))
+        })?;
+
+    Ok(element)
+}
+
+fn check_same_id(id: i64, element: &ElementInput) -> Result<(), ApiError> {
+    if element.id != id {
+        return Err(ApiError::bad_request(format!(
+            "The id in the url ({id}) is not the same as provided in the xml ({})",
+            element.id
+        )));
+    }
+
+    Ok(())
+}
+
+// The documents sent by the clients, the ids may be negative placeholders
+
+#[derive(Debug, Deserialize)]
+struct ChangesetTagsInput {
+    #[serde(rename = "tag", default)]
+    tags: Vec<types::Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangesetInput {
+    changeset: ChangesetTagsInput,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeRefInput {
+    #[serde(rename = "@ref")]
+    node_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberInput {
+    #[serde(rename = "@type")]
+    member_type: String,
+    #[serde(rename = "@ref")]
+    member_id: i64,
+    #[serde(rename = "@role", default)]
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ElementFields {
+    #[serde(rename = "@id")]
+    id: i64,
+    #[serde(rename = "@version")]
+    version: Option<u64>,
+    #[serde(rename = "@changeset")]
+    changeset: u64,
+    #[serde(rename = "@lat")]
+    lat: Option<f64>,
+    #[serde(rename = "@lon")]
+    lon: Option<f64>,
+    #[serde(rename = "tag", default)]
+    tags: Vec<types::Tag>,
+    #[serde(rename = "nd", default)]
+    node_refs: Vec<NodeRefInput>,
+    #[serde(rename = "member", default)]
+    members: Vec<MemberInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ItemInput {
+    Node(ElementFields),
+    Way(ElementFields),
+    Relation(ElementFields),
+}
+
+impl From<ItemInput> for ElementInput {
+    fn from(value: ItemInput) -> ElementInput {
+        let (element_type, fields) = match value {
+            ItemInput::Node(fields) => (ElementType::Node, fields),
+            ItemInput::Way(fields) => (ElementType::Way, fields),
+            ItemInput::Relation(fields) => (ElementType::Relation, fields),
+        };
+
+        ElementInput {
+            element_type,
+            id: fields.id,
+            version: fields.version,
+            changeset: fields.changeset,
+            lat: fields.lat,
+            lon: fields.lon,
+            tags: fields.tags,
+            node_refs: fields.node_refs.into_iter().map(|nd| nd.node_id).collect(),
+            members: fields
+                .members
+                .into_iter()
+                .map(|member| (member.member_type, member.member_id, member.role))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OsmInput {
+    #[serde(rename = "$value", default)]
+    items: Vec<ItemInput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockElementsInput {
+    #[serde(rename = "@if-unused")]
+    if_unused: Option<String>,
+    #[serde(rename = "$value", default)]
+    items: Vec<ItemInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BlockInput {
+    Create(BlockElementsInput),
+    Modify(BlockElementsInput),
+    Delete(BlockElementsInput),
+}
+
+#[derive(Debug, Deserialize)]
+struct OsmChangeInput {
+    #[serde(rename = "$value", default)]
+    blocks: Vec<BlockInput>,
+}
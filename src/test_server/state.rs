@@ -0,0 +1,921 @@
+use crate::dates::{format_note_date, format_timestamp};
+use crate::types::{self, ElementType};
+
+use http::StatusCode;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Largest area, in square degrees, of a map request
+pub(super) const MAXIMUM_AREA: f64 = 0.25;
+
+/// Largest number of nodes of a way
+pub(super) const MAXIMUM_WAYNODES: usize = 2000;
+
+/// Number of notes returned when the request has no limit
+const DEFAULT_NOTE_LIMIT: usize = 100;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new<M: Into<String>>(status: StatusCode, message: M) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, "")
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "Couldn't authenticate you")
+    }
+
+    pub fn bad_request<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn conflict<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    pub fn precondition_failed<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::PRECONDITION_FAILED, message)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct User {
+    pub id: u64,
+    pub display_name: String,
+    pub password: String,
+    pub account_created: i64,
+}
+
+/// A version of a node, way or relation; the fields of the other element types are empty
+#[derive(Debug, Clone)]
+pub(super) struct Version {
+    pub element_type: ElementType,
+    pub id: i64,
+    pub version: u64,
+    pub visible: bool,
+    pub changeset: u64,
+    pub timestamp: i64,
+    pub uid: u64,
+    pub user: String,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub tags: Vec<types::Tag>,
+    pub node_refs: Vec<i64>,
+    pub members: Vec<types::Member>,
+}
+
+impl Version {
+    pub fn to_element(&self) -> types::Element {
+        match self.element_type {
+            ElementType::Node => types::Element::Node(types::Node {
+                id: self.id,
+                visible: self.visible,
+                version: self.version,
+                changeset: self.changeset,
+                timestamp: format_timestamp(self.timestamp),
+                user: Some(self.user.clone()),
+                uid: Some(self.uid),
+                lat: self.lat,
+                lon: self.lon,
+                tags: self.tags.clone(),
+            }),
+            ElementType::Way => types::Element::Way(types::Way {
+                id: self.id,
+                visible: self.visible,
+                version: self.version,
+                changeset: self.changeset,
+                timestamp: format_timestamp(self.timestamp),
+                user: self.user.clone(),
+                uid: self.uid,
+                node_refs: self
+                    .node_refs
+                    .iter()
+                    .map(|node_id| types::NodeRef { node_id: *node_id })
+                    .collect(),
+                tags: self.tags.clone(),
+            }),
+            ElementType::Relation => types::Element::Relation(types::Relation {
+                id: self.id,
+                visible: self.visible,
+                version: self.version,
+                changeset: self.changeset,
+                timestamp: format_timestamp(self.timestamp),
+                user: self.user.clone(),
+                uid: self.uid,
+                tags: self.tags.clone(),
+                members: self.members.clone(),
+            }),
+        }
+    }
+}
+
+/// An element sent by a client, ids may be negative placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ElementInput {
+    pub element_type: ElementType,
+    pub id: i64,
+    pub version: Option<u64>,
+    pub changeset: u64,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub tags: Vec<types::Tag>,
+    pub node_refs: Vec<i64>,
+    /// member type, reference and role
+    pub members: Vec<(String, i64, String)>,
+}
+
+/// The outcome of an edit, one line of a `diffResult`
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Diff {
+    pub element_type: ElementType,
+    pub old_id: i64,
+    /// missing for deleted elements
+    pub new_id: Option<i64>,
+    pub new_version: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Changeset {
+    pub id: u64,
+    pub uid: u64,
+    pub user: String,
+    pub created_at: i64,
+    pub closed_at: Option<i64>,
+    pub tags: Vec<types::Tag>,
+    /// the action, type, id and version of every element written in the changeset
+    pub changes: Vec<(types::ChangeAction, ElementType, i64, u64)>,
+    pub bbox: Option<types::BoundingBox>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct NoteComment {
+    pub date: i64,
+    /// `None` for anonymous comments
+    pub user: Option<(u64, String)>,
+    pub action: types::CommentAction,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Note {
+    pub id: u64,
+    pub lat: f64,
+    pub lon: f64,
+    pub created_at: i64,
+    pub closed_at: Option<i64>,
+    pub status: types::NoteStatus,
+    pub comments: Vec<NoteComment>,
+}
+
+impl Note {
+    /// the date of the last comment, every update of a note adds one
+    pub fn updated_at(&self) -> i64 {
+        self.comments
+            .last()
+            .map_or(self.created_at, |comment| comment.date)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct NoteQuery {
+    pub bbox: Option<types::BoundingBox>,
+    pub text: Option<String>,
+    pub limit: Option<usize>,
+    /// days a closed note stays listed, all the closed notes when negative
+    pub closed: Option<i64>,
+    /// a user who commented the note
+    pub user: Option<u64>,
+    pub display_name: Option<String>,
+    /// inclusive bounds of the sort date
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// sorted by creation date rather than by the date of the last update
+    pub sort_by_creation: bool,
+    pub oldest_first: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct State {
+    pub users: Vec<User>,
+    /// every version of every element, in version order
+    pub elements: BTreeMap<(ElementType, i64), Vec<Version>>,
+    pub changesets: BTreeMap<u64, Changeset>,
+    pub notes: BTreeMap<u64, Note>,
+    last_ids: HashMap<ElementType, i64>,
+    /// seconds the clock of the server is ahead of the system clock
+    pub clock: i64,
+}
+
+impl State {
+    /// the current time of the server, in seconds since the Unix epoch
+    pub fn now(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            + self.clock
+    }
+
+    pub fn add_user(&mut self, display_name: &str, password: &str) -> u64 {
+        let id = self.users.len() as u64 + 1;
+
+        self.users.push(User {
+            id,
+            display_name: display_name.into(),
+            password: password.into(),
+            account_created: self.now(),
+        });
+
+        id
+    }
+
+    pub fn authenticate(&self, display_name: &str, password: &str) -> Option<&User> {
+        self.users
+            .iter()
+            .find(|user| user.display_name == display_name && user.password == password)
+    }
+
+    pub fn user(&self, user_id: u64) -> Option<&User> {
+        self.users.iter().find(|user| user.id == user_id)
+    }
+
+    pub fn current(&self, element_type: ElementType, id: i64) -> Option<&Version> {
+        self.elements.get(&(element_type, id))?.last()
+    }
+
+    pub fn history(&self, element_type: ElementType, id: i64) -> Option<&Vec<Version>> {
+        self.elements.get(&(element_type, id))
+    }
+
+    /// the current version of the element, an error if it does not exist or is deleted
+    pub fn visible(&self, element_type: ElementType, id: i64) -> Result<&Version, ApiError> {
+        match self.current(element_type, id) {
+            Some(version) if version.visible => Ok(version),
+            Some(_) => Err(ApiError::new(StatusCode::GONE, "")),
+            None => Err(ApiError::not_found()),
+        }
+    }
+
+    pub fn current_elements(&self) -> impl Iterator<Item = &Version> {
+        self.elements
+            .values()
+            .filter_map(|versions| versions.last())
+            .filter(|version| version.visible)
+    }
+
+    pub fn create_changeset(&mut self, user: &User, tags: Vec<types::Tag>) -> u64 {
+        let id = self.changesets.len() as u64 + 1;
+
+        self.changesets.insert(
+            id,
+            Changeset {
+                id,
+                uid: user.id,
+                user: user.display_name.clone(),
+                created_at: self.now(),
+                closed_at: None,
+                tags,
+                changes: vec![],
+                bbox: None,
+            },
+        );
+
+        id
+    }
+
+    /// the changeset, if it is open and owned by the user
+    pub fn open_changeset(&self, user: &User, changeset_id: u64) -> Result<&Changeset, ApiError> {
+        let changeset = self
+            .changesets
+            .get(&changeset_id)
+            .ok_or_else(ApiError::not_found)?;
+
+        if changeset.uid != user.id {
+            return Err(ApiError::conflict("The user doesn't own that changeset"));
+        }
+
+        if let Some(closed_at) = changeset.closed_at {
+            return Err(ApiError::conflict(format!(
+                "The changeset {changeset_id} was closed at {}",
+                format_timestamp(closed_at)
+            )));
+        }
+
+        Ok(changeset)
+    }
+
+    pub fn update_changeset(
+        &mut self,
+        user: &User,
+        changeset_id: u64,
+        tags: Vec<types::Tag>,
+    ) -> Result<(), ApiError> {
+        self.open_changeset(user, changeset_id)?;
+        self.changesets.get_mut(&changeset_id).unwrap().tags = tags;
+
+        Ok(())
+    }
+
+    pub fn close_changeset(&mut self, user: &User, changeset_id: u64) -> Result<(), ApiError> {
+        self.open_changeset(user, changeset_id)?;
+        self.changesets.get_mut(&changeset_id).unwrap().closed_at = Some(self.now());
+
+        Ok(())
+    }
+
+    /// Applies the blocks of an osmChange document in order. Any error leaves the state
+    /// untouched, like the transaction of the real API.
+    pub fn upload(
+        &mut self,
+        user: &User,
+        changeset_id: u64,
+        blocks: Vec<(types::ChangeAction, bool, Vec<ElementInput>)>,
+    ) -> Result<Vec<Diff>, ApiError> {
+        let mut state = self.clone();
+        let mut placeholders = HashMap::new();
+        let mut diffs = vec![];
+
+        state.open_changeset(user, changeset_id)?;
+
+        for (action, if_unused, elements) in blocks {
+            for element in elements {
+                if element.changeset != changeset_id {
+                    return Err(ApiError::conflict(format!(
+                        "Changeset mismatch: Provided {} but only {changeset_id} is allowed",
+                        element.changeset
+                    )));
+                }
+
+                let diff = match action {
+                    types::ChangeAction::Create => {
+                        state.create(user, element, &mut placeholders)?
+                    }
+                    types::ChangeAction::Modify => state.modify(user, element, &placeholders)?,
+                    types::ChangeAction::Delete => {
+                        state.delete(user, element, if_unused, &placeholders)?
+                    }
+                };
+
+                diffs.push(diff);
+            }
+        }
+
+        *self = state;
+
+        Ok(diffs)
+    }
+
+    /// Creates the element with a new id. The id of the input is a placeholder: the later
+    /// references to it in the same upload are resolved to the new id.
+    pub fn create(
+        &mut self,
+        user: &User,
+        element: ElementInput,
+        placeholders: &mut HashMap<(ElementType, i64), i64>,
+    ) -> Result<Diff, ApiError> {
+        self.open_changeset(user, element.changeset)?;
+
+        let last_id = self.last_ids.entry(element.element_type).or_default();
+        *last_id += 1;
+        let id = *last_id;
+        let version = self.new_version(user, &element, id, 1, placeholders)?;
+
+        placeholders.insert((element.element_type, element.id), id);
+        self.write(types::ChangeAction::Create, version);
+
+        Ok(Diff {
+            element_type: element.element_type,
+            old_id: element.id,
+            new_id: Some(id),
+            new_version: Some(1),
+        })
+    }
+
+    pub fn modify(
+        &mut self,
+        user: &User,
+        element: ElementInput,
+        placeholders: &HashMap<(ElementType, i64), i64>,
+    ) -> Result<Diff, ApiError> {
+        let id = resolve(placeholders, element.element_type, element.id)?;
+        let current = self.current_for_edit(user, &element, id)?;
+        let version = self.new_version(user, &element, id, current + 1, placeholders)?;
+
+        self.write(types::ChangeAction::Modify, version);
+
+        Ok(Diff {
+            element_type: element.element_type,
+            old_id: element.id,
+            new_id: Some(id),
+            new_version: Some(current + 1),
+        })
+    }
+
+    /// Deletes the element, or leaves it alone when it is still used and `if_unused` is set
+    pub fn delete(
+        &mut self,
+        user: &User,
+        element: ElementInput,
+        if_unused: bool,
+        placeholders: &HashMap<(ElementType, i64), i64>,
+    ) -> Result<Diff, ApiError> {
+        let id = resolve(placeholders, element.element_type, element.id)?;
+        let current = self.current_for_edit(user, &element, id)?;
+
+        if let Some(message) = self.used_by(element.element_type, id) {
+            if if_unused {
+                return Ok(Diff {
+                    element_type: element.element_type,
+                    old_id: element.id,
+                    new_id: Some(id),
+                    new_version: Some(current),
+                });
+            }
+
+            return Err(ApiError::precondition_failed(message));
+        }
+
+        let version = Version {
+            element_type: element.element_type,
+            id,
+            version: current + 1,
+            visible: false,
+            changeset: element.changeset,
+            timestamp: self.now(),
+            uid: user.id,
+            user: user.display_name.clone(),
+            lat: None,
+            lon: None,
+            tags: vec![],
+            node_refs: vec![],
+            members: vec![],
+        };
+
+        self.write(types::ChangeAction::Delete, version);
+
+        Ok(Diff {
+            element_type: element.element_type,
+            old_id: element.id,
+            new_id: None,
+            new_version: None,
+        })
+    }
+
+    /// checks the element can be edited, returning its current version
+    fn current_for_edit(
+        &self,
+        user: &User,
+        element: &ElementInput,
+        id: i64,
+    ) -> Result<u64, ApiError> {
+        let element_type = element.element_type;
+        let current = self
+            .current(element_type, id)
+            .ok_or_else(ApiError::not_found)?;
+
+        if !current.visible {
+            return Err(ApiError::new(
+                StatusCode::GONE,
+                format!("The {element_type} with the id {id} has already been deleted"),
+            ));
+        }
+
+        let provided = element.version.unwrap_or_default();
+
+        if provided != current.version {
+            return Err(ApiError::conflict(format!(
+                "Version mismatch: Provided {provided}, server had: {} of {} {id}",
+                current.version,
+                capitalize(element_type.as_str())
+            )));
+        }
+
+        self.open_changeset(user, element.changeset)?;
+
+        Ok(current.version)
+    }
+
+    /// builds a new version of the element, checking the references are visible elements
+    fn new_version(
+        &self,
+        user: &User,
+        element: &ElementInput,
+        id: i64,
+        version: u64,
+        placeholders: &HashMap<(ElementType, i64), i64>,
+    ) -> Result<Version, ApiError> {
+        let element_type = element.element_type;
+
+        if element_type == ElementType::Node && (element.lat.is_none() || element.lon.is_none()) {
+            return Err(ApiError::bad_request("Node must have lat and lon"));
+        }
+
+        let mut node_refs = vec![];
+        let mut missing = vec![];
+
+        for node_ref in &element.node_refs {
+            match resolve(placeholders, ElementType::Node, *node_ref) {
+                Ok(node_id) if self.visible(ElementType::Node, node_id).is_ok() => {
+                    node_refs.push(node_id)
+                }
+                _ => missing.push(node_ref.to_string()),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(ApiError::precondition_failed(format!(
+                "Way {id} requires the nodes with id in ({}), which either do not exist, or are not visible.",
+                missing.join(",")
+            )));
+        }
+
+        if element_type == ElementType::Way {
+            if node_refs.is_empty() {
+                return Err(ApiError::precondition_failed(format!(
+                    "Way {id} must have at least one node"
+                )));
+            }
+
+            if node_refs.len() > MAXIMUM_WAYNODES {
+                return Err(ApiError::bad_request(format!(
+                    "You tried to add {} nodes to way {id}, however only {MAXIMUM_WAYNODES} are allowed",
+                    node_refs.len()
+                )));
+            }
+        }
+
+        let mut members = vec![];
+
+        for (member_type, member_ref, role) in &element.members {
+            let member = types::Member {
+                member_type: member_type.clone(),
+                node_id: 0,
+                role: role.clone(),
+            };
+            let member_id = member
+                .element_type()
+                .ok_or_else(|| ApiError::bad_request(format!("Unknown member type {member_type}")))
+                .and_then(|member_element_type| {
+                    let member_id = resolve(placeholders, member_element_type, *member_ref)?;
+
+                    self.visible(member_element_type, member_id)?;
+                    Ok(member_id)
+                })
+                .map_err(|_| {
+                    ApiError::precondition_failed(format!(
+                        "Relation with id {id} cannot be saved due to {} with id {member_ref}",
+                        capitalize(member_type)
+                    ))
+                })?;
+
+            members.push(types::Member {
+                node_id: member_id,
+                ..member
+            });
+        }
+
+        Ok(Version {
+            element_type,
+            id,
+            version,
+            visible: true,
+            changeset: element.changeset,
+            timestamp: self.now(),
+            uid: user.id,
+            user: user.display_name.clone(),
+            lat: element.lat,
+            lon: element.lon,
+            tags: element.tags.clone(),
+            node_refs,
+            members,
+        })
+    }
+
+    /// the error message when a visible element still references the element
+    fn used_by(&self, element_type: ElementType, id: i64) -> Option<String> {
+        let mut ways = vec![];
+        let mut relations = vec![];
+
+        for version in self.current_elements() {
+            if element_type == ElementType::Node && version.node_refs.contains(&id) {
+                ways.push(version.id.to_string());
+            }
+
+            if version
+                .members
+                .iter()
+                .any(|member| member.element_type() == Some(element_type) && member.node_id == id)
+            {
+                relations.push(version.id.to_string());
+            }
+        }
+
+        let name = capitalize(element_type.as_str());
+
+        if !ways.is_empty() {
+            return Some(format!(
+                "{name} {id} is still used by ways {}.",
+                ways.join(",")
+            ));
+        }
+
+        if !relations.is_empty() {
+            return Some(format!(
+                "{name} {id} is still used by relations {}.",
+                relations.join(",")
+            ));
+        }
+
+        None
+    }
+
+    fn write(&mut self, action: types::ChangeAction, version: Version) {
+        let changeset = self.changesets.get_mut(&version.changeset).unwrap();
+
+        changeset
+            .changes
+            .push((action, version.element_type, version.id, version.version));
+
+        let coordinates = match version.element_type {
+            ElementType::Node => vec![(version.lon, version.lat)],
+            ElementType::Way => version
+                .node_refs
+                .iter()
+                .filter_map(|node_id| self.elements.get(&(ElementType::Node, *node_id))?.last())
+                .map(|node| (node.lon, node.lat))
+                .collect(),
+            ElementType::Relation => vec![],
+        };
+
+        for (lon, lat) in coordinates {
+            if let (Some(lon), Some(lat)) = (lon, lat) {
+                changeset.bbox = Some(match changeset.bbox {
+                    Some(bbox) => types::BoundingBox {
+                        left: bbox.left.min(lon),
+                        bottom: bbox.bottom.min(lat),
+                        right: bbox.right.max(lon),
+                        top: bbox.top.max(lat),
+                    },
+                    None => types::BoundingBox {
+                        left: lon,
+                        bottom: lat,
+                        right: lon,
+                        top: lat,
+                    },
+                });
+            }
+        }
+
+        self.elements
+            .entry((version.element_type, version.id))
+            .or_default()
+            .push(version);
+    }
+
+    /// The nodes inside the bounding box, the ways using them with all their nodes and the
+    /// relations having any of those as member
+    pub fn map(&self, bbox: &types::BoundingBox) -> Result<Vec<&Version>, ApiError> {
+        if bbox.left > bbox.right || bbox.bottom > bbox.top {
+            return Err(ApiError::bad_request(
+                "The latitudes must be between -90 and 90, longitudes between -180 and 180 and the minima must be less than the maxima.",
+            ));
+        }
+
+        let area = (bbox.right - bbox.left) * (bbox.top - bbox.bottom);
+
+        if area > MAXIMUM_AREA {
+            return Err(ApiError::bad_request(format!(
+                "The maximum bbox size is {MAXIMUM_AREA}, and your request was too large. Either request a smaller area, or use planet.osm"
+            )));
+        }
+
+        let inside = |version: &Version| match (version.lon, version.lat) {
+            (Some(lon), Some(lat)) => {
+                bbox.left <= lon && lon <= bbox.right && bbox.bottom <= lat && lat <= bbox.top
+            }
+            _ => false,
+        };
+
+        let mut node_ids = self
+            .current_elements()
+            .filter(|version| version.element_type == ElementType::Node && inside(version))
+            .map(|version| version.id)
+            .collect::<Vec<i64>>();
+        let ways = self
+            .current_elements()
+            .filter(|version| version.element_type == ElementType::Way)
+            .filter(|way| {
+                way.node_refs
+                    .iter()
+                    .any(|node_id| node_ids.contains(node_id))
+            })
+            .collect::<Vec<&Version>>();
+
+        for way in &ways {
+            node_ids.extend(way.node_refs.iter().copied());
+        }
+
+        node_ids.sort_unstable();
+        node_ids.dedup();
+
+        let relations = self
+            .current_elements()
+            .filter(|version| version.element_type == ElementType::Relation)
+            .filter(|relation| {
+                relation
+                    .members
+                    .iter()
+                    .any(|member| match member.element_type() {
+                        Some(ElementType::Node) => node_ids.contains(&member.node_id),
+                        Some(ElementType::Way) => ways.iter().any(|way| way.id == member.node_id),
+                        _ => false,
+                    })
+            });
+
+        let nodes = node_ids
+            .iter()
+            .filter_map(|node_id| self.current(ElementType::Node, *node_id));
+
+        Ok(nodes.chain(ways.iter().copied()).chain(relations).collect())
+    }
+
+    pub fn create_note(&mut self, lat: f64, lon: f64, text: &str, user: Option<&User>) -> u64 {
+        let id = self.notes.len() as u64 + 1;
+        let date = self.now();
+
+        self.notes.insert(
+            id,
+            Note {
+                id,
+                lat,
+                lon,
+                created_at: date,
+                closed_at: None,
+                status: types::NoteStatus::Open,
+                comments: vec![NoteComment {
+                    date,
+                    user: user.map(|user| (user.id, user.display_name.clone())),
+                    action: types::CommentAction::Opened,
+                    text: text.into(),
+                }],
+            },
+        );
+
+        id
+    }
+
+    pub fn note(&self, note_id: u64) -> Result<&Note, ApiError> {
+        let note = self.notes.get(&note_id).ok_or_else(ApiError::not_found)?;
+
+        match note.status {
+            types::NoteStatus::Hidden => Err(ApiError::new(StatusCode::GONE, "")),
+            _ => Ok(note),
+        }
+    }
+
+    /// comments, closes or reopens the note
+    pub fn update_note(
+        &mut self,
+        note_id: u64,
+        action: types::CommentAction,
+        text: &str,
+        user: &User,
+    ) -> Result<(), ApiError> {
+        let note = self.note(note_id)?;
+
+        match (action, note.status, note.closed_at) {
+            (types::CommentAction::Reopened, types::NoteStatus::Open, _) => {
+                return Err(ApiError::conflict(format!(
+                    "The note {note_id} is already open"
+                )))
+            }
+            (types::CommentAction::Reopened, _, _) => {}
+            (_, types::NoteStatus::Closed, Some(closed_at)) => {
+                return Err(ApiError::conflict(format!(
+                    "The note {note_id} was closed at {}",
+                    format_note_date(closed_at)
+                )))
+            }
+            _ => {}
+        }
+
+        let date = self.now();
+        let note = self.notes.get_mut(&note_id).unwrap();
+
+        match action {
+            types::CommentAction::Closed => {
+                note.status = types::NoteStatus::Closed;
+                note.closed_at = Some(date);
+            }
+            types::CommentAction::Reopened => {
+                note.status = types::NoteStatus::Open;
+                note.closed_at = None;
+            }
+            _ => {}
+        }
+
+        note.comments.push(NoteComment {
+            date,
+            user: Some((user.id, user.display_name.clone())),
+            action,
+            text: text.into(),
+        });
+
+        Ok(())
+    }
+
+    /// The notes matching the query, the most recently updated first unless the query sorts
+    /// them otherwise
+    pub fn search_notes(&self, query: &NoteQuery) -> Vec<&Note> {
+        let closed_days = query.closed.unwrap_or(7);
+        let oldest_closed = self
+            .now()
+            .saturating_sub(closed_days.max(0).saturating_mul(24 * 60 * 60));
+        let text = query.text.as_ref().map(|text| text.to_lowercase());
+        let sort_date = |note: &Note| match query.sort_by_creation {
+            true => note.created_at,
+            false => note.updated_at(),
+        };
+
+        let mut notes = self
+            .notes
+            .values()
+            .filter(|note| note.status != types::NoteStatus::Hidden)
+            .filter(|note| match note.closed_at {
+                Some(closed_at) => closed_days < 0 || closed_at >= oldest_closed && closed_days > 0,
+                None => true,
+            })
+            .filter(|note| match &query.bbox {
+                Some(bbox) => {
+                    bbox.left <= note.lon
+                        && note.lon <= bbox.right
+                        && bbox.bottom <= note.lat
+                        && note.lat <= bbox.top
+                }
+                None => true,
+            })
+            .filter(|note| match &text {
+                Some(text) => note
+                    .comments
+                    .iter()
+                    .any(|comment| comment.text.to_lowercase().contains(text)),
+                None => true,
+            })
+            .filter(|note| {
+                note.comments.iter().any(|comment| match &comment.user {
+                    Some((uid, name)) => {
+                        query.user.is_none_or(|user| *uid == user)
+                            && query.display_name.as_ref().is_none_or(|user| name == user)
+                    }
+                    None => query.user.is_none() && query.display_name.is_none(),
+                })
+            })
+            .filter(|note| query.from.is_none_or(|from| sort_date(note) >= from))
+            .filter(|note| query.to.is_none_or(|to| sort_date(note) <= to))
+            .collect::<Vec<&Note>>();
+
+        notes.sort_by_key(|note| (sort_date(note), note.id));
+
+        if !query.oldest_first {
+            notes.reverse();
+        }
+
+        notes.truncate(query.limit.unwrap_or(DEFAULT_NOTE_LIMIT));
+        notes
+    }
+}
+
+/// the id of an element of the request: a placeholder of the same upload or an existing id
+fn resolve(
+    placeholders: &HashMap<(ElementType, i64), i64>,
+    element_type: ElementType,
+    id: i64,
+) -> Result<i64, ApiError> {
+    match placeholders.get(&(element_type, id)) {
+        Some(id) => Ok(*id),
+        None if id > 0 => Ok(id),
+        None => Err(ApiError::bad_request(format!(
+            "Placeholder {element_type} not found for reference {id}"
+        ))),
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,188 @@
+//! The HTTP layer of the client. The endpoints build an `HttpRequest` and parse the
+//! `HttpResponse` returned by a `Transport`, so the client can run on any HTTP stack by
+//! implementing the trait and passing it to `Openstreetmap::from_transport()`.
+
+use crate::errors::OpenstreetmapError;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{Stream, TryStreamExt};
+use http::header::HeaderMap;
+use http::{Method, StatusCode};
+use std::fmt;
+use std::pin::Pin;
+use url::Url;
+
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// the body is streamed so large responses can be parsed while they are downloaded
+    pub body: BodyStream,
+}
+
+impl HttpResponse {
+    /// creates a response whose body is already in memory
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers,
+            body: Box::pin(futures::stream::iter([Ok(Bytes::from(body))])),
+        }
+    }
+
+    pub async fn bytes(self) -> Result<Vec<u8>, OpenstreetmapError> {
+        let bytes = self
+            .body
+            .try_fold(vec![], |mut bytes, chunk| async move {
+                bytes.extend_from_slice(&chunk);
+                Ok(bytes)
+            })
+            .await?;
+
+        Ok(bytes)
+    }
+
+    pub async fn text(self) -> Result<String, OpenstreetmapError> {
+        Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+    }
+}
+
+impl fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends the requests of the client. Responses with an error status are returned as
+/// responses, the client turns them into the matching `OpenstreetmapError`.
+pub trait Transport: fmt::Debug + Send + Sync {
+    fn send(&self, request: HttpRequest)
+        -> BoxFuture<'_, Result<HttpResponse, OpenstreetmapError>>;
+}
+
+/// The default transport, sending the requests with a `reqwest::Client`
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> BoxFuture<'_, Result<HttpResponse, OpenstreetmapError>> {
+        Box::pin(async move {
+            let mut builder = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers);
+
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            let res = builder.send().await?;
+
+            Ok(HttpResponse {
+                status: res.status(),
+                headers: res.headers().clone(),
+                body: Box::pin(res.bytes_stream().map_err(std::io::Error::other)),
+            })
+        })
+    }
+}
+
+/// Sends the requests with a `reqwest::blocking::Client` while the future is polled, so
+/// the future never yields, and reads the body as its stream is polled; used by the
+/// `blocking` client.
+#[cfg(feature = "blocking")]
+#[derive(Debug, Clone, Default)]
+pub struct BlockingReqwestTransport {
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingReqwestTransport {
+    pub fn new(client: reqwest::blocking::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Transport for BlockingReqwestTransport {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> BoxFuture<'_, Result<HttpResponse, OpenstreetmapError>> {
+        Box::pin(async move {
+            let mut builder = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers);
+
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            let res = builder.send()?;
+            let status = res.status();
+            let headers = res.headers().clone();
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body: Box::pin(read_chunks(res)),
+            })
+        })
+    }
+}
+
+// Size of the chunks read from a blocking response body
+#[cfg(feature = "blocking")]
+const CHUNK_SIZE: usize = 8192;
+
+/// Streams the body of a blocking response by reading a chunk each time the stream is
+/// polled, so a large response is parsed while it is downloaded. The stream ends after the
+/// first error.
+#[cfg(feature = "blocking")]
+fn read_chunks(
+    response: reqwest::blocking::Response,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    use std::io::Read;
+
+    futures::stream::unfold(Some(response), |response| async move {
+        let mut response = response?;
+        let mut chunk = vec![0; CHUNK_SIZE];
+
+        match response.read(&mut chunk) {
+            Ok(0) => None,
+            Ok(length) => {
+                chunk.truncate(length);
+                Some((Ok(Bytes::from(chunk)), Some(response)))
+            }
+            Err(error) => Some((Err(error), None)),
+        }
+    })
+}
@@ -5,6 +5,7 @@ use std::fmt;
 #[derive(Debug, Clone)]
 pub enum Credentials {
     Basic(String, String), // Username, password
+    Bearer(String),        // OAuth 2.0 access token
     None,
 }
 
@@ -94,7 +95,7 @@ impl Tag {
 #[serde(rename = "node")]
 pub struct Node {
     #[serde(rename = "@id")]
-    pub id: u64,
+    pub id: i64,
     #[serde(rename = "@visible")]
     pub visible: bool,
     #[serde(rename = "@version")]
@@ -103,13 +104,16 @@ pub struct Node {
     pub changeset: u64,
     #[serde(rename = "@timestamp")]
     pub timestamp: String,
-    #[serde(rename = "@user")]
+    // The attributes missing from the API responses, such as the coordinates of deleted
+    // nodes, are left out when writing instead of being written empty, so written documents
+    // read back the same: an empty `user` would otherwise be read as `Some("")`
+    #[serde(rename = "@user", skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
-    #[serde(rename = "@uid")]
+    #[serde(rename = "@uid", skip_serializing_if = "Option::is_none")]
     pub uid: Option<u64>,
-    #[serde(rename = "@lat")]
+    #[serde(rename = "@lat", skip_serializing_if = "Option::is_none")]
     pub lat: Option<f64>,
-    #[serde(rename = "@lon")]
+    #[serde(rename = "@lon", skip_serializing_if = "Option::is_none")]
     pub lon: Option<f64>,
     #[serde(rename = "tag", default)]
     pub tags: Vec<Tag>,
@@ -118,24 +122,68 @@ pub struct Node {
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct NodeRef {
     #[serde(rename = "@ref")]
-    pub node_id: u64,
+    pub node_id: i64,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum ElementType {
+    Node,
+    Way,
+    Relation,
+}
+
+impl ElementType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ElementType::Node => "node",
+            ElementType::Way => "way",
+            ElementType::Relation => "relation",
+        }
+    }
+}
+
+impl fmt::Display for ElementType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 pub struct Member {
     #[serde(rename = "@type")]
     pub member_type: String,
     #[serde(rename = "@ref")]
-    pub node_id: u64,
+    pub node_id: i64,
     #[serde(rename = "@role")]
     pub role: String,
 }
 
+impl Member {
+    /// the type of the referenced element, `None` for an unknown member type
+    pub fn element_type(&self) -> Option<ElementType> {
+        match self.member_type.as_str() {
+            "node" => Some(ElementType::Node),
+            "way" => Some(ElementType::Way),
+            "relation" => Some(ElementType::Relation),
+            _ => None,
+        }
+    }
+}
+
+// The elements of an osmChange document written by an editor have no user yet
+fn is_zero(uid: &u64) -> bool {
+    *uid == 0
+}
+
+fn known_user(user: &str) -> Option<&str> {
+    Some(user).filter(|user| !user.is_empty())
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename = "way")]
 pub struct Way {
     #[serde(rename = "@id")]
-    pub id: u64,
+    pub id: i64,
     #[serde(rename = "@visible")]
     pub visible: bool,
     #[serde(rename = "@version")]
@@ -144,9 +192,9 @@ pub struct Way {
     pub changeset: u64,
     #[serde(rename = "@timestamp")]
     pub timestamp: String,
-    #[serde(rename = "@user")]
+    #[serde(rename = "@user", default, skip_serializing_if = "String::is_empty")]
     pub user: String,
-    #[serde(rename = "@uid")]
+    #[serde(rename = "@uid", default, skip_serializing_if = "is_zero")]
     pub uid: u64,
     #[serde(rename = "nd", default)]
     pub node_refs: Vec<NodeRef>,
@@ -158,7 +206,7 @@ pub struct Way {
 #[serde(rename = "relation")]
 pub struct Relation {
     #[serde(rename = "@id")]
-    pub id: u64,
+    pub id: i64,
     #[serde(rename = "@visible")]
     pub visible: bool,
     #[serde(rename = "@version")]
@@ -167,9 +215,9 @@ pub struct Relation {
     pub changeset: u64,
     #[serde(rename = "@timestamp")]
     pub timestamp: String,
-    #[serde(rename = "@user")]
+    #[serde(rename = "@user", default, skip_serializing_if = "String::is_empty")]
     pub user: String,
-    #[serde(rename = "@uid")]
+    #[serde(rename = "@uid", default, skip_serializing_if = "is_zero")]
     pub uid: u64,
     #[serde(rename = "tag", default)]
     pub tags: Vec<Tag>,
@@ -177,6 +225,153 @@ pub struct Relation {
     pub members: Vec<Member>,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum Element {
+    Node(Node),
+    Way(Way),
+    Relation(Relation),
+}
+
+/// The metadata shared by the versions of nodes, ways and relations, and by `Element`
+pub trait ElementVersion {
+    fn element_type(&self) -> ElementType;
+    fn id(&self) -> i64;
+    fn version(&self) -> u64;
+    fn set_version(&mut self, version: u64);
+    fn changeset(&self) -> u64;
+    fn set_changeset(&mut self, changeset: u64);
+    fn timestamp(&self) -> &str;
+    /// `None` for the elements read from files without the user
+    fn user(&self) -> Option<&str>;
+    fn visible(&self) -> bool;
+    fn tags(&self) -> &[Tag];
+}
+
+// The element types share the names of their metadata fields, only the user is read
+// differently
+macro_rules! impl_element_version {
+    ($type:ident, |$element:ident| $user:expr) => {
+        impl ElementVersion for $type {
+            #[inline]
+            fn element_type(&self) -> ElementType {
+                ElementType::$type
+            }
+
+            #[inline]
+            fn id(&self) -> i64 {
+                self.id
+            }
+
+            #[inline]
+            fn version(&self) -> u64 {
+                self.version
+            }
+
+            #[inline]
+            fn set_version(&mut self, version: u64) {
+                self.version = version;
+            }
+
+            #[inline]
+            fn changeset(&self) -> u64 {
+                self.changeset
+            }
+
+            #[inline]
+            fn set_changeset(&mut self, changeset: u64) {
+                self.changeset = changeset;
+            }
+
+            #[inline]
+            fn timestamp(&self) -> &str {
+                &self.timestamp
+            }
+
+            #[inline]
+            fn user(&self) -> Option<&str> {
+                let $element = self;
+
+                $user
+            }
+
+            #[inline]
+            fn visible(&self) -> bool {
+                self.visible
+            }
+
+            #[inline]
+            fn tags(&self) -> &[Tag] {
+                &self.tags
+            }
+        }
+    };
+}
+
+impl_element_version!(Node, |node| node.user.as_deref());
+impl_element_version!(Way, |way| known_user(&way.user));
+impl_element_version!(Relation, |relation| known_user(&relation.user));
+
+// Calls the method on the node, way or relation held by the element
+macro_rules! forward {
+    ($element:expr, $method:ident($($arg:expr),*)) => {
+        match $element {
+            Element::Node(node) => node.$method($($arg),*),
+            Element::Way(way) => way.$method($($arg),*),
+            Element::Relation(relation) => relation.$method($($arg),*),
+        }
+    };
+}
+
+impl ElementVersion for Element {
+    fn element_type(&self) -> ElementType {
+        forward!(self, element_type())
+    }
+
+    fn id(&self) -> i64 {
+        forward!(self, id())
+    }
+
+    fn version(&self) -> u64 {
+        forward!(self, version())
+    }
+
+    fn set_version(&mut self, version: u64) {
+        forward!(self, set_version(version))
+    }
+
+    fn changeset(&self) -> u64 {
+        forward!(self, changeset())
+    }
+
+    fn set_changeset(&mut self, changeset: u64) {
+        forward!(self, set_changeset(changeset))
+    }
+
+    fn timestamp(&self) -> &str {
+        forward!(self, timestamp())
+    }
+
+    fn user(&self) -> Option<&str> {
+        forward!(self, user())
+    }
+
+    fn visible(&self) -> bool {
+        forward!(self, visible())
+    }
+
+    fn tags(&self) -> &[Tag] {
+        forward!(self, tags())
+    }
+}
+
+/// The `osmChange` block an element belongs to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChangeAction {
+    Create,
+    Modify,
+    Delete,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Map {
     pub bounds: BoundingBox,
@@ -214,48 +409,48 @@ impl ChangesetCreate {
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub struct DiscussionComment {
+    /// only returned by servers recent enough to number the comments
+    #[serde(rename = "@id", default)]
+    pub id: Option<u64>,
     #[serde(rename = "@date")]
     pub date: String,
     #[serde(rename = "@uid")]
     pub uid: u64,
     #[serde(rename = "@user")]
     pub user: String,
+    /// hidden comments are only returned to moderators
+    #[serde(rename = "@visible", default = "default_visible")]
+    pub visible: bool,
     pub text: String,
 }
 
+fn default_visible() -> bool {
+    true
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub struct Discussion {
     #[serde(rename = "comment", default)]
     pub comments: Vec<DiscussionComment>,
 }
 
+/// Deserialized from the `<changeset>` element, with the bounding box attributes gathered
+/// into `bbox`
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(from = "crate::api::changeset::ChangesetRaw")]
 pub struct Changeset {
-    #[serde(rename = "@id")]
     pub id: u64,
-    #[serde(rename = "@user")]
     pub user: String,
-    #[serde(rename = "@uid")]
     pub uid: u64,
-    #[serde(rename = "@created_at")]
     pub created_at: String,
-    #[serde(rename = "@closed_at")]
     pub closed_at: Option<String>,
-    #[serde(rename = "@open")]
     pub open: bool,
+    pub comments_count: u64,
+    pub changes_count: u64,
+    /// `None` for an empty changeset
+    pub bbox: Option<BoundingBox>,
     pub discussion: Option<Discussion>,
-    #[serde(rename = "tag", default)]
     pub tags: Vec<Tag>,
-
-    // The bounding box attributes will be missing for an empty changeset
-    #[serde(rename = "@min_lon")]
-    pub min_lon: Option<f64>,
-    #[serde(rename = "@min_lat")]
-    pub min_lat: Option<f64>,
-    #[serde(rename = "@max_lon")]
-    pub max_lon: Option<f64>,
-    #[serde(rename = "@max_lat")]
-    pub max_lat: Option<f64>,
 }
 
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
@@ -278,14 +473,23 @@ pub struct Creation {
     pub relations: Vec<Relation>,
 }
 
+// The fields are serialised in declaration order, so the relations are deleted before the
+// ways and the ways before the nodes they reference
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct Deletion {
-    #[serde(rename = "node", default)]
-    pub nodes: Vec<Node>,
-    #[serde(rename = "way", default)]
-    pub ways: Vec<Way>,
+    /// elements still used by others are kept instead of failing the upload
+    #[serde(
+        rename = "@if-unused",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub if_unused: bool,
     #[serde(rename = "relation", default)]
     pub relations: Vec<Relation>,
+    #[serde(rename = "way", default)]
+    pub ways: Vec<Way>,
+    #[serde(rename = "node", default)]
+    pub nodes: Vec<Node>,
 }
 
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
@@ -299,37 +503,38 @@ pub struct ChangesetChanges {
     pub deletions: Vec<Deletion>,
 }
 
+// The new id and version are missing for deleted elements
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename = "node")]
 pub struct DiffNode {
     #[serde(rename = "@old_id")]
-    pub old_id: u64,
+    pub old_id: i64,
     #[serde(rename = "@new_id")]
-    pub new_id: u64,
+    pub new_id: Option<i64>,
     #[serde(rename = "@new_version")]
-    pub new_version: u64,
+    pub new_version: Option<u64>,
 }
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename = "node")]
 pub struct DiffWay {
     #[serde(rename = "@old_id")]
-    pub old_id: u64,
+    pub old_id: i64,
     #[serde(rename = "@new_id")]
-    pub new_id: u64,
+    pub new_id: Option<i64>,
     #[serde(rename = "@new_version")]
-    pub new_version: u64,
+    pub new_version: Option<u64>,
 }
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename = "node")]
 pub struct DiffRelation {
     #[serde(rename = "@old_id")]
-    pub old_id: u64,
+    pub old_id: i64,
     #[serde(rename = "@new_id")]
-    pub new_id: u64,
+    pub new_id: Option<i64>,
     #[serde(rename = "@new_version")]
-    pub new_version: u64,
+    pub new_version: Option<u64>,
 }
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
@@ -343,7 +548,41 @@ pub struct DiffResult {
     pub relations: Vec<DiffRelation>,
 }
 
-#[derive(Debug, Default, PartialEq)]
+/// An element the revert leaves alone because it was edited after the reverted changeset, or
+/// because such an element still references it; the versions are then the same
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RevertConflict {
+    pub element_type: ElementType,
+    pub id: i64,
+    /// the last version written by the reverted changeset
+    pub version: u64,
+    /// the current version of the element
+    pub current_version: u64,
+}
+
+/// The changes undoing a changeset, to be uploaded into the target changeset
+#[derive(Debug, PartialEq)]
+pub struct RevertPlan {
+    pub changes: ChangesetChanges,
+    pub conflicts: Vec<RevertConflict>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RevertResult {
+    /// `None` when there was nothing to revert
+    pub diff: Option<DiffResult>,
+    pub conflicts: Vec<RevertConflict>,
+}
+
+/// Sort order of the changesets returned by a query, by creation time
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangesetOrder {
+    Newest,
+    Oldest,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct ChangesetQueryParams {
     pub bbox: Option<BoundingBox>,
     pub user_id: Option<u64>,
@@ -353,16 +592,19 @@ pub struct ChangesetQueryParams {
     pub open: Option<bool>,
     pub closed: Option<bool>,
     pub changeset_ids: Option<Vec<u64>>,
+    /// number of changesets returned, the server allows up to 100
+    pub limit: Option<u32>,
+    pub order: Option<ChangesetOrder>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct ElementIdParam {
-    pub id: u64,
+    pub id: i64,
     pub version: Option<u64>,
 }
 
 impl ElementIdParam {
-    pub fn new(id: u64, version: Option<u64>) -> Self {
+    pub fn new(id: i64, version: Option<u64>) -> Self {
         Self { id, version }
     }
 }
@@ -378,6 +620,13 @@ impl fmt::Display for ElementIdParam {
     }
 }
 
+/// Outcome of a batched fetch: the items found, in request order, and the ids that were not
+#[derive(Debug, PartialEq)]
+pub struct MultiGetResult<T, I> {
+    pub found: Vec<T>,
+    pub missing: Vec<I>,
+}
+
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct WayFull {
     pub way: Way,
@@ -463,18 +712,53 @@ pub struct User {
 
 pub type UserPreferences = HashMap<String, String>;
 
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteStatus {
+    Open,
+    Closed,
+    Hidden,
+    /// a status added to the API after this version of the crate, also the default since no
+    /// status is more likely than another
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentAction {
+    Opened,
+    Commented,
+    Closed,
+    Reopened,
+    Hidden,
+    /// an action added to the API after this version of the crate, also the default
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Deserialize)]
 pub struct Comment {
+    /// the user id, missing for anonymous comments
     #[serde(rename = "uid")]
-    pub id: u64,
+    pub id: Option<u64>,
     pub date: String,
-    pub user: String,
-    pub user_url: String,
-    pub action: String,
+    pub user: Option<String>,
+    pub user_url: Option<String>,
+    pub action: CommentAction,
     pub text: String,
     pub html: String,
 }
 
+impl Comment {
+    #[inline]
+    pub fn is_anonymous(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
 #[derive(Debug, Default, PartialEq)]
 pub struct Note {
     pub id: u64,
@@ -484,10 +768,26 @@ pub struct Note {
     pub comment_url: String,
     pub close_url: String,
     pub created_at: String,
-    pub status: String,
+    pub status: NoteStatus,
     pub comments: Vec<Comment>,
 }
 
+/// An entry of the notes RSS feed: a note being opened, commented, closed, reopened or hidden
+#[derive(Debug, Default, PartialEq)]
+pub struct NoteFeedItem {
+    pub title: String,
+    pub link: String,
+    /// taken from the title, `None` if the title is not recognised
+    pub action: Option<CommentAction>,
+    /// missing for anonymous comments
+    pub author: Option<String>,
+    pub date: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// HTML description of the comment
+    pub description: String,
+}
+
 #[derive(Debug, Default, PartialEq, Serialize)]
 pub struct NoteContent {
     pub lat: f64,
@@ -495,29 +795,76 @@ pub struct NoteContent {
     pub text: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum NoteSearchSortOption {
     CreatedAt,
     UpdatedAt,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NoteSearchOrderOption {
     Oldest,
     Newest,
 }
 
-#[derive(Debug, Default, Serialize)]
+/// Which notes to return depending on when they were closed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NoteClosedOption {
+    /// only the open notes
+    OpenOnly,
+    /// open notes and notes closed in the last given number of days
+    ClosedWithin(u32),
+    /// open and closed notes, regardless of when they were closed
+    All,
+}
+
+impl NoteClosedOption {
+    /// The value of the `closed` query parameter
+    pub fn as_days(&self) -> i64 {
+        match self {
+            NoteClosedOption::OpenOnly => 0,
+            NoteClosedOption::ClosedWithin(days) => i64::from(*days),
+            NoteClosedOption::All => -1,
+        }
+    }
+}
+
+impl Serialize for NoteClosedOption {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_i64(self.as_days())
+    }
+}
+
+fn serialize_bbox<S>(bbox: &Option<BoundingBox>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match bbox {
+        Some(bbox) => serializer.serialize_some(&format!(
+            "{},{},{},{}",
+            bbox.left, bbox.bottom, bbox.right, bbox.top
+        )),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct NoteSearchOptions {
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub q: String,
     pub limit: Option<u16>,
-    pub closed: Option<i64>,
+    pub closed: Option<NoteClosedOption>,
     pub display_name: Option<String>,
     pub user: Option<u64>,
     pub from: Option<String>,
     pub to: Option<String>,
     pub sort: Option<NoteSearchSortOption>,
     pub order: Option<NoteSearchOrderOption>,
+    #[serde(serialize_with = "serialize_bbox")]
+    pub bbox: Option<BoundingBox>,
 }
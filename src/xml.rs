@@ -0,0 +1,652 @@
+use crate::errors::OpenstreetmapError;
+use crate::types;
+
+use flate2::bufread::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::stream::{self, Stream};
+use quick_xml::de::{from_reader, DeError};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::se::{to_string, to_string_with_root};
+use quick_xml::{Reader, Writer};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use tokio::io::AsyncBufRead;
+
+// First two bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+enum Parsed {
+    Bounds(types::BoundingBox),
+    Element(types::Element),
+}
+
+// Element being copied out of the document: its tag name, the events read so far and the
+// current nesting depth
+struct PendingElement {
+    name: Vec<u8>,
+    writer: Writer<Vec<u8>>,
+    depth: usize,
+}
+
+// Turns XML events into elements regardless of how the events are read, so the blocking and
+// the async readers share the same parsing
+#[derive(Default)]
+struct ElementParser {
+    pending: Option<PendingElement>,
+    action: Option<types::ChangeAction>,
+    if_unused: bool,
+}
+
+impl ElementParser {
+    fn feed(&mut self, event: Event) -> Result<Option<Parsed>, OpenstreetmapError> {
+        if let Some(pending) = self.pending.as_mut() {
+            match event {
+                Event::Start(_) => pending.depth += 1,
+                Event::End(_) => pending.depth -= 1,
+                Event::Eof => {
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+                }
+                _ => {}
+            }
+
+            pending.writer.write_event(event)?;
+
+            if pending.depth > 0 {
+                return Ok(None);
+            }
+
+            return self.finish_element().map(Some);
+        }
+
+        match event {
+            Event::Start(ref start) | Event::Empty(ref start) => match start.name().as_ref() {
+                b"node" | b"way" | b"relation" => {
+                    let is_empty = matches!(event, Event::Empty(_));
+                    let mut pending = PendingElement {
+                        name: start.name().as_ref().to_vec(),
+                        writer: Writer::new(Vec::new()),
+                        depth: 1,
+                    };
+
+                    pending.writer.write_event(event)?;
+                    self.pending = Some(pending);
+
+                    if is_empty {
+                        return self.finish_element().map(Some);
+                    }
+                }
+                b"bounds" => return Ok(Some(Parsed::Bounds(read_bounds(start)?))),
+                b"create" => self.action = Some(types::ChangeAction::Create),
+                b"modify" => self.action = Some(types::ChangeAction::Modify),
+                b"delete" => {
+                    self.action = Some(types::ChangeAction::Delete);
+                    // The API only checks whether the attribute is there, whatever its value
+                    self.if_unused = start.try_get_attribute("if-unused")?.is_some();
+                }
+                _ => {}
+            },
+            Event::End(end) => {
+                if let b"create" | b"modify" | b"delete" = end.name().as_ref() {
+                    self.action = None;
+                    self.if_unused = false;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+
+    // Deserialises the copied events as a standalone document, so the elements are parsed by
+    // the same `serde` definitions used for the API responses
+    fn finish_element(&mut self) -> Result<Parsed, OpenstreetmapError> {
+        let pending = self.pending.take().expect("no pending element");
+        let xml = pending.writer.into_inner();
+
+        let element = match pending.name.as_slice() {
+            b"node" => types::Element::Node(from_reader(xml.as_slice())?),
+            b"way" => types::Element::Way(from_reader(xml.as_slice())?),
+            _ => types::Element::Relation(from_reader(xml.as_slice())?),
+        };
+
+        Ok(Parsed::Element(element))
+    }
+}
+
+fn read_bounds(start: &BytesStart) -> Result<types::BoundingBox, OpenstreetmapError> {
+    let mut bounds = types::BoundingBox {
+        left: 0.0,
+        bottom: 0.0,
+        right: 0.0,
+        top: 0.0,
+    };
+
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let value = attribute
+            .unescape_value()?
+            .parse::<f64>()
+            .map_err(DeError::InvalidFloat)?;
+
+        match attribute.key.as_ref() {
+            b"minlon" => bounds.left = value,
+            b"minlat" => bounds.bottom = value,
+            b"maxlon" => bounds.right = value,
+            b"maxlat" => bounds.top = value,
+            _ => {}
+        }
+    }
+
+    Ok(bounds)
+}
+
+/// Streams the nodes, ways and relations of an OSM XML document one element at a time.
+pub struct ElementReader<R: BufRead> {
+    reader: Reader<R>,
+    buffer: Vec<u8>,
+    parser: ElementParser,
+    bounds: Option<types::BoundingBox>,
+}
+
+impl<R: BufRead> ElementReader<R> {
+    pub fn new(reader: R) -> Self {
+        let mut reader = Reader::from_reader(reader);
+
+        reader.config_mut().trim_text(true);
+
+        Self {
+            reader,
+            buffer: vec![],
+            parser: ElementParser::default(),
+            bounds: None,
+        }
+    }
+
+    /// The `<bounds>` of the document, available once the reader went past it
+    #[inline]
+    pub fn bounds(&self) -> Option<types::BoundingBox> {
+        self.bounds
+    }
+
+    /// The `osmChange` block the last element was read from, if any
+    #[inline]
+    pub fn action(&self) -> Option<types::ChangeAction> {
+        self.parser.action
+    }
+
+    /// Whether the `<delete>` block the last element was read from has `if-unused`
+    #[inline]
+    pub fn if_unused(&self) -> bool {
+        self.parser.if_unused
+    }
+}
+
+impl<R: BufRead> Iterator for ElementReader<R> {
+    type Item = Result<types::Element, OpenstreetmapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+
+            let event = match self.reader.read_event_into(&mut self.buffer) {
+                Ok(event) => event,
+                Err(error) => return Some(Err(error.into())),
+            };
+            let is_eof = matches!(event, Event::Eof);
+
+            match self.parser.feed(event) {
+                Ok(Some(Parsed::Element(element))) => return Some(Ok(element)),
+                Ok(Some(Parsed::Bounds(bounds))) => self.bounds = Some(bounds),
+                Ok(None) if is_eof => return None,
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Like `ElementReader` but reading from an async source, such as a response body, without
+/// buffering the whole document.
+pub struct AsyncElementReader<R: AsyncBufRead + Unpin> {
+    reader: Reader<R>,
+    buffer: Vec<u8>,
+    parser: ElementParser,
+    bounds: Option<types::BoundingBox>,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncElementReader<R> {
+    pub fn new(reader: R) -> Self {
+        let mut reader = Reader::from_reader(reader);
+
+        reader.config_mut().trim_text(true);
+
+        Self {
+            reader,
+            buffer: vec![],
+            parser: ElementParser::default(),
+            bounds: None,
+        }
+    }
+
+    /// The `<bounds>` of the document, available once the reader went past it
+    #[inline]
+    pub fn bounds(&self) -> Option<types::BoundingBox> {
+        self.bounds
+    }
+
+    /// The `osmChange` block the last element was read from, if any
+    #[inline]
+    pub fn action(&self) -> Option<types::ChangeAction> {
+        self.parser.action
+    }
+
+    /// Whether the `<delete>` block the last element was read from has `if-unused`
+    #[inline]
+    pub fn if_unused(&self) -> bool {
+        self.parser.if_unused
+    }
+
+    /// Reads the next element, `None` once the document is over
+    pub async fn next_element(&mut self) -> Option<Result<types::Element, OpenstreetmapError>> {
+        loop {
+            self.buffer.clear();
+
+            let event = match self.reader.read_event_into_async(&mut self.buffer).await {
+                Ok(event) => event,
+                Err(error) => return Some(Err(error.into())),
+            };
+            let is_eof = matches!(event, Event::Eof);
+
+            match self.parser.feed(event) {
+                Ok(Some(Parsed::Element(element))) => return Some(Ok(element)),
+                Ok(Some(Parsed::Bounds(bounds))) => self.bounds = Some(bounds),
+                Ok(None) if is_eof => return None,
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+
+    /// Turns the reader into a stream of elements, which ends after the first error since the
+    /// reader cannot resume a malformed document
+    pub fn into_stream(self) -> impl Stream<Item = Result<types::Element, OpenstreetmapError>> {
+        stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+
+            match reader.next_element().await? {
+                Ok(element) => Some((Ok(element), Some(reader))),
+                Err(error) => Some((Err(error), None)),
+            }
+        })
+    }
+
+    /// Turns the reader into a stream of the elements of an `osmChange` document, paired with
+    /// the block they belong to. Elements outside of any block are ignored; like
+    /// `into_stream()`, the stream ends after the first error.
+    pub fn into_change_stream(
+        self,
+    ) -> impl Stream<Item = Result<(types::ChangeAction, types::Element), OpenstreetmapError>> {
+        stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+
+            loop {
+                let element = match reader.next_element().await? {
+                    Ok(element) => element,
+                    Err(error) => return Some((Err(error), None)),
+                };
+
+                if let Some(action) = reader.action() {
+                    return Some((Ok((action, element)), Some(reader)));
+                }
+            }
+        })
+    }
+}
+
+/// Reads an OSM XML document into a `Map`. Documents without `<bounds>` get the extent of
+/// their nodes as bounding box.
+pub fn read_map<R: BufRead>(reader: R) -> Result<types::Map, OpenstreetmapError> {
+    let mut elements = ElementReader::new(reader);
+    let mut nodes = vec![];
+    let mut ways = vec![];
+    let mut relations = vec![];
+
+    for element in elements.by_ref() {
+        match element? {
+            types::Element::Node(node) => nodes.push(node),
+            types::Element::Way(way) => ways.push(way),
+            types::Element::Relation(relation) => relations.push(relation),
+        }
+    }
+
+    let bounds = elements.bounds().unwrap_or_else(|| {
+        let coordinates = nodes
+            .iter()
+            .filter_map(|n| n.lon.zip(n.lat))
+            .collect::<Vec<(f64, f64)>>();
+
+        if coordinates.is_empty() {
+            return types::BoundingBox {
+                left: 0.0,
+                bottom: 0.0,
+                right: 0.0,
+                top: 0.0,
+            };
+        }
+
+        types::BoundingBox {
+            left: coordinates.iter().map(|c| c.0).fold(f64::MAX, f64::min),
+            bottom: coordinates.iter().map(|c| c.1).fold(f64::MAX, f64::min),
+            right: coordinates.iter().map(|c| c.0).fold(f64::MIN, f64::max),
+            top: coordinates.iter().map(|c| c.1).fold(f64::MIN, f64::max),
+        }
+    });
+
+    Ok(types::Map {
+        bounds,
+        nodes,
+        ways,
+        relations,
+    })
+}
+
+/// Writes an OSM XML document one element at a time; `finish()` closes the document.
+pub struct ElementWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ElementWriter<W> {
+    pub fn new(
+        mut writer: W,
+        generator: &str,
+        bounds: Option<&types::BoundingBox>,
+    ) -> Result<Self, OpenstreetmapError> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<osm version="{}" generator="{}">"#,
+            crate::DEFAULT_VERSION,
+            escape(generator)
+        )?;
+
+        if let Some(bbox) = bounds {
+            writeln!(
+                writer,
+                r#"<bounds minlat="{}" minlon="{}" maxlat="{}" maxlon="{}"/>"#,
+                bbox.bottom, bbox.left, bbox.top, bbox.right
+            )?;
+        }
+
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, element: &types::Element) -> Result<(), OpenstreetmapError> {
+        match element {
+            types::Element::Node(node) => self.write_node(node),
+            types::Element::Way(way) => self.write_way(way),
+            types::Element::Relation(relation) => self.write_relation(relation),
+        }
+    }
+
+    #[inline]
+    pub fn write_node(&mut self, node: &types::Node) -> Result<(), OpenstreetmapError> {
+        self.write_xml(&to_string(node)?)
+    }
+
+    #[inline]
+    pub fn write_way(&mut self, way: &types::Way) -> Result<(), OpenstreetmapError> {
+        self.write_xml(&to_string(way)?)
+    }
+
+    #[inline]
+    pub fn write_relation(&mut self, relation: &types::Relation) -> Result<(), OpenstreetmapError> {
+        self.write_xml(&to_string(relation)?)
+    }
+
+    fn write_xml(&mut self, xml: &str) -> Result<(), OpenstreetmapError> {
+        writeln!(self.writer, "{xml}")?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, OpenstreetmapError> {
+        writeln!(self.writer, "</osm>")?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Writes the map as an OSM XML document, returning the writer
+pub fn write_map<W: Write>(
+    writer: W,
+    map: &types::Map,
+    generator: &str,
+) -> Result<W, OpenstreetmapError> {
+    let mut elements = ElementWriter::new(writer, generator, Some(&map.bounds))?;
+
+    for node in map.nodes.iter() {
+        elements.write_node(node)?;
+    }
+
+    for way in map.ways.iter() {
+        elements.write_way(way)?;
+    }
+
+    for relation in map.relations.iter() {
+        elements.write_relation(relation)?;
+    }
+
+    elements.finish()
+}
+
+/// Reads an osmChange document into `ChangesetChanges`, decompressing it first if the
+/// content is gzip-compressed. The elements are read one at a time, so blocks may repeat and
+/// interleave, e.g. `<create>` after `<modify>` after `<create>`; every run of elements of the
+/// same action becomes a block of its own. A run is split further wherever `write_changes()`
+/// would otherwise reorder its elements, so the document is written back in the same order.
+pub fn read_changes<R: BufRead>(
+    mut reader: R,
+) -> Result<types::ChangesetChanges, OpenstreetmapError> {
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        collect_changes(ElementReader::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        collect_changes(ElementReader::new(reader))
+    }
+}
+
+fn collect_changes<R: BufRead>(
+    mut elements: ElementReader<R>,
+) -> Result<types::ChangesetChanges, OpenstreetmapError> {
+    let mut changes = ChangesBuilder::default();
+
+    while let Some(element) = elements.next() {
+        let element = element?;
+
+        // Elements outside of any block are ignored
+        if let Some(action) = elements.action() {
+            changes.set_if_unused(elements.if_unused());
+            changes.push(action, element);
+        }
+    }
+
+    Ok(changes.finish())
+}
+
+/// Gathers the elements of an osmChange document, read one at a time, into blocks
+pub(crate) struct ChangesBuilder {
+    changes: types::ChangesetChanges,
+    if_unused: bool,
+    // action, `if-unused` and write position of the last element
+    last: Option<(types::ChangeAction, bool, u8)>,
+}
+
+impl Default for ChangesBuilder {
+    fn default() -> Self {
+        Self {
+            changes: types::ChangesetChanges {
+                modifications: vec![],
+                creations: vec![],
+                deletions: vec![],
+            },
+            if_unused: false,
+            last: None,
+        }
+    }
+}
+
+impl ChangesBuilder {
+    /// sets whether the next deleted elements come from a `<delete if-unused>` block
+    pub fn set_if_unused(&mut self, if_unused: bool) {
+        self.if_unused = if_unused;
+    }
+
+    /// adds the element to the last block of the action, starting a new block when the
+    /// previous element belongs to another action, or when the block would write the element
+    /// before the previous one
+    pub fn push(&mut self, action: types::ChangeAction, element: types::Element) {
+        let if_unused = action == types::ChangeAction::Delete && self.if_unused;
+        let position = write_position(action, &element);
+        let is_new_block = match self.last {
+            Some((last_action, last_if_unused, last_position)) => {
+                last_action != action || last_if_unused != if_unused || last_position > position
+            }
+            None => true,
+        };
+        let changes = &mut self.changes;
+
+        self.last = Some((action, if_unused, position));
+
+        let (nodes, ways, relations) = match action {
+            types::ChangeAction::Create => {
+                if is_new_block {
+                    changes.creations.push(types::Creation {
+                        nodes: vec![],
+                        ways: vec![],
+                        relations: vec![],
+                    });
+                }
+
+                let block = changes.creations.last_mut().expect("no create block");
+                (&mut block.nodes, &mut block.ways, &mut block.relations)
+            }
+            types::ChangeAction::Modify => {
+                if is_new_block {
+                    changes.modifications.push(types::Modification {
+                        nodes: vec![],
+                        ways: vec![],
+                        relations: vec![],
+                    });
+                }
+
+                let block = changes.modifications.last_mut().expect("no modify block");
+                (&mut block.nodes, &mut block.ways, &mut block.relations)
+            }
+            types::ChangeAction::Delete => {
+                if is_new_block {
+                    changes.deletions.push(types::Deletion {
+                        if_unused,
+                        relations: vec![],
+                        ways: vec![],
+                        nodes: vec![],
+                    });
+                }
+
+                let block = changes.deletions.last_mut().expect("no delete block");
+                (&mut block.nodes, &mut block.ways, &mut block.relations)
+            }
+        };
+
+        match element {
+            types::Element::Node(node) => nodes.push(node),
+            types::Element::Way(way) => ways.push(way),
+            types::Element::Relation(relation) => relations.push(relation),
+        }
+    }
+
+    pub fn finish(self) -> types::ChangesetChanges {
+        self.changes
+    }
+}
+
+// Position of the element inside a block once written: nodes, ways then relations, except for
+// deletions which are written the other way round
+fn write_position(action: types::ChangeAction, element: &types::Element) -> u8 {
+    let position = match element {
+        types::Element::Node(_) => 0,
+        types::Element::Way(_) => 1,
+        types::Element::Relation(_) => 2,
+    };
+
+    match action {
+        types::ChangeAction::Delete => 2 - position,
+        _ => position,
+    }
+}
+
+/// Writes the changes as an osmChange document, returning the writer. Blocks are written in
+/// creation, modification, deletion order; inside a block the nodes come before the ways and
+/// the ways before the relations, except for deletions where the relations come first so no
+/// element is deleted while a later one in the block still references it.
+pub fn write_changes<W: Write>(
+    mut writer: W,
+    changes: &types::ChangesetChanges,
+    generator: &str,
+) -> Result<W, OpenstreetmapError> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<osmChange version="{}" generator="{}">"#,
+        crate::DEFAULT_VERSION,
+        escape(generator)
+    )?;
+
+    for creation in changes.creations.iter() {
+        writeln!(writer, "{}", to_string_with_root("create", creation)?)?;
+    }
+
+    for modification in changes.modifications.iter() {
+        writeln!(writer, "{}", to_string_with_root("modify", modification)?)?;
+    }
+
+    for deletion in changes.deletions.iter() {
+        writeln!(writer, "{}", to_string_with_root("delete", deletion)?)?;
+    }
+
+    writeln!(writer, "</osmChange>")?;
+
+    Ok(writer)
+}
+
+/// Reads an `.osc` or `.osc.gz` file
+pub fn read_changes_file<P: AsRef<Path>>(
+    path: P,
+) -> Result<types::ChangesetChanges, OpenstreetmapError> {
+    read_changes(BufReader::new(File::open(path)?))
+}
+
+/// Writes an `.osc` file, gzip-compressed when the file name ends in `.gz`
+pub fn write_changes_file<P: AsRef<Path>>(
+    path: P,
+    changes: &types::ChangesetChanges,
+    generator: &str,
+) -> Result<(), OpenstreetmapError> {
+    let is_gzip = path.as_ref().extension().is_some_and(|ext| ext == "gz");
+    let writer = BufWriter::new(File::create(path)?);
+
+    if is_gzip {
+        write_changes(
+            GzEncoder::new(writer, Compression::default()),
+            changes,
+            generator,
+        )?
+        .finish()?
+        .flush()?;
+    } else {
+        write_changes(writer, changes, generator)?.flush()?;
+    }
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
+use futures::StreamExt;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use pretty_assertions::assert_eq;
 use rstest::*;
-use wiremock::matchers::{method, path, query_param};
+use wiremock::matchers::{body_string_contains, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use super::utils::credentials;
@@ -62,7 +63,7 @@ async fn test_create(
     case(
         r#"
         <osm>
-            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
+            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" comments_count="0" changes_count="4" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
                 <tag k="created_by" v="JOSM 1.61"/>
             </changeset>
         </osm>
@@ -74,16 +75,40 @@ async fn test_create(
             created_at: "2008-11-08T19:07:39+01:00".into(),
             closed_at: None,
             open: true,
-            min_lon: Some(7.0191821),
-            min_lat: Some(49.2785426),
-            max_lon: Some(7.0197485),
-            max_lat: Some(49.2793101),
+            comments_count: 0,
+            changes_count: 4,
+            bbox: Some(types::BoundingBox {
+                left: 7.0191821,
+                bottom: 49.2785426,
+                right: 7.0197485,
+                top: 49.2793101,
+            }),
             discussion: None,
             tags: vec![types::Tag {
                 k: "created_by".into(),
                 v: "JOSM 1.61".into(),
             }],
         }
+    ),
+    case(
+        r#"
+        <osm version="0.6" generator="OpenStreetMap server">
+            <changeset id="10" created_at="2020-12-09T22:51:17Z" open="false" comments_count="0" changes_count="0" closed_at="2020-12-09T23:51:17Z" uid="123" user="fred"/>
+        </osm>
+        "#,
+        types::Changeset {
+            id: 10,
+            user: "fred".into(),
+            uid: 123,
+            created_at: "2020-12-09T22:51:17Z".into(),
+            closed_at: Some("2020-12-09T23:51:17Z".into()),
+            open: false,
+            comments_count: 0,
+            changes_count: 0,
+            bbox: None,
+            discussion: None,
+            tags: vec![],
+        }
     )
 )]
 #[actix_rt::test]
@@ -120,12 +145,18 @@ async fn test_get(
     case(
         r#"
         <osm>
-            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
+            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" comments_count="2" changes_count="4" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
                 <tag k="created_by" v="JOSM 1.61"/>
                 <discussion>
-                    <comment date="2015-01-01T18:56:48Z" uid="1841" user="metaodi">
+                    <comment id="1053" date="2015-01-01T18:56:48Z" uid="1841" user="metaodi" visible="true">
                         <text>Did you verify those street names?</text>
                     </comment>
+                    <comment id="1054" date="2015-01-02T09:12:03Z" uid="1842" user="spammer" visible="false">
+                        <text>Buy now</text>
+                    </comment>
+                    <comment date="2015-01-03T10:00:00Z" uid="1841" user="metaodi">
+                        <text>Thanks</text>
+                    </comment>
                 </discussion>
             </changeset>
         </osm>
@@ -137,16 +168,36 @@ async fn test_get(
             created_at: "2008-11-08T19:07:39+01:00".into(),
             closed_at: None,
             open: true,
-            min_lon: Some(7.0191821),
-            min_lat: Some(49.2785426),
-            max_lon: Some(7.0197485),
-            max_lat: Some(49.2793101),
+            comments_count: 2,
+            changes_count: 4,
+            bbox: Some(types::BoundingBox {
+                left: 7.0191821,
+                bottom: 49.2785426,
+                right: 7.0197485,
+                top: 49.2793101,
+            }),
             discussion: Some(types::Discussion {
                 comments: vec![types::DiscussionComment {
+                    id: Some(1053),
                     date: "2015-01-01T18:56:48Z".into(),
                     uid: 1841,
                     user: "metaodi".into(),
+                    visible: true,
                     text: "Did you verify those street names?".into(),
+                }, types::DiscussionComment {
+                    id: Some(1054),
+                    date: "2015-01-02T09:12:03Z".into(),
+                    uid: 1842,
+                    user: "spammer".into(),
+                    visible: false,
+                    text: "Buy now".into(),
+                }, types::DiscussionComment {
+                    id: None,
+                    date: "2015-01-03T10:00:00Z".into(),
+                    uid: 1841,
+                    user: "metaodi".into(),
+                    visible: true,
+                    text: "Thanks".into(),
                 }],
             }),
             tags: vec![types::Tag {
@@ -188,12 +239,52 @@ async fn test_get_with_discussion(
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn test_deserialize() {
+    /*
+    GIVEN a changeset element with a bounding box
+    WHEN deserializing it into a Changeset
+    THEN the bounding box attributes are gathered into the bbox
+    */
+    // GIVEN
+    let xml = r#"
+        <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="false" closed_at="2008-11-08T20:07:39+01:00" changes_count="4" min_lon="7.0" min_lat="49.0" max_lon="8.0" max_lat="50.0">
+            <tag k="comment" v="Streets"/>
+        </changeset>
+    "#;
+
+    // WHEN
+    let actual: types::Changeset = quick_xml::de::from_str(xml).unwrap();
+
+    // THEN
+    let expected = types::Changeset {
+        id: 10,
+        user: "fred".into(),
+        uid: 123,
+        created_at: "2008-11-08T19:07:39+01:00".into(),
+        closed_at: Some("2008-11-08T20:07:39+01:00".into()),
+        open: false,
+        comments_count: 0,
+        changes_count: 4,
+        bbox: Some(types::BoundingBox {
+            left: 7.0,
+            bottom: 49.0,
+            right: 8.0,
+            top: 50.0,
+        }),
+        discussion: None,
+        tags: vec![types::Tag::new("comment", "Streets")],
+    };
+
+    assert_eq!(actual, expected);
+}
+
 #[rstest(body, response_str, expected,
     case(
         vec![types::Tag::new("created_by", "JOSM 1.61")],
         r#"
         <osm>
-            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
+            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" comments_count="0" changes_count="4" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
                 <tag k="created_by" v="JOSM 1.61"/>
             </changeset>
         </osm>
@@ -205,10 +296,14 @@ async fn test_get_with_discussion(
             created_at: "2008-11-08T19:07:39+01:00".into(),
             closed_at: None,
             open: true,
-            min_lon: Some(7.0191821),
-            min_lat: Some(49.2785426),
-            max_lon: Some(7.0197485),
-            max_lat: Some(49.2793101),
+            comments_count: 0,
+            changes_count: 4,
+            bbox: Some(types::BoundingBox {
+                left: 7.0191821,
+                bottom: 49.2785426,
+                right: 7.0197485,
+                top: 49.2793101,
+            }),
             discussion: None,
             tags: vec![types::Tag {
                 k: "created_by".into(),
@@ -381,6 +476,70 @@ async fn test_download(
     assert_eq!(actual, expected);
 }
 
+#[rstest]
+#[actix_rt::test]
+async fn test_download_stream(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the download_stream() function with a changeset ID
+    THEN returns the elements of the changeset with their action
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let response_str = r#"
+        <osmChange version="0.6" generator="acme osm editor">
+            <create>
+                <node id="1" changeset="10" version="1" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" visible="true"/>
+            </create>
+            <modify>
+                <way id="2" changeset="10" version="2" timestamp="2009-12-09T08:19:00Z" user="user" uid="1" visible="true">
+                    <nd ref="1"/>
+                </way>
+            </modify>
+            <delete>
+                <node id="3" changeset="10" version="3" timestamp="2009-12-09T08:19:00Z" visible="false"/>
+            </delete>
+        </osmChange>
+    "#;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changeset/10/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(response_str, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client
+        .changeset()
+        .download_stream(10)
+        .await
+        .unwrap()
+        .map(|change| {
+            let (action, element) = change.unwrap();
+            let id = match element {
+                types::Element::Node(n) => format!("node/{}", n.id),
+                types::Element::Way(w) => format!("way/{}", w.id),
+                types::Element::Relation(r) => format!("relation/{}", r.id),
+            };
+
+            (action, id)
+        })
+        .collect::<Vec<(types::ChangeAction, String)>>()
+        .await;
+
+    // THEN
+    let expected = vec![
+        (types::ChangeAction::Create, "node/1".to_string()),
+        (types::ChangeAction::Modify, "way/2".to_string()),
+        (types::ChangeAction::Delete, "node/3".to_string()),
+    ];
+
+    assert_eq!(actual, expected);
+}
+
 #[rstest( body, response_str, expected,
     case(
         types::ChangesetChanges {
@@ -414,9 +573,9 @@ async fn test_download(
         </diffResult>
         "#,
         types::DiffResult {
-            nodes:vec![types::DiffNode { old_id:1234, new_id:42, new_version:2 }],
-            ways:vec![types::DiffWay { old_id:1234, new_id:42, new_version:2 }],
-            relations:vec![types::DiffRelation { old_id:1234, new_id:42, new_version:2 }],
+            nodes:vec![types::DiffNode { old_id:1234, new_id:Some(42), new_version:Some(2) }],
+            ways:vec![types::DiffWay { old_id:1234, new_id:Some(42), new_version:Some(2) }],
+            relations:vec![types::DiffRelation { old_id:1234, new_id:Some(42), new_version:Some(2) }],
         }
     ),
 )]
@@ -488,7 +647,7 @@ async fn test_comment(credentials: types::Credentials, changeset_id: u64, commen
         10,
         r#"
         <osm>
-            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
+            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" comments_count="0" changes_count="4" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
                 <tag k="created_by" v="JOSM 1.61"/>
             </changeset>
         </osm>
@@ -500,10 +659,14 @@ async fn test_comment(credentials: types::Credentials, changeset_id: u64, commen
             created_at: "2008-11-08T19:07:39+01:00".into(),
             closed_at: None,
             open: true,
-            min_lon: Some(7.0191821),
-            min_lat: Some(49.2785426),
-            max_lon: Some(7.0197485),
-            max_lat: Some(49.2793101),
+            comments_count: 0,
+            changes_count: 4,
+            bbox: Some(types::BoundingBox {
+                left: 7.0191821,
+                bottom: 49.2785426,
+                right: 7.0197485,
+                top: 49.2793101,
+            }),
             discussion: None,
             tags: vec![types::Tag {
                 k: "created_by".into(),
@@ -548,7 +711,7 @@ async fn test_subscribe(
         10,
         r#"
         <osm>
-            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
+            <changeset id="10" user="fred" uid="123" created_at="2008-11-08T19:07:39+01:00" open="true" comments_count="0" changes_count="4" min_lon="7.0191821" min_lat="49.2785426" max_lon="7.0197485" max_lat="49.2793101">
                 <tag k="created_by" v="JOSM 1.61"/>
             </changeset>
         </osm>
@@ -560,10 +723,14 @@ async fn test_subscribe(
             created_at: "2008-11-08T19:07:39+01:00".into(),
             closed_at: None,
             open: true,
-            min_lon: Some(7.0191821),
-            min_lat: Some(49.2785426),
-            max_lon: Some(7.0197485),
-            max_lat: Some(49.2793101),
+            comments_count: 0,
+            changes_count: 4,
+            bbox: Some(types::BoundingBox {
+                left: 7.0191821,
+                bottom: 49.2785426,
+                right: 7.0197485,
+                top: 49.2793101,
+            }),
             discussion: None,
             tags: vec![types::Tag {
                 k: "created_by".into(),
@@ -604,3 +771,358 @@ async fn test_unsubscribe(
     // THEN
     assert_eq!(actual, expected);
 }
+
+#[rstest]
+#[actix_rt::test]
+async fn test_revert(credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a changeset creating a node and modifying two ways
+        AND one of the ways modified again by a later changeset
+    WHEN calling the revert() function with the changeset ID and a target changeset ID
+    THEN the node is deleted and the way is restored in the target changeset
+        AND the way edited later is reported as a conflict
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changeset/10/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osmChange version="0.6" generator="OpenStreetMap server">
+                <create>
+                    <node id="1" visible="true" version="1" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2" lat="1.0" lon="2.0"/>
+                </create>
+                <modify>
+                    <way id="5" visible="true" version="2" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2">
+                        <nd ref="1"/>
+                        <nd ref="3"/>
+                    </way>
+                    <way id="6" visible="true" version="4" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2"/>
+                </modify>
+            </osmChange>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1/history"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <node id="1" visible="true" version="1" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2" lat="1.0" lon="2.0"/>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1/ways"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <way id="5" visible="true" version="2" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2">
+                    <nd ref="1"/>
+                    <nd ref="3"/>
+                </way>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1/relations"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"<osm version="0.6"></osm>"#, "application/xml"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/way/5/history"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <way id="5" visible="true" version="1" changeset="8" timestamp="2020-12-01T10:00:00Z" user="mapper" uid="1">
+                    <nd ref="3"/>
+                    <nd ref="4"/>
+                </way>
+                <way id="5" visible="true" version="2" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2">
+                    <nd ref="1"/>
+                    <nd ref="3"/>
+                </way>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/way/6/history"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <way id="6" visible="true" version="3" changeset="8" timestamp="2020-12-01T10:00:00Z" user="mapper" uid="1"/>
+                <way id="6" visible="true" version="4" changeset="10" timestamp="2020-12-09T22:51:17Z" user="vandal" uid="2"/>
+                <way id="6" visible="true" version="5" changeset="11" timestamp="2020-12-10T08:00:00Z" user="mapper" uid="1"/>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/0.6/changeset/20/upload"))
+        .and(body_string_contains(
+            r#"<modify><way id="5" visible="true" version="2" changeset="20""#,
+        ))
+        .and(body_string_contains(
+            r#"<delete><node id="1" visible="true" version="1" changeset="20""#,
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <diffResult generator="OpenStreetMap Server" version="0.6">
+                <way old_id="5" new_id="5" new_version="3"/>
+                <node old_id="1"/>
+            </diffResult>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), credentials);
+
+    // WHEN
+    let actual = client.changeset().revert(10, 20).await.unwrap();
+
+    // THEN
+    let expected = types::RevertResult {
+        diff: Some(types::DiffResult {
+            nodes: vec![types::DiffNode {
+                old_id: 1,
+                new_id: None,
+                new_version: None,
+            }],
+            ways: vec![types::DiffWay {
+                old_id: 5,
+                new_id: Some(5),
+                new_version: Some(3),
+            }],
+            relations: vec![],
+        }),
+        conflicts: vec![types::RevertConflict {
+            element_type: types::ElementType::Way,
+            id: 6,
+            version: 4,
+            current_version: 5,
+        }],
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_revert_changes_referenced(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a changeset creating a node and adding it to a way
+        AND the way modified again by a later changeset
+    WHEN calling the revert_changes() function with the changeset ID and a target changeset ID
+    THEN the way is reported as a conflict
+        AND the node still referenced by the way is reported as a conflict instead of deleted
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let histories = [
+        (
+            "node/5",
+            r#"
+            <node id="5" visible="true" version="1" changeset="10" timestamp="2009-12-09T08:19:00Z" user="user" uid="1" lat="1.0" lon="2.0"/>
+            "#,
+        ),
+        (
+            "way/7",
+            r#"
+            <way id="7" visible="true" version="1" changeset="9" timestamp="2009-12-09T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="2"/></way>
+            <way id="7" visible="true" version="2" changeset="10" timestamp="2009-12-09T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="5"/><nd ref="2"/></way>
+            <way id="7" visible="true" version="3" changeset="11" timestamp="2009-12-10T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="5"/><nd ref="2"/><tag k="highway" v="path"/></way>
+            "#,
+        ),
+    ];
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changeset/10/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osmChange version="0.6" generator="OpenStreetMap server">
+                <create>
+                    <node id="5" visible="true" version="1" changeset="10" timestamp="2009-12-09T08:19:00Z" user="user" uid="1" lat="1.0" lon="2.0"/>
+                </create>
+                <modify>
+                    <way id="7" visible="true" version="2" changeset="10" timestamp="2009-12-09T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="5"/><nd ref="2"/></way>
+                </modify>
+            </osmChange>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/5/ways"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <way id="7" visible="true" version="3" changeset="11" timestamp="2009-12-10T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="5"/><nd ref="2"/><tag k="highway" v="path"/></way>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/5/relations"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"<osm version="0.6"></osm>"#, "application/xml"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    for (element, history) in histories {
+        Mock::given(method("GET"))
+            .and(path(format!("/api/0.6/{element}/history")))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                format!(r#"<osm version="0.6">{history}</osm>"#),
+                "application/xml",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client.changeset().revert_changes(10, 20).await.unwrap();
+
+    // THEN
+    let expected = types::RevertPlan {
+        changes: types::ChangesetChanges {
+            modifications: vec![],
+            creations: vec![],
+            deletions: vec![],
+        },
+        conflicts: vec![
+            types::RevertConflict {
+                element_type: types::ElementType::Way,
+                id: 7,
+                version: 2,
+                current_version: 3,
+            },
+            types::RevertConflict {
+                element_type: types::ElementType::Node,
+                id: 5,
+                version: 1,
+                current_version: 1,
+            },
+        ],
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_revert_changes_used_later(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a changeset creating a node
+        AND a later changeset adding the node to a way
+    WHEN calling the revert_changes() function with the changeset ID and a target changeset ID
+    THEN the node is reported as a conflict instead of deleted
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let node = r#"<node id="5" visible="true" version="1" changeset="10" timestamp="2009-12-09T08:19:00Z" user="user" uid="1" lat="1.0" lon="2.0"/>"#;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changeset/10/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!(r#"<osmChange version="0.6"><create>{node}</create></osmChange>"#),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/5/history"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!(r#"<osm version="0.6">{node}</osm>"#),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/5/ways"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6">
+                <way id="8" visible="true" version="2" changeset="11" timestamp="2009-12-10T08:19:00Z" user="user" uid="1"><nd ref="1"/><nd ref="5"/></way>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/5/relations"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"<osm version="0.6"></osm>"#, "application/xml"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client.changeset().revert_changes(10, 20).await.unwrap();
+
+    // THEN
+    let expected = types::RevertPlan {
+        changes: types::ChangesetChanges {
+            modifications: vec![],
+            creations: vec![],
+            deletions: vec![],
+        },
+        conflicts: vec![types::RevertConflict {
+            element_type: types::ElementType::Node,
+            id: 5,
+            version: 1,
+            current_version: 1,
+        }],
+    };
+
+    assert_eq!(actual, expected);
+}
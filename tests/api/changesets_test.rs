@@ -1,8 +1,10 @@
+use futures::StreamExt;
+use openstreetmap_api::errors::OpenstreetmapError;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use pretty_assertions::assert_eq;
 use rstest::*;
-use wiremock::matchers::{method, path, query_param};
+use wiremock::matchers::{method, path, query_param, query_param_is_missing};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use super::utils::no_credentials;
@@ -27,10 +29,14 @@ fn response_str() -> String {
             created_at: "2020-12-09T22:51:17Z".into(),
             closed_at: Some("2020-12-09T22:51:18Z".into()),
             open: false,
-            min_lon: Some(-2.0845198),
-            min_lat: Some(57.1444672),
-            max_lon: Some(-2.0814377),
-            max_lat: Some(57.1447233),
+            comments_count: 0,
+            changes_count: 3,
+            bbox: Some(types::BoundingBox {
+                left: -2.0845198,
+                bottom: 57.1444672,
+                right: -2.0814377,
+                top: 57.1447233,
+            }),
             discussion: None,
             tags: vec![types::Tag {
                 k: "comment".into(),
@@ -107,6 +113,8 @@ async fn test_get_with_query(no_credentials: types::Credentials, response_str: S
         open: None,
         closed: None,
         changeset_ids: None,
+        limit: None,
+        order: None,
     };
 
     let actual = client.changesets(query).await.unwrap();
@@ -114,3 +122,235 @@ async fn test_get_with_query(no_credentials: types::Credentials, response_str: S
     // THEN
     assert_eq!(actual.is_empty(), false);
 }
+
+fn changesets_page(changesets: &[(u64, &str)]) -> String {
+    let changesets = changesets
+        .iter()
+        .map(|(id, created_at)| {
+            format!(
+                r#"<changeset id="{id}" created_at="{created_at}" open="false" closed_at="{created_at}" uid="10723" user="expobrain"/>"#
+            )
+        })
+        .collect::<String>();
+
+    format!(r#"<osm version="0.6">{changesets}</osm>"#)
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND more changesets than fit in a page
+    WHEN calling the changesets_stream() function
+    THEN the pages are followed backwards in time
+        AND the changesets repeated at page boundaries are returned once
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param_is_missing("time"))
+        .and(query_param("limit", "2"))
+        .and(query_param("order", "newest"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(5, "2020-12-09T12:00:05Z"), (4, "2020-12-09T12:00:03Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param(
+            "time",
+            "1970-01-01T00:00:00Z,2020-12-09T12:00:03Z",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(4, "2020-12-09T12:00:03Z"), (2, "2020-12-09T12:00:01Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param(
+            "time",
+            "1970-01-01T00:00:00Z,2020-12-09T12:00:01Z",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(2, "2020-12-09T12:00:01Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let query = types::ChangesetQueryParams {
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .changesets_stream(query)
+        .map(|changeset| changeset.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec![5, 4, 2]);
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream_same_second(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a full page of changesets created within the same second
+    WHEN calling the changesets_stream() function
+    THEN the window moves past that second once the page only repeats changesets
+        AND the older changesets are returned
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param_is_missing("time"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(5, "2020-12-09T12:00:05Z"), (4, "2020-12-09T12:00:05Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param(
+            "time",
+            "1970-01-01T00:00:00Z,2020-12-09T12:00:05Z",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(5, "2020-12-09T12:00:05Z"), (4, "2020-12-09T12:00:05Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param(
+            "time",
+            "1970-01-01T00:00:00Z,2020-12-09T12:00:04Z",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(2, "2020-12-09T12:00:01Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let query = types::ChangesetQueryParams {
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .changesets_stream(query)
+        .map(|changeset| changeset.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec![5, 4, 2]);
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream_repeated_page(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a server returning the same full page whatever the time window
+    WHEN calling the changesets_stream() function
+    THEN the changesets of the page are returned once
+        AND the stream ends with an error instead of requesting the page forever
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(5, "2020-12-09T12:00:05Z"), (4, "2020-12-09T12:00:03Z")]),
+            "application/xml",
+        ))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let query = types::ChangesetQueryParams {
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .changesets_stream(query)
+        .map(|changeset| changeset.map(|c| c.id))
+        .collect::<Vec<_>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual.len(), 3);
+    assert_eq!(actual[0].as_ref().unwrap(), &5);
+    assert_eq!(actual[1].as_ref().unwrap(), &4);
+    assert!(matches!(actual[2], Err(OpenstreetmapError::PagingStalled)));
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream_limit(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the changesets_stream() function with a limit above the server's maximum
+    THEN the pages are requested with the maximum limit
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/changesets"))
+        .and(query_param("limit", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            changesets_page(&[(5, "2020-12-09T12:00:05Z")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let query = types::ChangesetQueryParams {
+        limit: Some(500),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .changesets_stream(query)
+        .map(|changeset| changeset.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec![5]);
+}
@@ -1,3 +1,4 @@
+use openstreetmap_api::diff::TagBlame;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use pretty_assertions::assert_eq;
@@ -34,7 +35,7 @@ async fn test_create_element(
     credentials: types::Credentials,
     node: types::Node,
     response_str: &str,
-    expected: u64,
+    expected: i64,
 ) {
     /*
     GIVEN an OSM client
@@ -90,7 +91,7 @@ async fn test_create_element(
 #[actix_rt::test]
 async fn test_get(
     no_credentials: types::Credentials,
-    element_id: u64,
+    element_id: i64,
     response_str: &str,
     expected: types::Node,
 ) {
@@ -252,7 +253,7 @@ async fn test_delete_element(
 #[actix_rt::test]
 async fn test_history(
     no_credentials: types::Credentials,
-    element_id: u64,
+    element_id: i64,
     response_str: &str,
     expected: Vec<types::Node>,
 ) {
@@ -280,6 +281,69 @@ async fn test_history(
     assert_eq!(actual, expected);
 }
 
+#[rstest]
+#[actix_rt::test]
+async fn test_blame(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a way whose name was changed by a later version
+    WHEN calling the blame() function
+    THEN every current tag is attributed to the version which last set its value
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/way/1234/history"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm>
+                <way id="1234" changeset="42" version="1" timestamp="2009-12-09T08:19:00Z" uid="1" user="alice" visible="true">
+                    <nd ref="1"/>
+                    <tag k="highway" v="residential"/>
+                    <tag k="name" v="Main Street"/>
+                </way>
+                <way id="1234" changeset="43" version="2" timestamp="2010-01-10T10:00:00Z" uid="2" user="bob" visible="true">
+                    <nd ref="1"/>
+                    <tag k="highway" v="residential"/>
+                    <tag k="name" v="High Street"/>
+                </way>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client.ways().blame(1234).await.unwrap();
+
+    // THEN
+    let expected = vec![
+        TagBlame {
+            key: "highway".into(),
+            value: "residential".into(),
+            version: 1,
+            changeset: 42,
+            timestamp: "2009-12-09T08:19:00Z".into(),
+            user: Some("alice".into()),
+        },
+        TagBlame {
+            key: "name".into(),
+            value: "High Street".into(),
+            version: 2,
+            changeset: 43,
+            timestamp: "2010-01-10T10:00:00Z".into(),
+            user: Some("bob".into()),
+        },
+    ];
+
+    assert_eq!(actual, expected);
+}
+
 #[rstest(element_id, version_id, response_str, expected,
     case(
         1234,
@@ -311,7 +375,7 @@ async fn test_history(
 #[actix_rt::test]
 async fn test_version(
     no_credentials: types::Credentials,
-    element_id: u64,
+    element_id: i64,
     version_id: u64,
     response_str: &str,
     expected: types::Node,
@@ -474,6 +538,159 @@ async fn test_multi_get(
     assert_eq!(actual, expected);
 }
 
+#[actix_rt::test]
+async fn test_multi_get_latest_version_twice() {
+    /*
+    GIVEN an OSM client
+    WHEN calling the multi_get() function with a node and its latest version
+    THEN returns the node once
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/nodes/"))
+        .and(query_param("nodes", "1234,1234v2"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"<osm><node id="1234" changeset="42" version="2" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true" /></osm>"#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+
+    // WHEN
+    let actual = client
+        .nodes()
+        .multi_get(vec![
+            types::ElementIdParam::new(1234, None),
+            types::ElementIdParam::new(1234, Some(2)),
+        ])
+        .await
+        .unwrap();
+
+    // THEN
+    assert_eq!(actual.len(), 1);
+    assert_eq!((actual[0].id, actual[0].version), (1234, 2));
+}
+
+#[rstest(max_url_length, element_id_params, responses, expected,
+    case(
+        1,
+        vec![
+            types::ElementIdParam::new(2000, None),
+            types::ElementIdParam::new(1234, Some(2)),
+            types::ElementIdParam::new(3000, None),
+        ],
+        vec![
+            ("2000", 200, r#"<osm><node id="2000" changeset="42" version="1" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true" /></osm>"#),
+            ("1234v2", 200, r#"<osm><node id="1234" changeset="42" version="2" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true" /></osm>"#),
+            ("3000", 404, ""),
+        ],
+        types::MultiGetResult {
+            found: vec![
+                types::Node {
+                    id: 2000,
+                    changeset: 42,
+                    version: 1,
+                    uid: Some(1),
+                    timestamp: "2009-12-09T08:19:00Z".into(),
+                    user: Some("user".into()),
+                    visible: true,
+                    lat: Some(1.0),
+                    lon: Some(2.0),
+                    tags: vec![],
+                },
+                types::Node {
+                    id: 1234,
+                    changeset: 42,
+                    version: 2,
+                    uid: Some(1),
+                    timestamp: "2009-12-09T08:19:00Z".into(),
+                    user: Some("user".into()),
+                    visible: true,
+                    lat: Some(1.0),
+                    lon: Some(2.0),
+                    tags: vec![],
+                },
+            ],
+            missing: vec![types::ElementIdParam::new(3000, None)],
+        }
+    ),
+    case(
+        openstreetmap_api::DEFAULT_MAX_URL_LENGTH,
+        vec![
+            types::ElementIdParam::new(3000, None),
+            types::ElementIdParam::new(2000, None),
+        ],
+        vec![
+            ("3000,2000", 404, ""),
+            ("3000", 404, ""),
+            ("2000", 200, r#"<osm><node id="2000" changeset="42" version="1" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true" /></osm>"#),
+        ],
+        types::MultiGetResult {
+            found: vec![types::Node {
+                id: 2000,
+                changeset: 42,
+                version: 1,
+                uid: Some(1),
+                timestamp: "2009-12-09T08:19:00Z".into(),
+                user: Some("user".into()),
+                visible: true,
+                lat: Some(1.0),
+                lon: Some(2.0),
+                tags: vec![],
+            }],
+            missing: vec![types::ElementIdParam::new(3000, None)],
+        }
+    )
+)]
+#[actix_rt::test]
+async fn test_multi_get_partial(
+    no_credentials: types::Credentials,
+    max_url_length: usize,
+    element_id_params: Vec<types::ElementIdParam>,
+    responses: Vec<(&str, u16, &str)>,
+    expected: types::MultiGetResult<types::Node, types::ElementIdParam>,
+) {
+    /*
+    GIVEN an OSM client with a maximum URL length
+    WHEN calling the multi_get_partial() function
+    THEN the ids are fetched in batches
+        AND the nodes are returned in request order
+        AND the missing ids are reported
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    for (ids, status, response_str) in responses {
+        Mock::given(method("GET"))
+            .and(path("/api/0.6/nodes/"))
+            .and(query_param("nodes", ids))
+            .respond_with(
+                ResponseTemplate::new(status).set_body_raw(response_str, "application/xml"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials)
+        .with_max_url_length(max_url_length)
+        .with_concurrency(2);
+
+    // WHEN
+    let actual = client
+        .nodes()
+        .multi_get_partial(element_id_params)
+        .await
+        .unwrap();
+
+    // THEN
+    assert_eq!(actual, expected);
+}
+
 #[rstest(element_id, response_str, expected,
     case(
         4507,
@@ -504,7 +721,7 @@ async fn test_multi_get(
 #[actix_rt::test]
 async fn test_relations(
     no_credentials: types::Credentials,
-    element_id: u64,
+    element_id: i64,
     response_str: &str,
     expected: Vec<types::Relation>,
 ) {
@@ -558,7 +775,7 @@ async fn test_relations(
 #[actix_rt::test]
 async fn test_ways(
     no_credentials: types::Credentials,
-    node_id: u64,
+    node_id: i64,
     response_str: &str,
     expected: Vec<types::Way>,
 ) {
@@ -625,7 +842,7 @@ async fn test_ways(
 #[actix_rt::test]
 async fn test_way_full(
     no_credentials: types::Credentials,
-    way_id: u64,
+    way_id: i64,
     response_str: &str,
     expected: types::WayFull,
 ) {
@@ -710,7 +927,7 @@ async fn test_way_full(
 #[actix_rt::test]
 async fn test_relation_full(
     no_credentials: types::Credentials,
-    relation_id: u64,
+    relation_id: i64,
     response_str: &str,
     expected: types::RelationFull,
 ) {
@@ -1,3 +1,5 @@
+use futures::StreamExt;
+use openstreetmap_api::errors::OpenstreetmapError;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use pretty_assertions::assert_eq;
@@ -148,3 +150,265 @@ async fn test_get(no_credentials: types::Credentials, response_str: &str, expect
     // THEN
     assert_eq!(actual, expected);
 }
+
+fn capabilities_response(maximum_area: f64) -> String {
+    format!(
+        r#"
+        <osm version="0.6" generator="OpenStreetMap server">
+            <api>
+                <version minimum="0.6" maximum="0.6"/>
+                <area maximum="{maximum_area}"/>
+                <note_area maximum="25"/>
+                <tracepoints per_page="5000"/>
+                <waynodes maximum="2000"/>
+                <changesets maximum_elements="10000"/>
+                <timeout seconds="300"/>
+                <status database="online" api="online" gpx="online"/>
+            </api>
+            <policy>
+                <imagery>
+                    <blacklist regex=".*\.here\.com[/:].*"/>
+                </imagery>
+            </policy>
+        </osm>
+        "#
+    )
+}
+
+async fn mount_tiles(mock_server: &MockServer) {
+    for (tile, node_id) in [
+        ("0,0,1,1", 1),
+        ("1,0,2,1", 2),
+        ("0,1,1,2", 3),
+        ("1,1,2,2", 4),
+    ] {
+        let response_str = format!(
+            r#"
+            <osm version="0.6">
+                <bounds minlat="0" minlon="0" maxlat="1" maxlon="1"/>
+                <node id="{node_id}" visible="true" version="1" changeset="1" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0" lon="1.0"/>
+                <node id="100" visible="true" version="1" changeset="1" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0" lon="1.0"/>
+                <way id="200" visible="true" version="1" changeset="1" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1">
+                    <nd ref="{node_id}"/>
+                    <nd ref="100"/>
+                </way>
+            </osm>
+            "#
+        );
+
+        Mock::given(method("GET"))
+            .and(path("/api/0.6/map"))
+            .and(query_param("bbox", tile))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(response_str, "application/xml"))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+}
+
+#[rstest(maximum_area, bbox_requests, case(4.0, 1), case(1.0, 0))]
+#[actix_rt::test]
+async fn test_get_large(no_credentials: types::Credentials, maximum_area: f64, bbox_requests: u64) {
+    /*
+    GIVEN an OSM client
+        AND a bbox larger than the maximum area of the capabilities, or rejected by the server
+        for having too many nodes
+    WHEN calling the map_large() function
+    THEN the bbox is downloaded as four tiles
+        AND the elements shared by the tiles are returned once
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/capabilities"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(capabilities_response(maximum_area), "application/xml"),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .and(query_param("bbox", "0,0,2,2"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            "You requested too many nodes (limit is 50000). Either request a smaller area, or use planet.osm",
+        ))
+        .expect(bbox_requests)
+        .mount(&mock_server)
+        .await;
+
+    mount_tiles(&mock_server).await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials).with_concurrency(4);
+    let bbox = types::BoundingBox {
+        left: 0.0,
+        bottom: 0.0,
+        right: 2.0,
+        top: 2.0,
+    };
+
+    // WHEN
+    let actual = client.map_large(&bbox).await.unwrap();
+
+    // THEN
+    assert_eq!(actual.bounds, bbox);
+    assert_eq!(
+        actual.nodes.iter().map(|n| n.id).collect::<Vec<i64>>(),
+        vec![1, 100, 2, 3, 4]
+    );
+    assert_eq!(
+        actual.ways.iter().map(|w| w.id).collect::<Vec<i64>>(),
+        vec![200]
+    );
+    assert!(actual.relations.is_empty());
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_large_bad_request(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a bbox rejected by the server for another reason than its size
+    WHEN calling the map_large() function
+    THEN the error is returned without splitting the bbox
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let message = "The latitudes must be between -90 and 90, longitudes between -180 and 180 and the minima must be less than the maxima.";
+
+    Mock::given(method("GET"))
+        .and(path("/api/capabilities"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(capabilities_response(0.25), "application/xml"),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(message))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let bbox = types::BoundingBox {
+        left: 0.0,
+        bottom: 95.0,
+        right: 0.1,
+        top: 95.1,
+    };
+
+    // WHEN
+    let actual = client.map_large(&bbox).await;
+
+    // THEN
+    assert!(matches!(
+        actual,
+        Err(OpenstreetmapError::Client { code, error }) if code == 400 && error == message
+    ));
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the map_stream() function
+    THEN returns the nodes, ways and relations inside the bbox in document order
+        AND the bounds of the response
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let response_str = r#"
+        <osm version="0.6" generator="CGImap 0.8.3">
+            <bounds minlat="2.0000000" minlon="1.0000000" maxlat="4.0000000" maxlon="3.0000000"/>
+            <node id="1150316" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0000000" lon="1.0000000"/>
+            <way id="49780" visible="true" version="1" changeset="2308" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1">
+                <nd ref="1150316"/>
+            </way>
+            <relation id="4507" visible="true" version="1" changeset="3198" timestamp="2010-02-25T19:52:18Z" user="rus" uid="96">
+                <member type="way" ref="49780" role="outer"/>
+            </relation>
+        </osm>
+    "#;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .and(query_param("bbox", "1,2,3,4"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(response_str, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let (bounds, elements) = client.map_stream(&bbox).await.unwrap();
+    let actual = elements
+        .map(|element| match element.unwrap() {
+            types::Element::Node(n) => format!("node/{}", n.id),
+            types::Element::Way(w) => format!("way/{}", w.id),
+            types::Element::Relation(r) => format!("relation/{}", r.id),
+        })
+        .collect::<Vec<String>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec!["node/1150316", "way/49780", "relation/4507"]);
+    assert_eq!(bounds, Some(bbox));
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_stream_error(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the map_stream() function on a response with a malformed element
+    THEN the elements before it are returned
+        AND the stream ends after the error
+    */
+
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let response_str = r#"
+        <osm version="0.6" generator="CGImap 0.8.3">
+            <node id="1150316" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0000000" lon="1.0000000"/>
+            <node id="not-a-number" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0000000" lon="1.0000000"/>
+            <node id="1150317" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0000000" lon="1.0000000"/>
+        </osm>
+    "#;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(response_str, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let (bounds, elements) = client.map_stream(&bbox).await.unwrap();
+    let actual = elements
+        .map(|element| element.is_ok())
+        .collect::<Vec<bool>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec![true, false]);
+    assert_eq!(bounds, None);
+}
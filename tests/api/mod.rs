@@ -1,4 +1,5 @@
 mod capabilities_test;
+#[allow(clippy::let_unit_value)]
 mod changeset_test;
 mod changesets_test;
 mod elements_test;
@@ -1,8 +1,12 @@
+use futures::StreamExt;
+use openstreetmap_api::errors::OpenstreetmapError;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use rstest::*;
 use urlencoding::encode;
-use wiremock::matchers::{method, path, query_param, QueryParamExactMatcher};
+use wiremock::matchers::{
+    method, path, query_param, query_param_is_missing, QueryParamExactMatcher,
+};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use super::utils::credentials;
@@ -18,13 +22,13 @@ fn note() -> types::Note {
         comment_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16659/comment".into(),
         close_url: "https://master.apis.dev.openstreetmap.org/api/0.6/notes/16659/close".into(),
         created_at: "2019-06-15 08:26:04 UTC".into(),
-        status: "open".into(),
+        status: types::NoteStatus::Open,
         comments: vec![types::Comment {
-            id: 1234,
+            id: Some(1234),
             date: "2019-06-15 08:26:04 UTC".into(),
-            user: "userName".into(),
-            user_url: "https://master.apis.dev.openstreetmap.org/user/userName".into(),
-            action: "opened".into(),
+            user: Some("userName".into()),
+            user_url: Some("https://master.apis.dev.openstreetmap.org/user/userName".into()),
+            action: types::CommentAction::Opened,
             text: "ThisIsANote".into(),
             html: "<p>ThisIsANote</p>".into(),
         }],
@@ -94,7 +98,7 @@ fn note_response() -> &'static str {
             top: 4.0,
         },
         None,
-        Some(10),
+        Some(types::NoteClosedOption::ClosedWithin(10)),
         vec!(query_param("bbox", "1,2,3,4"), query_param("closed", "10")),
     ),
     case(
@@ -105,7 +109,7 @@ fn note_response() -> &'static str {
             top: 4.0,
         },
         None,
-        Some(-1),
+        Some(types::NoteClosedOption::All),
         vec!(query_param("bbox", "1,2,3,4"), query_param("closed", "-1")),
     )
 )]
@@ -114,7 +118,7 @@ async fn test_get_by_bounding_box(
     no_credentials: types::Credentials,
     bbox: types::BoundingBox,
     limit: Option<u16>,
-    closed: Option<i64>,
+    closed: Option<types::NoteClosedOption>,
     request_params: Vec<QueryParamExactMatcher>,
     note_response: &str,
     notes: Vec<types::Note>,
@@ -176,6 +180,37 @@ async fn test_get(no_credentials: types::Credentials, note_response: &str, note:
     assert_eq!(actual, note);
 }
 
+#[rstest]
+#[actix_rt::test]
+async fn test_get_unknown_status(no_credentials: types::Credentials, note_response: &str) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the get() function for a note with a status and an action this crate does not
+        know
+    THEN the note is returned with the unknown status and action
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let response = note_response
+        .replace("<status>open</status>", "<status>locked</status>")
+        .replace("<action>opened</action>", "<action>locked</action>");
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/16659"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(response, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client.notes().get(16659).await.unwrap();
+
+    // THEN
+    assert_eq!(actual.status, types::NoteStatus::Unknown);
+    assert_eq!(actual.comments[0].action, types::CommentAction::Unknown);
+}
+
 #[fixture]
 fn note_content() -> types::NoteContent {
     types::NoteContent {
@@ -363,7 +398,7 @@ async fn test_reopen(
     ),
     case(
         types::NoteSearchOptions {
-            q: "SearchTerm".into(), closed: Some(7), ..Default::default()
+            q: "SearchTerm".into(), closed: Some(types::NoteClosedOption::ClosedWithin(7)), ..Default::default()
         },
         vec!(
             query_param("q", "SearchTerm"),
@@ -372,7 +407,7 @@ async fn test_reopen(
     ),
     case(
         types::NoteSearchOptions {
-            q: "SearchTerm".into(), closed: Some(-1), ..Default::default()
+            q: "SearchTerm".into(), closed: Some(types::NoteClosedOption::All), ..Default::default()
         },
         vec!(
             query_param("q", "SearchTerm"),
@@ -479,6 +514,13 @@ async fn test_search(
     assert_eq!(actual, notes);
 }
 
+// Feed in the format of the notes feed of openstreetmap.org, with the HTML descriptions in
+// CDATA sections and `dc:creator` left out for anonymous comments
+#[fixture]
+fn feed_response() -> &'static str {
+    include_str!("../fixtures/notes_feed.rss")
+}
+
 #[rstest(bbox, request_param,
     case(
         types::BoundingBox {
@@ -495,13 +537,12 @@ async fn test_feed_by_boundng_box(
     no_credentials: types::Credentials,
     bbox: types::BoundingBox,
     request_param: QueryParamExactMatcher,
-    note_response: &str,
-    notes: Vec<types::Note>,
+    feed_response: &str,
 ) {
     /*
     GIVEN an OSM client
     WHEN calling the feed_by_bounding_box() function with bounding box
-    THEN returns a list of notes
+    THEN returns the items of the RSS feed
     */
     // GIVEN
     let mock_server = MockServer::start().await;
@@ -509,7 +550,7 @@ async fn test_feed_by_boundng_box(
     Mock::given(method("GET"))
         .and(path("/api/0.6/notes/feed"))
         .and(request_param)
-        .respond_with(ResponseTemplate::new(200).set_body_raw(note_response, "application/xml"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(feed_response, "application/rss+xml"))
         .mount(&mock_server)
         .await;
 
@@ -519,5 +560,284 @@ async fn test_feed_by_boundng_box(
     let actual = client.notes().feed_by_bounding_box(&bbox).await.unwrap();
 
     // THEN
-    assert_eq!(actual, notes);
+    let description = |by: &str, date: &str, text: &str| {
+        format!(
+            r#"<div class="note-comment" style="margin-top: 5px">
+  <div class="note-comment-description" style="font-size: smaller; color: #999999">{by} <abbr title="{date}">over 4 years ago</abbr></div>
+  <div class="note-comment-text"><p>{text}</p></div>
+</div>
+"#
+        )
+    };
+    let expected = vec![
+        types::NoteFeedItem {
+            title: "reactivated note (near Dover, Kent, England, CT16 1DQ, United Kingdom)".into(),
+            link: "https://www.openstreetmap.org/note/16659#c1237".into(),
+            action: Some(types::CommentAction::Reopened),
+            author: Some("userName".into()),
+            date: "Tue, 18 Jun 2019 09:12:45 +0000".into(),
+            lat: 51.1277,
+            lon: 1.3134,
+            description: description(
+                r#"Reactivated by <a href="https://www.openstreetmap.org/user/userName">userName</a>"#,
+                "Tue, 18 Jun 2019 09:12:45 +0000",
+                "The bench is gone again",
+            ),
+        },
+        types::NoteFeedItem {
+            title: "closed note (near Dover, Kent, England, CT16 1DQ, United Kingdom)".into(),
+            link: "https://www.openstreetmap.org/note/16659#c1236".into(),
+            action: Some(types::CommentAction::Closed),
+            author: Some("otherUser".into()),
+            date: "Mon, 17 Jun 2019 18:30:00 +0000".into(),
+            lat: 51.1277,
+            lon: 1.3134,
+            description: description(
+                r#"Resolved by <a href="https://www.openstreetmap.org/user/otherUser">otherUser</a>"#,
+                "Mon, 17 Jun 2019 18:30:00 +0000",
+                "Added the bench",
+            ),
+        },
+        types::NoteFeedItem {
+            title: "new comment (near Dover, Kent, England, CT16 1DQ, United Kingdom)".into(),
+            link: "https://www.openstreetmap.org/note/16659#c1235".into(),
+            action: Some(types::CommentAction::Commented),
+            author: Some("userName".into()),
+            date: "Sun, 16 Jun 2019 10:00:00 +0000".into(),
+            lat: 51.1277,
+            lon: 1.3134,
+            description: description(
+                r#"Comment from <a href="https://www.openstreetmap.org/user/userName">userName</a>"#,
+                "Sun, 16 Jun 2019 10:00:00 +0000",
+                "Still there",
+            ),
+        },
+        types::NoteFeedItem {
+            title: "new note (near Dover, Kent, England, CT16 1DQ, United Kingdom)".into(),
+            link: "https://www.openstreetmap.org/note/16659#c1234".into(),
+            action: Some(types::CommentAction::Opened),
+            author: None,
+            date: "Sat, 15 Jun 2019 08:26:04 +0000".into(),
+            lat: 51.1277,
+            lon: 1.3134,
+            description: description(
+                r#"Created by anonymous"#,
+                "Sat, 15 Jun 2019 08:26:04 +0000",
+                "Missing bench",
+            ),
+        },
+    ];
+
+    assert_eq!(actual, expected);
+}
+
+fn notes_page(notes: &[(u64, &str)]) -> String {
+    let notes = notes
+        .iter()
+        .map(|(id, created_at)| {
+            format!(
+                r#"
+                <note lon="0.1000000" lat="51.0000000">
+                    <id>{id}</id>
+                    <url>https://api.openstreetmap.org/api/0.6/notes/{id}</url>
+                    <comment_url>https://api.openstreetmap.org/api/0.6/notes/{id}/comment</comment_url>
+                    <close_url>https://api.openstreetmap.org/api/0.6/notes/{id}/close</close_url>
+                    <date_created>{created_at}</date_created>
+                    <status>open</status>
+                    <comments></comments>
+                </note>
+                "#
+            )
+        })
+        .collect::<String>();
+
+    format!(r#"<osm version="0.6">{notes}</osm>"#)
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_by_bounding_box_stream(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND more notes in a bbox than fit in a page
+    WHEN calling the get_by_bounding_box_stream() function
+    THEN the pages are followed forward in time
+        AND the notes repeated at page boundaries are returned once
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .and(query_param("bbox", "1,2,3,4"))
+        .and(query_param("limit", "100"))
+        .and(query_param("sort", "created_at"))
+        .and(query_param("order", "oldest"))
+        .and(query_param_is_missing("from"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(
+                notes_page(
+                    &(1..=100)
+                        .map(|id| (id, "2019-06-15 08:26:04 UTC"))
+                        .collect::<Vec<_>>(),
+                ),
+                "application/xml",
+            ),
+        )
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .and(query_param("bbox", "1,2,3,4"))
+        .and(query_param("from", "2019-06-15 08:26:04 UTC"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            notes_page(&[
+                (100, "2019-06-15 08:26:04 UTC"),
+                (101, "2019-06-16 10:00:00 UTC"),
+            ]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let actual = client
+        .notes()
+        .get_by_bounding_box_stream(&bbox, None)
+        .map(|note| note.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, (1..=101).collect::<Vec<u64>>());
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_search_stream_same_second(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a full page of notes created within the same second
+    WHEN calling the search_stream() function
+    THEN the window moves past that second once the page only repeats notes
+        AND the newer notes are returned
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .and(query_param_is_missing("from"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            notes_page(&[
+                (1, "2019-06-15 08:26:04 UTC"),
+                (2, "2019-06-15 08:26:04 UTC"),
+            ]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .and(query_param("from", "2019-06-15 08:26:04 UTC"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            notes_page(&[
+                (1, "2019-06-15 08:26:04 UTC"),
+                (2, "2019-06-15 08:26:04 UTC"),
+            ]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .and(query_param("from", "2019-06-15T08:26:05Z"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            notes_page(&[(3, "2019-06-16 10:00:00 UTC")]),
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let options = types::NoteSearchOptions {
+        q: "bench".into(),
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .notes()
+        .search_stream(options)
+        .map(|note| note.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, vec![1, 2, 3]);
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_get_by_bounding_box_stream_repeated_page(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+        AND a server returning the same full page whatever the date window
+    WHEN calling the get_by_bounding_box_stream() function
+    THEN the notes of the page are returned once
+        AND the stream ends with an error instead of requesting the page forever
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/search"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(
+                notes_page(
+                    &(1..=100)
+                        .map(|id| (id, "2019-06-15 08:26:04 UTC"))
+                        .collect::<Vec<_>>(),
+                ),
+                "application/xml",
+            ),
+        )
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let actual = client
+        .notes()
+        .get_by_bounding_box_stream(&bbox, None)
+        .collect::<Vec<_>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual.len(), 101);
+    assert!(actual[..100].iter().all(|note| note.is_ok()));
+    assert!(matches!(
+        actual[100],
+        Err(OpenstreetmapError::PagingStalled)
+    ));
 }
@@ -1,3 +1,4 @@
+use openstreetmap_api::errors::OpenstreetmapError;
 use openstreetmap_api::types;
 use openstreetmap_api::Openstreetmap;
 use pretty_assertions::assert_eq;
@@ -135,6 +136,101 @@ async fn test_users(
     assert_eq!(actual, expected);
 }
 
+#[rstest]
+#[actix_rt::test]
+async fn test_users_missing(no_credentials: types::Credentials) {
+    /*
+    GIVEN an OSM client
+    WHEN calling the users() function with an id the server does not return
+    THEN NotFound is returned, as for multi_get()
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/users"))
+        .and(query_param("users", "12023,1"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"
+            <osm version="0.6" generator="OpenStreetMap server">
+                <user id="12023" display_name="jbpbis" account_created="2007-08-16T01:35:56Z">
+                    <contributor-terms agreed="false"/>
+                </user>
+            </osm>
+            "#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials);
+
+    // WHEN
+    let actual = client.user().users(&[12023, 1]).await;
+
+    // THEN
+    assert!(matches!(actual, Err(OpenstreetmapError::NotFound)));
+}
+
+#[rstest(user_ids, responses, expected,
+    case(
+        vec![12023, 1, 12023],
+        vec![
+            ("12023", r#"
+            <osm version="0.6" generator="OpenStreetMap server">
+                <user id="12023" display_name="jbpbis" account_created="2007-08-16T01:35:56Z">
+                    <contributor-terms agreed="false"/>
+                </user>
+            </osm>
+            "#),
+            ("1", r#"<osm version="0.6" generator="OpenStreetMap server"></osm>"#),
+        ],
+        types::MultiGetResult {
+            found: vec![types::User {
+                id: 12023,
+                display_name: "jbpbis".into(),
+                account_created: "2007-08-16T01:35:56Z".into(),
+                ..Default::default()
+            }],
+            missing: vec![1],
+        }
+    )
+)]
+#[actix_rt::test]
+async fn test_users_partial(
+    no_credentials: types::Credentials,
+    user_ids: Vec<u64>,
+    responses: Vec<(&str, &str)>,
+    expected: types::MultiGetResult<types::User, u64>,
+) {
+    /*
+    GIVEN an OSM client with a short maximum URL length
+    WHEN calling the users_partial() function
+    THEN the ids are fetched in batches
+        AND the users not returned by the server are reported as missing
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    for (ids, response_str) in responses {
+        Mock::given(method("GET"))
+            .and(path("/api/0.6/users"))
+            .and(query_param("users", ids))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(response_str, "application/xml"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let client = Openstreetmap::new(mock_server.uri(), no_credentials).with_max_url_length(1);
+
+    // WHEN
+    let actual = client.user().users_partial(&user_ids).await.unwrap();
+
+    // THEN
+    assert_eq!(actual, expected);
+}
+
 #[rstest(response_str, expected,
     case(
         r#"
@@ -0,0 +1,206 @@
+#![cfg(feature = "blocking")]
+
+use openstreetmap_api::blocking::Openstreetmap;
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::types;
+use pretty_assertions::assert_eq;
+use wiremock::matchers::{body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const NODE_RESPONSE: &str = r#"
+<osm>
+    <node id="1234" changeset="42" version="2" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true">
+        <tag k="amenity" v="school"/>
+    </node>
+</osm>
+"#;
+
+// The mock server needs an async runtime, while the blocking client must run outside of it
+fn mock_server(mocks: Vec<Mock>) -> (actix_rt::Runtime, MockServer) {
+    let runtime = actix_rt::Runtime::new().unwrap();
+    let mock_server = runtime.block_on(async {
+        let mock_server = MockServer::start().await;
+
+        for mock in mocks {
+            mock.mount(&mock_server).await;
+        }
+
+        mock_server
+    });
+
+    (runtime, mock_server)
+}
+
+#[test]
+fn test_get() {
+    /*
+    GIVEN a blocking OSM client
+    WHEN calling nodes().get() with a node ID
+    THEN returns the node parsed like the async client does
+    */
+    // GIVEN
+    let (_runtime, mock_server) = mock_server(vec![Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))]);
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+
+    // WHEN
+    let actual = client.nodes().get(1234).unwrap();
+
+    // THEN
+    let expected = types::Node {
+        id: 1234,
+        changeset: 42,
+        version: 2,
+        uid: Some(1),
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: Some("user".into()),
+        visible: true,
+        lat: Some(12.1234567),
+        lon: Some(-8.7654321),
+        tags: vec![types::Tag::new("amenity", "school")],
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_update_with_auth() {
+    /*
+    GIVEN a blocking OSM client with credentials
+    WHEN calling nodes().update() with a node
+    THEN the node is sent as XML with basic authentication
+        AND returns the new version
+    */
+    // GIVEN
+    let (_runtime, mock_server) = mock_server(vec![Mock::given(method("PUT"))
+        .and(path("/api/0.6/node/1234"))
+        .and(header("authorization", "Basic dXNlcjpwYXNz"))
+        .and(header("content-type", "text/xml"))
+        .and(body_string_contains(r#"<node id="1234""#))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("3", "text/plain"))]);
+
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let client = Openstreetmap::new(mock_server.uri(), credentials);
+    let node = types::Node {
+        id: 1234,
+        changeset: 42,
+        version: 2,
+        uid: None,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: None,
+        visible: true,
+        lat: Some(12.1234567),
+        lon: Some(-8.7654321),
+        tags: vec![],
+    };
+
+    // WHEN
+    let actual = client.nodes().update(node).unwrap();
+
+    // THEN
+    assert_eq!(actual, 3);
+}
+
+#[test]
+fn test_not_found() {
+    /*
+    GIVEN a blocking OSM client
+    WHEN requesting a missing note
+    THEN returns the same error as the async client
+    */
+    // GIVEN
+    let (_runtime, mock_server) = mock_server(vec![Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/1"))
+        .respond_with(ResponseTemplate::new(404))]);
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+
+    // WHEN
+    let actual = client.notes().get(1);
+
+    // THEN
+    assert!(matches!(actual, Err(OpenstreetmapError::NotFound)));
+}
+
+#[test]
+fn test_map_stream() {
+    /*
+    GIVEN a blocking OSM client
+    WHEN calling map_stream() with a bounding box
+    THEN the elements are returned by an iterator
+    */
+    // GIVEN
+    let (_runtime, mock_server) = mock_server(vec![Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))]);
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let actual = client
+        .map_stream(&bbox)
+        .unwrap()
+        .1
+        .map(|element| match element.unwrap() {
+            types::Element::Node(node) => node.id,
+            _ => unreachable!(),
+        })
+        .collect::<Vec<i64>>();
+
+    // THEN
+    assert_eq!(actual, vec![1234]);
+}
+
+#[test]
+fn test_map_stream_chunks() {
+    /*
+    GIVEN a blocking OSM client
+        AND a map response larger than the chunks the body is read in
+    WHEN calling map_stream() with a bounding box
+    THEN every element is returned, including the ones split across chunks
+    */
+    // GIVEN
+    let nodes = (1..=1000)
+        .map(|id| {
+            format!(
+                r#"<node id="{id}" changeset="42" version="1" lat="1.0" lon="2.0" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true"/>"#
+            )
+        })
+        .collect::<String>();
+    let (_runtime, mock_server) = mock_server(vec![Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(format!("<osm>{nodes}</osm>"), "application/xml"),
+        )]);
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let actual = client
+        .map_stream(&bbox)
+        .unwrap()
+        .1
+        .map(|element| match element.unwrap() {
+            types::Element::Node(node) => node.id,
+            _ => unreachable!(),
+        })
+        .collect::<Vec<i64>>();
+
+    // THEN
+    assert_eq!(actual, (1..=1000).collect::<Vec<i64>>());
+}
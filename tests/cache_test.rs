@@ -0,0 +1,453 @@
+#![cfg(all(feature = "cache", feature = "reqwest"))]
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use openstreetmap_api::cache::{Cache, CacheEntry, CacheStore, DiskStore, MemoryStore};
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+use wiremock::matchers::{header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const NODE_RESPONSE: &str = r#"
+<osm>
+    <node id="1234" changeset="42" version="2" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true"/>
+</osm>
+"#;
+
+const NOTE_RESPONSE: &str = r#"
+<osm>
+    <note lon="0.1000000" lat="51.0000000">
+        <id>5</id>
+        <url>https://api.openstreetmap.org/api/0.6/notes/5</url>
+        <comment_url>https://api.openstreetmap.org/api/0.6/notes/5/comment</comment_url>
+        <close_url>https://api.openstreetmap.org/api/0.6/notes/5/close</close_url>
+        <date_created>2019-06-15 08:26:04 UTC</date_created>
+        <status>closed</status>
+        <comments/>
+    </note>
+</osm>
+"#;
+
+/// Shares a store between the cache of a client and the test
+#[derive(Debug, Clone, Default)]
+struct SharedStore(Arc<MemoryStore>);
+
+impl CacheStore for SharedStore {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CacheEntry>> {
+        self.0.get(url)
+    }
+
+    fn put<'a>(&'a self, url: &'a str, entry: CacheEntry) -> BoxFuture<'a, ()> {
+        self.0.put(url, entry)
+    }
+
+    fn remove_matching<'a>(
+        &'a self,
+        matches: &'a (dyn Fn(&str) -> bool + Sync),
+    ) -> BoxFuture<'a, ()> {
+        self.0.remove_matching(matches)
+    }
+}
+
+fn node_response() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml")
+}
+
+#[actix_rt::test]
+async fn test_fresh_response() {
+    /*
+    GIVEN an OSM client with a memory cache
+        AND a response fresh for an hour
+    WHEN fetching the same node twice
+    THEN the second node is served from the cache
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(node_response().insert_header("cache-control", "public, max-age=3600"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(MemoryStore::default()));
+
+    // WHEN
+    let first = client.nodes().get(1234).await.unwrap();
+    let second = client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    assert_eq!(first, second);
+}
+
+#[actix_rt::test]
+async fn test_revalidate_etag() {
+    /*
+    GIVEN an OSM client with a memory cache
+        AND a response with an ETag which must be revalidated
+    WHEN fetching the same node twice
+    THEN the second request sends If-None-Match
+        AND the node is served from the cache when the server answers 304
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .and(header("if-none-match", r#""abc""#))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(
+            node_response()
+                .insert_header("cache-control", "private, max-age=0, must-revalidate")
+                .insert_header("etag", r#""abc""#),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(MemoryStore::default()));
+
+    // WHEN
+    let first = client.nodes().get(1234).await.unwrap();
+    let second = client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    assert_eq!(first, second);
+}
+
+#[actix_rt::test]
+async fn test_version_cached_forever() {
+    /*
+    GIVEN an OSM client with a memory cache
+        AND a historical version which must be revalidated
+    WHEN fetching the same version twice
+    THEN the second version is served from the cache
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234/2"))
+        .respond_with(node_response().insert_header("cache-control", "no-cache"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let store = SharedStore::default();
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(store.clone()));
+
+    // WHEN
+    client.nodes().version(1234, 2).await.unwrap();
+    client.nodes().version(1234, 2).await.unwrap();
+
+    // THEN
+    let entry = store
+        .get(&format!("{}/api/0.6/node/1234/2", mock_server.uri()))
+        .await
+        .unwrap();
+
+    assert_eq!(entry.expires, None);
+}
+
+#[actix_rt::test]
+async fn test_not_cached() {
+    /*
+    GIVEN an OSM client with a memory cache
+        AND a response without ETag nor max-age
+    WHEN fetching the same node twice
+    THEN both requests are sent to the server
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(node_response())
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(MemoryStore::default()));
+
+    // WHEN
+    client.nodes().get(1234).await.unwrap();
+    client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    // the expectations are verified when the mock server is dropped
+}
+
+#[actix_rt::test]
+async fn test_authenticated_not_cached() {
+    /*
+    GIVEN an OSM client with credentials and a memory cache
+    WHEN fetching the user details twice
+    THEN both requests are sent to the server
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/user/details"))
+        .and(header_exists("authorization"))
+        .respond_with(ResponseTemplate::new(200).insert_header("cache-control", "max-age=3600"))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let store = SharedStore::default();
+    let client = Openstreetmap::new(mock_server.uri(), credentials)
+        .with_middleware(Cache::new(store.clone()));
+
+    // WHEN
+    let _ = client.user().details().await;
+    let _ = client.user().details().await;
+
+    // THEN
+    let url = format!("{}/api/0.6/user/details", mock_server.uri());
+
+    assert_eq!(store.get(&url).await, None);
+}
+
+#[actix_rt::test]
+async fn test_stream_not_cached() {
+    /*
+    GIVEN an OSM client with a memory cache
+        AND a map response fresh for an hour
+    WHEN streaming the same map twice
+    THEN both maps are downloaded from the server
+        AND the response is not stored
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(node_response().insert_header("cache-control", "max-age=3600"))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let store = SharedStore::default();
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(store.clone()));
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    for _ in 0..2 {
+        let (_, elements) = client.map_stream(&bbox).await.unwrap();
+        let elements = elements.collect::<Vec<_>>().await;
+
+        assert_eq!(elements.len(), 1);
+    }
+
+    // THEN
+    let url = format!("{}/api/0.6/map?bbox=1,2,3,4", mock_server.uri());
+
+    assert_eq!(store.get(&url).await, None);
+}
+
+#[actix_rt::test]
+async fn test_disk_store() {
+    /*
+    GIVEN two OSM clients with a disk cache in the same directory
+    WHEN fetching the same node with each client
+    THEN the second client is served from the cache written by the first one
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(node_response().insert_header("cache-control", "max-age=3600"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let directory = std::env::temp_dir().join(format!("osm-cache-test-{}", std::process::id()));
+    let first_client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(DiskStore::new(&directory)));
+    let second_client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(Cache::new(DiskStore::new(&directory)));
+
+    // WHEN
+    let first = first_client.nodes().get(1234).await.unwrap();
+    let second = second_client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    assert_eq!(first, second);
+
+    let files = std::fs::read_dir(&directory)
+        .unwrap()
+        .map(|file| file.unwrap().path())
+        .collect::<Vec<_>>();
+
+    // the temporary file of the write is renamed
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].extension().unwrap(), "entry");
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_update_evicts() {
+    /*
+    GIVEN an OSM client with a memory cache holding a node
+    WHEN updating the node
+    THEN the cached node is evicted
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(node_response().insert_header("cache-control", "max-age=3600"))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("PUT"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("3", "text/plain"))
+        .mount(&mock_server)
+        .await;
+
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let store = SharedStore::default();
+    let client = Openstreetmap::new(mock_server.uri(), credentials)
+        .with_middleware(Cache::new(store.clone()));
+    let url = format!("{}/api/0.6/node/1234", mock_server.uri());
+    let node = client.nodes().get(1234).await.unwrap();
+
+    assert!(store.get(&url).await.is_some());
+
+    // WHEN
+    client.nodes().update(node).await.unwrap();
+
+    // THEN
+    assert_eq!(store.get(&url).await, None);
+}
+
+fn cached_entry() -> CacheEntry {
+    CacheEntry {
+        etag: None,
+        expires: None,
+        body: NODE_RESPONSE.into(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_upload_evicts_elements() {
+    /*
+    GIVEN an OSM client with a memory cache holding elements, a map and a note
+    WHEN uploading a changeset
+    THEN every cached element and map is evicted
+        AND the historical versions and the note are kept
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/0.6/changeset/42/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"<diffResult version="0.6"><node old_id="1234" new_id="1234" new_version="3"/></diffResult>"#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let store = SharedStore::default();
+    let client = Openstreetmap::new(mock_server.uri(), credentials)
+        .with_middleware(Cache::new(store.clone()));
+    let url = |endpoint: &str| format!("{}/api/0.6/{endpoint}", mock_server.uri());
+    let evicted = [
+        "node/1234",
+        "nodes?nodes=1234,1235",
+        "node/1234/ways",
+        "way/7/full",
+        "map?bbox=1,2,3,4",
+    ];
+    let kept = ["node/1234/2", "notes/5"];
+
+    for endpoint in evicted.iter().chain(&kept) {
+        store.put(&url(endpoint), cached_entry()).await;
+    }
+
+    let changes = types::ChangesetChanges {
+        modifications: vec![],
+        creations: vec![],
+        deletions: vec![],
+    };
+
+    // WHEN
+    client.changeset().upload(42, changes).await.unwrap();
+
+    // THEN
+    for endpoint in evicted {
+        assert_eq!(store.get(&url(endpoint)).await, None, "{endpoint}");
+    }
+
+    for endpoint in kept {
+        assert!(store.get(&url(endpoint)).await.is_some(), "{endpoint}");
+    }
+}
+
+#[actix_rt::test]
+async fn test_note_comment_evicts_notes() {
+    /*
+    GIVEN an OSM client with a disk cache holding notes and a node
+    WHEN closing a note
+    THEN the cached notes are evicted
+        AND the node is kept
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/0.6/notes/5/close"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NOTE_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let directory =
+        std::env::temp_dir().join(format!("osm-cache-evict-test-{}", std::process::id()));
+    let store = DiskStore::new(&directory);
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let client = Openstreetmap::new(mock_server.uri(), credentials)
+        .with_middleware(Cache::new(store.clone()));
+    let url = |endpoint: &str| format!("{}/api/0.6/{endpoint}", mock_server.uri());
+
+    for endpoint in ["notes/5", "notes/search?q=bench", "node/1234"] {
+        store.put(&url(endpoint), cached_entry()).await;
+    }
+
+    // WHEN
+    client.notes().close(5, "fixed").await.unwrap();
+
+    // THEN
+    assert_eq!(store.get(&url("notes/5")).await, None);
+    assert_eq!(store.get(&url("notes/search?q=bench")).await, None);
+    assert_eq!(store.get(&url("node/1234")).await, Some(cached_entry()));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
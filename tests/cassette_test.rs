@@ -0,0 +1,216 @@
+#![cfg(feature = "cassette")]
+
+use openstreetmap_api::cassette::Cassette;
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::transport::ReqwestTransport;
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const NODE_RESPONSE: &str = r#"
+<osm>
+    <node id="1234" changeset="42" version="2" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true"/>
+</osm>
+"#;
+
+const UPDATED_NODE_RESPONSE: &str = r#"
+<osm>
+    <node id="1234" changeset="43" version="3" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-10T08:19:00Z" uid="1" user="user" visible="true"/>
+</osm>
+"#;
+
+// A host without server, so a replayed client fails if it sends a request
+const OFFLINE_HOST: &str = "http://127.0.0.1:9";
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("osm-cassette-test-{}", std::process::id()))
+        .join(format!("{name}.json"))
+}
+
+#[actix_rt::test]
+async fn test_record_and_replay() {
+    /*
+    GIVEN a cassette recorded while fetching a node, updating it and fetching it again
+    WHEN replaying the same calls on a client without server
+    THEN the recorded responses are returned in order
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(UPDATED_NODE_RESPONSE, "application/xml"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("PUT"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("3", "text/plain"))
+        .mount(&mock_server)
+        .await;
+
+    let cassette_path = cassette_path("record_and_replay");
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let cassette = Cassette::record(&cassette_path, ReqwestTransport::default());
+    let client = Openstreetmap::from_transport(mock_server.uri(), credentials.clone(), cassette);
+
+    let node = client.nodes().get(1234).await.unwrap();
+    let version = client.nodes().update(node).await.unwrap();
+    let updated_node = client.nodes().get(1234).await.unwrap();
+
+    // the cassette is written when the client is dropped
+    assert!(!cassette_path.exists());
+    drop(client);
+
+    // WHEN
+    let cassette = Cassette::replay(&cassette_path).unwrap();
+    let client = Openstreetmap::from_transport(OFFLINE_HOST, credentials, cassette);
+
+    let replayed_node = client.nodes().get(1234).await.unwrap();
+    let replayed_node_version = replayed_node.version;
+    let replayed_version = client.nodes().update(replayed_node).await.unwrap();
+    let replayed_updated_node = client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    assert_eq!(replayed_node_version, 2);
+    assert_eq!(replayed_version, version);
+    assert_eq!(replayed_updated_node, updated_node);
+    assert_eq!(updated_node.version, 3);
+
+    let fixture = std::fs::read_to_string(&cassette_path).unwrap();
+
+    assert!(!fixture.contains("dXNlcjpwYXNz"));
+
+    std::fs::remove_file(cassette_path).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_replay_unmatched() {
+    /*
+    GIVEN a cassette recorded while fetching a node
+    WHEN replaying a request for another node, or the same request twice
+    THEN returns a transport error
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let cassette_path = cassette_path("replay_unmatched");
+    let cassette = Cassette::record(&cassette_path, ReqwestTransport::default());
+    let client =
+        Openstreetmap::from_transport(mock_server.uri(), types::Credentials::None, cassette);
+
+    client.nodes().get(1234).await.unwrap();
+    drop(client);
+
+    let cassette = Cassette::replay(&cassette_path).unwrap();
+    let client = Openstreetmap::from_transport(OFFLINE_HOST, types::Credentials::None, cassette);
+
+    // WHEN
+    let other = client.nodes().get(1).await;
+    let first = client.nodes().get(1234).await;
+    let second = client.nodes().get(1234).await;
+
+    // THEN
+    assert!(matches!(other, Err(OpenstreetmapError::Transport(_))));
+    assert!(first.is_ok());
+    assert!(matches!(second, Err(OpenstreetmapError::Transport(_))));
+
+    std::fs::remove_file(cassette_path).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_replay_error_response() {
+    /*
+    GIVEN a cassette recorded while requesting a missing note
+    WHEN replaying the request
+    THEN returns the same error as the server
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/notes/1"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let cassette_path = cassette_path("replay_error_response");
+    let cassette = Cassette::record(&cassette_path, ReqwestTransport::default());
+    let client =
+        Openstreetmap::from_transport(mock_server.uri(), types::Credentials::None, cassette);
+
+    client.notes().get(1).await.unwrap_err();
+    drop(client);
+
+    let cassette = Cassette::replay(&cassette_path).unwrap();
+    let client = Openstreetmap::from_transport(OFFLINE_HOST, types::Credentials::None, cassette);
+
+    // WHEN
+    let actual = client.notes().get(1).await;
+
+    // THEN
+    assert!(matches!(actual, Err(OpenstreetmapError::NotFound)));
+
+    std::fs::remove_file(cassette_path).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_record_without_secret_headers() {
+    /*
+    GIVEN a server setting a session cookie and asking for authentication
+    WHEN recording a request to it
+    THEN the cookie and authentication headers are not written to the cassette
+        AND the other headers are
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(NODE_RESPONSE, "application/xml")
+                .insert_header("set-cookie", "_osm_session=secretsession; path=/")
+                .insert_header("www-authenticate", "Bearer realm=\"secretrealm\"")
+                .insert_header("etag", "\"abc\""),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let cassette_path = cassette_path("record_without_secret_headers");
+    let cassette = Cassette::record(&cassette_path, ReqwestTransport::default());
+    let client =
+        Openstreetmap::from_transport(mock_server.uri(), types::Credentials::None, cassette);
+
+    // WHEN
+    client.nodes().get(1234).await.unwrap();
+    drop(client);
+
+    // THEN
+    let fixture = std::fs::read_to_string(&cassette_path).unwrap();
+
+    assert!(!fixture.contains("secretsession"));
+    assert!(!fixture.contains("secretrealm"));
+    assert!(fixture.contains("etag"));
+
+    std::fs::remove_file(cassette_path).unwrap();
+}
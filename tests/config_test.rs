@@ -0,0 +1,283 @@
+#![cfg(feature = "config")]
+
+use openstreetmap_api::config::{Config, TokenStore};
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CONFIG_TOML: &str = r#"
+default_profile = "dev"
+
+[profiles.dev]
+user = "mapper"
+password = "secret"
+
+[profiles.staging]
+host = "https://staging.example.org"
+"#;
+
+const ENV_VARS: [&str; 6] = [
+    "OPENSTREETMAP_CONFIG",
+    "OPENSTREETMAP_PROFILE",
+    "OPENSTREETMAP_HOST",
+    "OPENSTREETMAP_USER",
+    "OPENSTREETMAP_PASSWORD",
+    "OPENSTREETMAP_TOKEN",
+];
+
+/// the tests change the process environment, so they run one at a time
+static ENV: Mutex<()> = Mutex::new(());
+
+fn clean_env() -> MutexGuard<'static, ()> {
+    let guard = ENV.lock().unwrap_or_else(|error| error.into_inner());
+
+    for name in ENV_VARS {
+        env::remove_var(name);
+    }
+
+    guard
+}
+
+fn config_file(name: &str) -> PathBuf {
+    let directory = env::temp_dir()
+        .join(format!("openstreetmap-config-{}", std::process::id()))
+        .join(name);
+
+    fs::create_dir_all(&directory).unwrap();
+
+    let path = directory.join("config.toml");
+
+    fs::write(&path, CONFIG_TOML).unwrap();
+    path
+}
+
+fn basic(credentials: &types::Credentials) -> Option<(&str, &str)> {
+    match credentials {
+        types::Credentials::Basic(user, password) => Some((user, password)),
+        _ => None,
+    }
+}
+
+fn bearer(credentials: &types::Credentials) -> Option<&str> {
+    match credentials {
+        types::Credentials::Bearer(token) => Some(token),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_profile_from_file() {
+    /*
+    GIVEN a configuration file with a default profile
+    WHEN resolving the default, a named and a built-in profile
+    THEN the hosts and the credentials of the file are used, with the built-in hosts as fallback
+    */
+    // GIVEN
+    let _env = clean_env();
+    let config = Config::from_file(config_file("file")).unwrap();
+
+    // WHEN
+    let default = config.profile(None).unwrap();
+    let staging = config.profile(Some("staging")).unwrap();
+    let local = config.profile(Some("local")).unwrap();
+
+    // THEN
+    assert_eq!(default.name, "dev");
+    assert_eq!(default.host, "https://master.apis.dev.openstreetmap.org");
+    assert_eq!(basic(&default.credentials), Some(("mapper", "secret")));
+    assert_eq!(staging.host, "https://staging.example.org");
+    assert!(matches!(staging.credentials, types::Credentials::None));
+    assert_eq!(local.host, "http://localhost:3000");
+}
+
+#[test]
+fn test_unknown_profile() {
+    /*
+    GIVEN a configuration file
+    WHEN resolving a profile missing from the file and not built-in
+    THEN a configuration error is returned
+    */
+    // GIVEN
+    let _env = clean_env();
+    let config = Config::from_file(config_file("unknown")).unwrap();
+
+    // WHEN
+    let result = config.profile(Some("test"));
+
+    // THEN
+    assert!(matches!(
+        result,
+        Err(OpenstreetmapError::Config(message)) if message == "unknown profile test"
+    ));
+}
+
+#[test]
+fn test_env_overrides() {
+    /*
+    GIVEN a configuration file
+        AND the profile, host and credentials in the environment
+    WHEN resolving the default profile and requesting the same profile
+    THEN the environment takes precedence over the file
+        AND the requested profile keeps the host of the file
+        AND a token takes precedence over a user and password
+    */
+    // GIVEN
+    let _env = clean_env();
+    let config = Config::from_file(config_file("env")).unwrap();
+
+    env::set_var("OPENSTREETMAP_PROFILE", "staging");
+    env::set_var("OPENSTREETMAP_HOST", "http://127.0.0.1:3000");
+    env::set_var("OPENSTREETMAP_USER", "bot");
+    env::set_var("OPENSTREETMAP_PASSWORD", "hunter2");
+
+    // WHEN
+    let basic_profile = config.profile(None).unwrap();
+    let requested_profile = config.profile(Some("staging")).unwrap();
+
+    env::set_var("OPENSTREETMAP_TOKEN", "env-token");
+
+    let token_profile = config.profile(None).unwrap();
+
+    // THEN
+    assert_eq!(basic_profile.name, "staging");
+    assert_eq!(basic_profile.host, "http://127.0.0.1:3000");
+    assert_eq!(requested_profile.host, "https://staging.example.org");
+    assert_eq!(basic(&basic_profile.credentials), Some(("bot", "hunter2")));
+    assert_eq!(bearer(&token_profile.credentials), Some("env-token"));
+}
+
+#[test]
+fn test_requested_profile_credentials() {
+    /*
+    GIVEN a configuration file
+        AND a host, a token and a user and password in the environment
+    WHEN requesting profiles with a host
+    THEN the credentials of the environment are not used for their host
+    */
+    // GIVEN
+    let _env = clean_env();
+    let config = Config::from_file(config_file("requested")).unwrap();
+
+    env::set_var("OPENSTREETMAP_HOST", "http://127.0.0.1:3000");
+    env::set_var("OPENSTREETMAP_USER", "bot");
+    env::set_var("OPENSTREETMAP_PASSWORD", "hunter2");
+    env::set_var("OPENSTREETMAP_TOKEN", "env-token");
+
+    // WHEN
+    let staging = config.profile(Some("staging")).unwrap();
+    let dev = config.profile(Some("dev")).unwrap();
+
+    // THEN
+    assert_eq!(staging.host, "https://staging.example.org");
+    assert!(matches!(staging.credentials, types::Credentials::None));
+    assert_eq!(dev.host, "https://master.apis.dev.openstreetmap.org");
+    assert_eq!(basic(&dev.credentials), Some(("mapper", "secret")));
+}
+
+#[test]
+fn test_load_missing_file() {
+    /*
+    GIVEN `OPENSTREETMAP_CONFIG` pointing to a missing file
+    WHEN loading the configuration
+    THEN the configuration is empty and the dev profile is used
+    */
+    // GIVEN
+    let _env = clean_env();
+    let path = config_file("missing").with_file_name("missing.toml");
+
+    env::set_var("OPENSTREETMAP_CONFIG", &path);
+
+    // WHEN
+    let config = Config::load().unwrap();
+
+    // THEN
+    let profile = config.profile(None).unwrap();
+
+    assert_eq!(config.default_profile, None);
+    assert!(config.profiles.is_empty());
+    assert_eq!(profile.name, "dev");
+    assert_eq!(profile.host, "https://master.apis.dev.openstreetmap.org");
+}
+
+#[test]
+fn test_token_store() {
+    /*
+    GIVEN an empty token file
+    WHEN saving, replacing and removing tokens
+    THEN the tokens are read back
+        AND the file is only readable by its owner
+    */
+    // GIVEN
+    let path = config_file("tokens").with_file_name("tokens.toml");
+    let tokens = TokenStore::new(&path);
+
+    let _ = fs::remove_file(&path);
+
+    // WHEN
+    tokens.save("dev", "first").unwrap();
+    tokens.save("local", "other").unwrap();
+    tokens.save("dev", "second").unwrap();
+
+    let removed = tokens.remove("local").unwrap();
+
+    // THEN
+    assert_eq!(tokens.get("dev").unwrap(), Some("second".into()));
+    assert_eq!(tokens.get("local").unwrap(), None);
+    assert!(removed);
+    assert!(!tokens.remove("local").unwrap());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn test_stored_token() {
+    /*
+    GIVEN a configuration file
+        AND a stored token for the default profile
+    WHEN calling an authenticated endpoint with the profile
+    THEN the token is sent as a Bearer authorization over the user and password of the file
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+    let profile = {
+        let _env = clean_env();
+        let config = Config::from_file(config_file("stored")).unwrap();
+
+        env::set_var("OPENSTREETMAP_HOST", mock_server.uri());
+        config.tokens().save("dev", "stored-token").unwrap();
+        config.profile(None).unwrap()
+    };
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/user/preferences"))
+        .and(header("Authorization", "Bearer stored-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"<osm version="0.6"><preferences><preference k="key" v="value"/></preferences></osm>"#,
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(profile.host, profile.credentials);
+
+    // WHEN
+    let preferences = client.user().preferences().await.unwrap();
+
+    // THEN
+    assert_eq!(preferences.get("key").map(String::as_str), Some("value"));
+}
@@ -0,0 +1,192 @@
+use openstreetmap_api::dataset::Dataset;
+use openstreetmap_api::types::*;
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+fn node(id: i64, version: u64, tags: Vec<Tag>) -> Node {
+    Node {
+        id,
+        visible: true,
+        version,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: Some("user".into()),
+        uid: Some(1),
+        lat: Some(1.0),
+        lon: Some(2.0),
+        tags,
+    }
+}
+
+fn way(id: i64, version: u64, node_ids: &[i64], tags: Vec<Tag>) -> Way {
+    Way {
+        id,
+        visible: true,
+        version,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        node_refs: node_ids
+            .iter()
+            .map(|node_id| NodeRef { node_id: *node_id })
+            .collect(),
+        tags,
+    }
+}
+
+fn relation(id: i64, version: u64, members: &[(&str, i64)]) -> Relation {
+    Relation {
+        id,
+        visible: true,
+        version,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        tags: vec![Tag::new("type", "multipolygon")],
+        members: members
+            .iter()
+            .map(|(member_type, id)| Member {
+                member_type: member_type.to_string(),
+                node_id: *id,
+                role: "outer".into(),
+            })
+            .collect(),
+    }
+}
+
+#[fixture]
+fn dataset() -> Dataset {
+    Dataset::from(Map {
+        bounds: BoundingBox {
+            left: 0.0,
+            bottom: 0.0,
+            right: 1.0,
+            top: 1.0,
+        },
+        nodes: vec![
+            node(1, 1, vec![Tag::new("amenity", "school")]),
+            node(2, 1, vec![Tag::new("amenity", "cafe")]),
+            node(3, 1, vec![]),
+        ],
+        ways: vec![
+            way(10, 1, &[1, 2], vec![Tag::new("highway", "residential")]),
+            way(11, 1, &[2, 3], vec![]),
+        ],
+        relations: vec![relation(20, 1, &[("way", 10), ("node", 3)])],
+    })
+}
+
+#[rstest]
+fn test_reverse_lookups(dataset: Dataset) {
+    /*
+    GIVEN a dataset built from a map
+    WHEN looking up the parents of an element
+    THEN the ways and relations referencing it are returned
+    */
+    // WHEN
+    let ways = dataset
+        .ways_for_node(2)
+        .iter()
+        .map(|w| w.id)
+        .collect::<Vec<i64>>();
+    let relations = dataset
+        .relations_for_member(ElementType::Way, 10)
+        .iter()
+        .map(|r| r.id)
+        .collect::<Vec<i64>>();
+
+    // THEN
+    assert_eq!(ways, vec![10, 11]);
+    assert_eq!(relations, vec![20]);
+    assert!(dataset
+        .relations_for_member(ElementType::Node, 10)
+        .is_empty());
+}
+
+#[rstest(key, value, expected,
+    case("amenity", None, vec![1, 2]),
+    case("amenity", Some("cafe"), vec![2]),
+    case("shop", None, vec![])
+)]
+fn test_nodes_with_tag(dataset: Dataset, key: &str, value: Option<&str>, expected: Vec<i64>) {
+    /*
+    GIVEN a dataset
+    WHEN querying the nodes by tag
+    THEN the matching nodes are returned
+    */
+    // WHEN
+    let actual = dataset
+        .nodes_with_tag(key, value)
+        .map(|n| n.id)
+        .collect::<Vec<i64>>();
+
+    // THEN
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn test_merge_way_full_replaces_older_versions(mut dataset: Dataset) {
+    /*
+    GIVEN a dataset
+    WHEN merging a newer version of a way and an older version of one of its nodes
+    THEN the newer way replaces the stored one
+        AND its node references are re-indexed
+        AND the older node is ignored
+    */
+    // WHEN
+    dataset.merge_way_full(WayFull {
+        way: way(10, 2, &[1, 3], vec![]),
+        nodes: vec![node(1, 0, vec![])],
+    });
+
+    // THEN
+    assert_eq!(dataset.way(10).unwrap().version, 2);
+    assert_eq!(dataset.node(1).unwrap().version, 1);
+    assert_eq!(
+        dataset
+            .ways_for_node(2)
+            .iter()
+            .map(|w| w.id)
+            .collect::<Vec<i64>>(),
+        vec![11]
+    );
+    assert_eq!(
+        dataset
+            .ways_for_node(3)
+            .iter()
+            .map(|w| w.id)
+            .collect::<Vec<i64>>(),
+        vec![10, 11]
+    );
+}
+
+#[rstest]
+fn test_merge_relation_full(mut dataset: Dataset) {
+    /*
+    GIVEN a dataset
+    WHEN merging a newer version of a relation with its members
+    THEN the members index follows the new version
+    */
+    // WHEN
+    dataset.merge_relation_full(RelationFull {
+        relation: relation(20, 2, &[("way", 12)]),
+        ways: vec![way(12, 1, &[4, 1], vec![])],
+        nodes: vec![node(4, 1, vec![])],
+    });
+
+    // THEN
+    assert!(dataset
+        .relations_for_member(ElementType::Way, 10)
+        .is_empty());
+    assert_eq!(
+        dataset
+            .relations_for_member(ElementType::Way, 12)
+            .iter()
+            .map(|r| r.version)
+            .collect::<Vec<u64>>(),
+        vec![2]
+    );
+    assert!(dataset.node(4).is_some());
+}
@@ -0,0 +1,264 @@
+use openstreetmap_api::diff::*;
+use openstreetmap_api::types::*;
+use pretty_assertions::assert_eq;
+
+fn node(version: u64, changeset: u64, user: &str, tags: &[(&str, &str)]) -> Node {
+    Node {
+        id: 1,
+        visible: true,
+        version,
+        changeset,
+        timestamp: format!("2020-12-0{version}T08:00:00Z"),
+        user: Some(user.into()),
+        uid: Some(1),
+        lat: Some(1.0),
+        lon: Some(2.0),
+        tags: tags.iter().map(|(k, v)| Tag::new(k, v)).collect(),
+    }
+}
+
+fn way(version: u64, node_ids: &[i64]) -> Way {
+    Way {
+        id: 1,
+        visible: true,
+        version,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        node_refs: node_ids
+            .iter()
+            .map(|node_id| NodeRef { node_id: *node_id })
+            .collect(),
+        tags: vec![],
+    }
+}
+
+fn member(id: i64, role: &str) -> Member {
+    Member {
+        member_type: "way".into(),
+        node_id: id,
+        role: role.into(),
+    }
+}
+
+#[test]
+fn test_diff_node() {
+    /*
+    GIVEN two versions of a node with changed tags and position
+    WHEN comparing them
+    THEN the added, removed and modified tags are returned sorted by key
+        AND the move is returned
+    */
+    // GIVEN
+    let old = node(1, 10, "alice", &[("name", "Old"), ("amenity", "cafe")]);
+    let new = Node {
+        lat: Some(1.5),
+        ..node(2, 11, "bob", &[("name", "New"), ("cuisine", "coffee")])
+    };
+
+    // WHEN
+    let actual = diff(&old, &new);
+
+    // THEN
+    let expected = ElementDiff {
+        from_version: 1,
+        to_version: 2,
+        visible: None,
+        tags: vec![
+            TagChange::Removed {
+                key: "amenity".into(),
+                value: "cafe".into(),
+            },
+            TagChange::Added {
+                key: "cuisine".into(),
+                value: "coffee".into(),
+            },
+            TagChange::Modified {
+                key: "name".into(),
+                old_value: "Old".into(),
+                new_value: "New".into(),
+            },
+        ],
+        moved: Some(Move {
+            from: (2.0, 1.0),
+            to: (2.0, 1.5),
+        }),
+        node_refs: None,
+        members: None,
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_diff_deleted_node() {
+    /*
+    GIVEN a node and its deleted version
+    WHEN comparing them
+    THEN the visibility change and the removed tags are returned
+    */
+    // GIVEN
+    let old = node(1, 10, "alice", &[("name", "Old")]);
+    let new = Node {
+        visible: false,
+        lat: None,
+        lon: None,
+        ..node(2, 11, "bob", &[])
+    };
+
+    // WHEN
+    let actual = diff(&old, &new);
+
+    // THEN
+    assert_eq!(actual.visible, Some(false));
+    assert_eq!(
+        actual.tags,
+        vec![TagChange::Removed {
+            key: "name".into(),
+            value: "Old".into()
+        }]
+    );
+    assert_eq!(actual.moved, None);
+}
+
+#[test]
+fn test_diff_way() {
+    /*
+    GIVEN two versions of a closed way with a node replaced
+    WHEN comparing them
+    THEN the added and removed nodes are returned
+        AND the closing node is not reported as removed
+    */
+    // GIVEN
+    let old = way(1, &[1, 2, 3, 1]);
+    let new = way(2, &[1, 4, 3, 1]);
+
+    // WHEN
+    let actual = diff(&old, &new);
+
+    // THEN
+    let expected = ListChange {
+        added: vec![4],
+        removed: vec![2],
+        reordered: false,
+    };
+
+    assert_eq!(actual.node_refs, Some(expected));
+    assert!(actual.tags.is_empty());
+}
+
+#[test]
+fn test_diff_relation() {
+    /*
+    GIVEN two versions of a relation with reordered members and a changed role
+    WHEN comparing them
+    THEN the member with the new role is reported as added and removed
+        AND the members are reported as reordered
+    */
+    // GIVEN
+    let relation = |version, members| Relation {
+        id: 1,
+        visible: true,
+        version,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        tags: vec![],
+        members,
+    };
+    let old = relation(
+        1,
+        vec![member(1, "outer"), member(2, "inner"), member(3, "")],
+    );
+    let new = relation(
+        2,
+        vec![member(2, "inner"), member(1, "outer"), member(3, "outer")],
+    );
+
+    // WHEN
+    let actual = diff(&old, &new);
+
+    // THEN
+    let expected = ListChange {
+        added: vec![member(3, "outer")],
+        removed: vec![member(3, "")],
+        reordered: true,
+    };
+
+    assert_eq!(actual.members, Some(expected));
+}
+
+#[test]
+fn test_diff_unchanged() {
+    /*
+    GIVEN two identical versions but for the metadata
+    WHEN comparing them
+    THEN the diff is empty
+    */
+    // WHEN
+    let actual = diff(
+        &node(1, 10, "alice", &[("name", "Same")]),
+        &node(2, 11, "bob", &[("name", "Same")]),
+    );
+
+    // THEN
+    assert!(actual.is_empty());
+}
+
+#[test]
+fn test_blame() {
+    /*
+    GIVEN the unsorted history of a node whose tags were set by several users
+    WHEN blaming its tags
+    THEN every current tag is attributed to the version which last set its value
+        AND a tag removed and added again is attributed to the version re-adding it
+    */
+    // GIVEN
+    let history = vec![
+        node(3, 12, "carol", &[("name", "Final"), ("amenity", "cafe")]),
+        node(1, 10, "alice", &[("name", "First"), ("amenity", "cafe")]),
+        node(
+            4,
+            13,
+            "dave",
+            &[("name", "Final"), ("amenity", "cafe"), ("wifi", "yes")],
+        ),
+        node(2, 11, "bob", &[("name", "First")]),
+    ];
+
+    // WHEN
+    let actual = blame(&history);
+
+    // THEN
+    let tag_blame = |key: &str, value: &str, version, changeset, user: &str| TagBlame {
+        key: key.into(),
+        value: value.into(),
+        version,
+        changeset,
+        timestamp: format!("2020-12-0{version}T08:00:00Z"),
+        user: Some(user.into()),
+    };
+    let expected = vec![
+        tag_blame("name", "Final", 3, 12, "carol"),
+        tag_blame("amenity", "cafe", 3, 12, "carol"),
+        tag_blame("wifi", "yes", 4, 13, "dave"),
+    ];
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_blame_empty_history() {
+    /*
+    GIVEN an empty history
+    WHEN blaming its tags
+    THEN nothing is returned
+    */
+    // WHEN
+    let actual = blame::<Node>(&[]);
+
+    // THEN
+    assert_eq!(actual, vec![]);
+}
@@ -0,0 +1,214 @@
+#![cfg(feature = "geojson")]
+
+use openstreetmap_api::errors::GeometryError;
+use openstreetmap_api::geojson::*;
+use openstreetmap_api::types::*;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+fn node(id: i64, lon: f64, lat: f64, tags: Vec<Tag>) -> Node {
+    Node {
+        id,
+        visible: true,
+        version: 1,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: Some("user".into()),
+        uid: Some(1),
+        lat: Some(lat),
+        lon: Some(lon),
+        tags,
+    }
+}
+
+#[test]
+fn test_map_to_feature_collection() {
+    /*
+    GIVEN a map with a tagged node, a way and a route relation
+    WHEN converting it into GeoJSON
+    THEN the tagged node and the way are exported as features
+        AND the untagged way nodes are skipped
+        AND the relation is returned as an error
+    */
+    // GIVEN
+    let map = Map {
+        bounds: BoundingBox {
+            left: 0.0,
+            bottom: 0.0,
+            right: 1.0,
+            top: 1.0,
+        },
+        nodes: vec![
+            node(1, 0.0, 0.0, vec![]),
+            node(2, 1.0, 1.0, vec![Tag::new("amenity", "cafe")]),
+        ],
+        ways: vec![Way {
+            id: 10,
+            visible: true,
+            version: 1,
+            changeset: 1,
+            timestamp: "2009-12-09T08:19:00Z".into(),
+            user: "user".into(),
+            uid: 1,
+            node_refs: vec![NodeRef { node_id: 1 }, NodeRef { node_id: 2 }],
+            tags: vec![Tag::new("highway", "residential")],
+        }],
+        relations: vec![Relation {
+            id: 20,
+            visible: true,
+            version: 1,
+            changeset: 1,
+            timestamp: "2009-12-09T08:19:00Z".into(),
+            user: "user".into(),
+            uid: 1,
+            tags: vec![Tag::new("type", "route")],
+            members: vec![],
+        }],
+    };
+
+    // WHEN
+    let (collection, errors) = map_to_feature_collection(&map);
+    let actual = serde_json::to_value(collection).unwrap();
+
+    // THEN
+    let expected = json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "id": "node/2",
+                "geometry": {"type": "Point", "coordinates": [1.0, 1.0]},
+                "properties": {"amenity": "cafe"}
+            },
+            {
+                "type": "Feature",
+                "id": "way/10",
+                "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 1.0]]},
+                "properties": {"highway": "residential"}
+            }
+        ]
+    });
+
+    assert_eq!(actual, expected);
+    assert_eq!(errors, vec![GeometryError::UnsupportedRelation(20)]);
+}
+
+#[test]
+fn test_notes_to_feature_collection() {
+    /*
+    GIVEN a list of notes
+    WHEN converting them into GeoJSON
+    THEN every note is a point feature
+    */
+    // GIVEN
+    let notes = vec![Note {
+        id: 16659,
+        lat: 51.0,
+        lon: 0.1,
+        url: "https://api.openstreetmap.org/api/0.6/notes/16659".into(),
+        created_at: "2019-06-15 08:26:04 UTC".into(),
+        status: NoteStatus::Open,
+        comments: vec![Comment {
+            date: "2019-06-15 08:26:04 UTC".into(),
+            user: Some("userName".into()),
+            action: CommentAction::Opened,
+            text: "ThisIsANote".into(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }];
+
+    // WHEN
+    let actual = serde_json::to_value(notes_to_feature_collection(&notes)).unwrap();
+
+    // THEN
+    let expected = json!({
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "id": 16659,
+            "geometry": {"type": "Point", "coordinates": [0.1, 51.0]},
+            "properties": {
+                "status": "open",
+                "url": "https://api.openstreetmap.org/api/0.6/notes/16659",
+                "created_at": "2019-06-15 08:26:04 UTC",
+                "comments": [{
+                    "date": "2019-06-15 08:26:04 UTC",
+                    "user": "userName",
+                    "action": "opened",
+                    "text": "ThisIsANote"
+                }]
+            }
+        }]
+    });
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_changesets_to_feature_collection() {
+    /*
+    GIVEN a changeset with a bounding box and an empty one
+    WHEN converting them into GeoJSON
+    THEN the bounding box is a polygon
+        AND the empty changeset has no geometry
+    */
+    // GIVEN
+    let changeset = |id, bbox: Option<BoundingBox>| Changeset {
+        id,
+        user: "user".into(),
+        uid: 1,
+        created_at: "2020-12-09T22:51:17Z".into(),
+        closed_at: None,
+        open: true,
+        comments_count: 2,
+        changes_count: 5,
+        bbox,
+        discussion: None,
+        tags: vec![Tag::new("comment", "fix")],
+    };
+    let bbox = BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+    let changesets = vec![changeset(1, Some(bbox)), changeset(2, None)];
+
+    // WHEN
+    let actual = serde_json::to_value(changesets_to_feature_collection(&changesets)).unwrap();
+
+    // THEN
+    let properties = json!({
+        "user": "user",
+        "uid": 1,
+        "created_at": "2020-12-09T22:51:17Z",
+        "closed_at": null,
+        "open": true,
+        "comments_count": 2,
+        "changes_count": 5,
+        "tags": {"comment": "fix"}
+    });
+    let expected = json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "id": 1,
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[1.0, 2.0], [3.0, 2.0], [3.0, 4.0], [1.0, 4.0], [1.0, 2.0]]]
+                },
+                "properties": properties
+            },
+            {
+                "type": "Feature",
+                "id": 2,
+                "geometry": null,
+                "properties": properties
+            }
+        ]
+    });
+
+    assert_eq!(actual, expected);
+}
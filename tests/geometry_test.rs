@@ -0,0 +1,233 @@
+#![cfg(feature = "geo-types")]
+
+use geo_types::{line_string, polygon, Geometry, MultiPolygon};
+use openstreetmap_api::errors::GeometryError;
+use openstreetmap_api::geometry::{is_area, GeometryBuilder};
+use openstreetmap_api::types::*;
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+fn node(id: i64, lon: f64, lat: f64) -> Node {
+    Node {
+        id,
+        visible: true,
+        version: 1,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: Some("user".into()),
+        uid: Some(1),
+        lat: Some(lat),
+        lon: Some(lon),
+        tags: vec![],
+    }
+}
+
+fn way(id: i64, node_ids: &[i64], tags: Vec<Tag>) -> Way {
+    Way {
+        id,
+        visible: true,
+        version: 1,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        node_refs: node_ids
+            .iter()
+            .map(|node_id| NodeRef { node_id: *node_id })
+            .collect(),
+        tags,
+    }
+}
+
+fn multipolygon(members: &[(i64, &str)]) -> Relation {
+    Relation {
+        id: 100,
+        visible: true,
+        version: 1,
+        changeset: 1,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: "user".into(),
+        uid: 1,
+        tags: vec![Tag::new("type", "multipolygon")],
+        members: members
+            .iter()
+            .map(|(way_id, role)| Member {
+                member_type: "way".into(),
+                node_id: *way_id,
+                role: role.to_string(),
+            })
+            .collect(),
+    }
+}
+
+#[fixture]
+fn nodes() -> Vec<Node> {
+    vec![
+        // outer square
+        node(1, 0.0, 0.0),
+        node(2, 4.0, 0.0),
+        node(3, 4.0, 4.0),
+        node(4, 0.0, 4.0),
+        // inner square
+        node(5, 1.0, 1.0),
+        node(6, 2.0, 1.0),
+        node(7, 2.0, 2.0),
+        node(8, 1.0, 2.0),
+    ]
+}
+
+#[rstest(tags, node_ids, expected,
+    case(vec![Tag::new("building", "yes")], vec![1, 2, 3, 1], true),
+    case(vec![Tag::new("building", "yes")], vec![1, 2, 3], false),
+    case(vec![Tag::new("highway", "residential")], vec![1, 2, 3, 1], false),
+    case(vec![Tag::new("highway", "pedestrian"), Tag::new("area", "yes")], vec![1, 2, 3, 1], true),
+    case(vec![Tag::new("natural", "coastline")], vec![1, 2, 3, 1], false),
+    case(vec![Tag::new("waterway", "riverbank")], vec![1, 2, 3, 1], true),
+    case(vec![Tag::new("building", "yes"), Tag::new("area", "no")], vec![1, 2, 3, 1], false)
+)]
+fn test_is_area(tags: Vec<Tag>, node_ids: Vec<i64>, expected: bool) {
+    /*
+    GIVEN a way
+    WHEN checking if it is an area
+    THEN closed ways with area tags are areas
+    */
+    // WHEN
+    let actual = is_area(&way(1, &node_ids, tags));
+
+    // THEN
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn test_way_full_geometry(nodes: Vec<Node>) {
+    /*
+    GIVEN a WayFull for a closed way with area tags
+    WHEN building its geometry
+    THEN a polygon is returned
+    */
+    // GIVEN
+    let way_full = WayFull {
+        way: way(10, &[1, 2, 3, 1], vec![Tag::new("landuse", "grass")]),
+        nodes,
+    };
+
+    // WHEN
+    let actual = GeometryBuilder::from(&way_full).way(&way_full.way).unwrap();
+
+    // THEN
+    let expected = Geometry::Polygon(polygon![
+        (x: 0.0, y: 0.0),
+        (x: 4.0, y: 0.0),
+        (x: 4.0, y: 4.0),
+        (x: 0.0, y: 0.0),
+    ]);
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn test_way_geometry_missing_node(nodes: Vec<Node>) {
+    /*
+    GIVEN a way referencing an unknown node
+    WHEN building its geometry
+    THEN the missing node is reported
+    */
+    // GIVEN
+    let way_full = WayFull {
+        way: way(10, &[1, 2, 99], vec![]),
+        nodes,
+    };
+
+    // WHEN
+    let actual = GeometryBuilder::from(&way_full).way(&way_full.way);
+
+    // THEN
+    assert_eq!(actual, Err(GeometryError::MissingNode(99)));
+}
+
+#[rstest]
+fn test_relation_geometry(nodes: Vec<Node>) {
+    /*
+    GIVEN a multipolygon relation whose outer ring is split over two reversed ways
+    WHEN building its geometry
+    THEN the ways are joined into a polygon
+        AND the inner way is a hole of that polygon
+    */
+    // GIVEN
+    let relation_full = RelationFull {
+        relation: multipolygon(&[(10, "outer"), (11, "outer"), (12, "inner")]),
+        ways: vec![
+            way(10, &[1, 2, 3], vec![]),
+            way(11, &[1, 4, 3], vec![]),
+            way(12, &[5, 6, 7, 8, 5], vec![]),
+        ],
+        nodes,
+    };
+
+    // WHEN
+    let actual = GeometryBuilder::from(&relation_full)
+        .relation(&relation_full.relation)
+        .unwrap();
+
+    // THEN
+    let expected = MultiPolygon::new(vec![geo_types::Polygon::new(
+        line_string![
+            (x: 0.0, y: 0.0),
+            (x: 0.0, y: 4.0),
+            (x: 4.0, y: 4.0),
+            (x: 4.0, y: 0.0),
+            (x: 0.0, y: 0.0),
+        ],
+        vec![line_string![
+            (x: 1.0, y: 1.0),
+            (x: 2.0, y: 1.0),
+            (x: 2.0, y: 2.0),
+            (x: 1.0, y: 2.0),
+            (x: 1.0, y: 1.0),
+        ]],
+    )]);
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest(members, ways, expected,
+    case(
+        vec![(10, "outer")],
+        vec![way(10, &[1, 2, 3], vec![])],
+        GeometryError::UnclosedRing { relation_id: 100 }
+    ),
+    case(
+        vec![(10, "outer"), (11, "outer")],
+        vec![way(10, &[1, 2, 3, 4, 1], vec![])],
+        GeometryError::MissingWay(11)
+    ),
+    case(
+        vec![(10, "outer"), (11, "inner")],
+        vec![way(10, &[5, 6, 7, 8, 5], vec![]), way(11, &[1, 2, 3, 4, 1], vec![])],
+        GeometryError::InnerRingOutside { relation_id: 100 }
+    )
+)]
+fn test_relation_geometry_broken(
+    nodes: Vec<Node>,
+    members: Vec<(i64, &str)>,
+    ways: Vec<Way>,
+    expected: GeometryError,
+) {
+    /*
+    GIVEN a broken multipolygon relation
+    WHEN building its geometry
+    THEN the error is reported
+    */
+    // GIVEN
+    let relation_full = RelationFull {
+        relation: multipolygon(&members),
+        ways,
+        nodes,
+    };
+
+    // WHEN
+    let actual = GeometryBuilder::from(&relation_full).relation(&relation_full.relation);
+
+    // THEN
+    assert_eq!(actual, Err(expected));
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "reqwest")]
+
+use futures::future::BoxFuture;
+use http::header::{HeaderMap, HeaderValue};
+use http::StatusCode;
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::middleware::{Middleware, Next, Request};
+use openstreetmap_api::transport::HttpResponse;
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const VERSIONS_RESPONSE: &str = r#"
+<osm>
+    <api>
+        <version>0.6</version>
+    </api>
+</osm>
+"#;
+
+#[derive(Debug)]
+struct UserAgent(&'static str);
+
+impl Middleware for UserAgent {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>> {
+        request
+            .http
+            .headers
+            .insert("user-agent", HeaderValue::from_static(self.0));
+
+        next.run(request)
+    }
+}
+
+/// Records the endpoint, the options and the response status of every request
+#[derive(Debug, Clone, Default)]
+struct Recorder {
+    calls: Arc<Mutex<Vec<(String, bool, StatusCode)>>>,
+}
+
+impl Middleware for Recorder {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>> {
+        Box::pin(async move {
+            let endpoint = request.endpoint.clone();
+            let use_auth = request.options.use_auth;
+            let response = next.run(request).await?;
+
+            self.calls
+                .lock()
+                .unwrap()
+                .push((endpoint, use_auth, response.status));
+
+            Ok(response)
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Canned(&'static str);
+
+impl Middleware for Canned {
+    fn handle<'a>(
+        &'a self,
+        _request: Request,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, Result<HttpResponse, OpenstreetmapError>> {
+        let response = HttpResponse::new(StatusCode::OK, HeaderMap::new(), self.0.into());
+
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[actix_rt::test]
+async fn test_modify_request() {
+    /*
+    GIVEN an OSM client with a middleware adding a header
+    WHEN calling versions()
+    THEN the request sent to the server has the header
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/versions"))
+        .and(header("user-agent", "my-editor/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(VERSIONS_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None)
+        .with_middleware(UserAgent("my-editor/1.0"));
+
+    // WHEN
+    let actual = client.versions().await.unwrap();
+
+    // THEN
+    assert_eq!(actual, vec!["0.6".to_string()]);
+}
+
+#[actix_rt::test]
+async fn test_observe_endpoint_and_options() {
+    /*
+    GIVEN an OSM client with a recording middleware
+    WHEN calling an endpoint with and one without authentication
+    THEN the middleware sees the endpoint, the options and the response of each request
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/versions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(VERSIONS_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let recorder = Recorder::default();
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let client =
+        Openstreetmap::new(mock_server.uri(), credentials).with_middleware(recorder.clone());
+
+    // WHEN
+    client.versions().await.unwrap();
+    let actual = client.user().details().await;
+
+    // THEN
+    assert!(matches!(actual, Err(OpenstreetmapError::NotFound)));
+    assert_eq!(
+        *recorder.calls.lock().unwrap(),
+        vec![
+            ("versions".to_string(), false, StatusCode::OK),
+            ("user/details".to_string(), true, StatusCode::NOT_FOUND),
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn test_short_circuit() {
+    /*
+    GIVEN an OSM client with a middleware answering every request
+        AND a recording middleware added after it
+    WHEN calling versions()
+    THEN returns the response of the middleware
+        AND the rest of the chain is not called
+    */
+    // GIVEN
+    let recorder = Recorder::default();
+    let client = Openstreetmap::new("http://127.0.0.1:9", types::Credentials::None)
+        .with_middleware(Canned(VERSIONS_RESPONSE))
+        .with_middleware(recorder.clone());
+
+    // WHEN
+    let actual = client.versions().await.unwrap();
+
+    // THEN
+    assert_eq!(actual, vec!["0.6".to_string()]);
+    assert!(recorder.calls.lock().unwrap().is_empty());
+}
@@ -0,0 +1,303 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use openstreetmap_api::types::*;
+use openstreetmap_api::xml::{read_changes, read_changes_file, write_changes, write_changes_file};
+use pretty_assertions::assert_eq;
+use rstest::*;
+use std::io::Write;
+
+const OSC_XML: &str = r#"
+<osmChange version="0.6" generator="acme osm editor">
+    <create>
+        <node id="1" changeset="42" version="0" lat="12.5" lon="-8.25" timestamp="2009-12-09T08:19:00Z" visible="true">
+            <tag k="amenity" v="school"/>
+        </node>
+    </create>
+    <modify>
+        <way id="49780" changeset="42" version="2" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1" visible="true">
+            <nd ref="1"/>
+            <nd ref="1150316"/>
+        </way>
+    </modify>
+    <delete>
+        <node id="1150317" changeset="42" version="3" timestamp="2009-12-09T08:19:00Z" visible="true"/>
+    </delete>
+</osmChange>
+"#;
+
+fn node(id: i64, version: u64, tags: Vec<Tag>) -> Node {
+    Node {
+        id,
+        visible: true,
+        version,
+        changeset: 42,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: None,
+        uid: None,
+        lat: None,
+        lon: None,
+        tags,
+    }
+}
+
+#[fixture]
+fn changes() -> ChangesetChanges {
+    ChangesetChanges {
+        creations: vec![Creation {
+            nodes: vec![Node {
+                lat: Some(12.5),
+                lon: Some(-8.25),
+                ..node(1, 0, vec![Tag::new("amenity", "school")])
+            }],
+            ways: vec![],
+            relations: vec![],
+        }],
+        modifications: vec![Modification {
+            nodes: vec![],
+            ways: vec![Way {
+                id: 49780,
+                visible: true,
+                version: 2,
+                changeset: 42,
+                timestamp: "2009-12-09T08:51:50Z".into(),
+                user: "guggis".into(),
+                uid: 1,
+                node_refs: vec![NodeRef { node_id: 1 }, NodeRef { node_id: 1150316 }],
+                tags: vec![],
+            }],
+            relations: vec![],
+        }],
+        deletions: vec![Deletion {
+            if_unused: false,
+            nodes: vec![node(1150317, 3, vec![])],
+            ways: vec![],
+            relations: vec![],
+        }],
+    }
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+}
+
+#[rstest]
+fn test_read_changes(changes: ChangesetChanges) {
+    /*
+    GIVEN an osmChange document
+    WHEN reading it
+    THEN the created, modified and deleted elements are returned
+    */
+    // WHEN
+    let actual = read_changes(OSC_XML.as_bytes()).unwrap();
+
+    // THEN
+    assert_eq!(actual, changes);
+}
+
+#[test]
+fn test_read_changes_placeholders() {
+    /*
+    GIVEN an osmChange document with negative placeholder ids
+        AND interleaved create and modify blocks
+        AND ways and relations without user and uid
+    WHEN reading it
+    THEN every run of elements of the same action is a block
+        AND the placeholder ids and references are kept
+    */
+    // GIVEN
+    let xml = r#"
+        <osmChange version="0.6" generator="editor">
+            <create>
+                <node id="-1" changeset="42" version="0" lat="1" lon="2" timestamp="2009-12-09T08:19:00Z" visible="true"/>
+            </create>
+            <modify>
+                <way id="10" changeset="42" version="3" timestamp="2009-12-09T08:19:00Z" visible="true">
+                    <nd ref="-1"/>
+                    <nd ref="5"/>
+                </way>
+            </modify>
+            <create>
+                <relation id="-2" changeset="42" version="0" timestamp="2009-12-09T08:19:00Z" visible="true">
+                    <member type="node" ref="-1" role="stop"/>
+                    <tag k="type" v="route"/>
+                </relation>
+            </create>
+        </osmChange>
+    "#;
+
+    // WHEN
+    let actual = read_changes(xml.as_bytes()).unwrap();
+
+    // THEN
+    let expected = ChangesetChanges {
+        creations: vec![
+            Creation {
+                nodes: vec![Node {
+                    lat: Some(1.0),
+                    lon: Some(2.0),
+                    ..node(-1, 0, vec![])
+                }],
+                ways: vec![],
+                relations: vec![],
+            },
+            Creation {
+                nodes: vec![],
+                ways: vec![],
+                relations: vec![Relation {
+                    id: -2,
+                    visible: true,
+                    version: 0,
+                    changeset: 42,
+                    timestamp: "2009-12-09T08:19:00Z".into(),
+                    user: String::new(),
+                    uid: 0,
+                    tags: vec![Tag::new("type", "route")],
+                    members: vec![Member {
+                        member_type: "node".into(),
+                        node_id: -1,
+                        role: "stop".into(),
+                    }],
+                }],
+            },
+        ],
+        modifications: vec![Modification {
+            nodes: vec![],
+            ways: vec![Way {
+                id: 10,
+                visible: true,
+                version: 3,
+                changeset: 42,
+                timestamp: "2009-12-09T08:19:00Z".into(),
+                user: String::new(),
+                uid: 0,
+                node_refs: vec![NodeRef { node_id: -1 }, NodeRef { node_id: 5 }],
+                tags: vec![],
+            }],
+            relations: vec![],
+        }],
+        deletions: vec![],
+    };
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn test_read_changes_gzip(changes: ChangesetChanges) {
+    /*
+    GIVEN a gzip-compressed osmChange document
+    WHEN reading it
+    THEN it is decompressed transparently
+    */
+    // GIVEN
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(OSC_XML.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    // WHEN
+    let actual = read_changes(compressed.as_slice()).unwrap();
+
+    // THEN
+    assert_eq!(actual, changes);
+}
+
+#[rstest]
+fn test_write_changes(changes: ChangesetChanges) {
+    /*
+    GIVEN a set of changes
+    WHEN writing them
+    THEN an osmChange document is returned
+    */
+    // WHEN
+    let actual = String::from_utf8(write_changes(vec![], &changes, "test").unwrap()).unwrap();
+
+    // THEN
+    let expected = [
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        r#"<osmChange version="0.6" generator="test">"#,
+        r#"<create><node id="1" visible="true" version="0" changeset="42" timestamp="2009-12-09T08:19:00Z" lat="12.5" lon="-8.25"><tag k="amenity" v="school"/></node></create>"#,
+        r#"<modify><way id="49780" visible="true" version="2" changeset="42" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1"><nd ref="1"/><nd ref="1150316"/></way></modify>"#,
+        r#"<delete><node id="1150317" visible="true" version="3" changeset="42" timestamp="2009-12-09T08:19:00Z"/></delete>"#,
+        r#"</osmChange>"#,
+        "",
+    ]
+    .join("\n");
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_deletions_round_trip() {
+    /*
+    GIVEN an osmChange document deleting a way and its nodes with if-unused
+        AND another block deleting a node before a way
+    WHEN reading it and writing it back
+    THEN the relations, ways and nodes of a block are written in that order
+        AND the elements keep their order across the written blocks
+        AND the if-unused attribute is kept
+    */
+    // GIVEN
+    let timestamp = r#"timestamp="2009-12-09T08:19:00Z""#;
+    let xml = [
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<osmChange version="0.6" generator="test">"#.into(),
+        format!(
+            r#"<delete if-unused="true"><way id="10" visible="false" version="2" changeset="42" {timestamp}><nd ref="1"/><nd ref="2"/></way><node id="1" visible="false" version="2" changeset="42" {timestamp}/><node id="2" visible="false" version="2" changeset="42" {timestamp}/></delete>"#
+        ),
+        format!(
+            r#"<delete><node id="3" visible="false" version="2" changeset="42" {timestamp}/></delete>"#
+        ),
+        format!(
+            r#"<delete><way id="11" visible="false" version="2" changeset="42" {timestamp}><nd ref="4"/><nd ref="5"/></way></delete>"#
+        ),
+        r#"</osmChange>"#.into(),
+        "".into(),
+    ]
+    .join("\n");
+
+    // WHEN
+    let changes = read_changes(xml.as_bytes()).unwrap();
+    let actual = String::from_utf8(write_changes(vec![], &changes, "test").unwrap()).unwrap();
+
+    // THEN
+    let deletions = changes
+        .deletions
+        .iter()
+        .map(|d| {
+            let ids = d.relations.iter().map(|r| r.id);
+            let ids = ids.chain(d.ways.iter().map(|w| w.id));
+
+            (
+                d.if_unused,
+                ids.chain(d.nodes.iter().map(|n| n.id)).collect(),
+            )
+        })
+        .collect::<Vec<(bool, Vec<i64>)>>();
+
+    assert_eq!(
+        deletions,
+        vec![(true, vec![10, 1, 2]), (false, vec![3]), (false, vec![11])]
+    );
+    assert_eq!(actual, xml);
+}
+
+#[rstest(file_name, case("changes.osc"), case("changes.osc.gz"))]
+fn test_changes_file_round_trip(changes: ChangesetChanges, file_name: &str) {
+    /*
+    GIVEN a set of changes
+    WHEN writing them to a file and reading the file back
+    THEN the same changes are returned
+    */
+    // GIVEN
+    let path = temp_path(file_name);
+
+    // WHEN
+    write_changes_file(&path, &changes, "test").unwrap();
+    let actual = read_changes_file(&path).unwrap();
+    let is_gzip = std::fs::read(&path).unwrap().starts_with(&[0x1f, 0x8b]);
+
+    std::fs::remove_file(&path).unwrap();
+
+    // THEN
+    assert_eq!(actual, changes);
+    assert_eq!(is_gzip, file_name.ends_with(".gz"));
+}
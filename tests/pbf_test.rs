@@ -0,0 +1,72 @@
+#![cfg(feature = "pbf")]
+
+use openstreetmap_api::pbf::PbfReader;
+use openstreetmap_api::types::*;
+use pretty_assertions::assert_eq;
+
+fn node(id: i64, timestamp: &str, lat: f64, lon: f64) -> Element {
+    Element::Node(Node {
+        id,
+        visible: true,
+        version: 1,
+        changeset: 0,
+        timestamp: timestamp.into(),
+        user: Some("testuser".into()),
+        uid: Some(17),
+        lat: Some(lat),
+        lon: Some(lon),
+        tags: vec![],
+    })
+}
+
+#[test]
+fn test_pbf_reader() {
+    /*
+    GIVEN an OSM PBF file with dense nodes
+    WHEN streaming its elements
+    THEN the nodes, ways and relations are converted into the crate types
+    */
+    // GIVEN
+    let reader = PbfReader::from_path("tests/fixtures/test.osm.pbf").unwrap();
+
+    // WHEN
+    let actual = reader.collect::<Result<Vec<Element>, _>>().unwrap();
+
+    // THEN
+    let expected = vec![
+        node(105, "2003-04-05T06:07:08Z", 52.1224031, 11.6284017),
+        node(106, "2003-04-05T06:07:09Z", 52.1199235, 11.6256446),
+        node(108, "2003-04-05T06:07:10Z", 52.1198991, 11.6310192),
+        Element::Way(Way {
+            id: 107,
+            visible: true,
+            version: 1,
+            changeset: 0,
+            timestamp: "2003-04-05T06:07:11Z".into(),
+            user: "testuser".into(),
+            uid: 17,
+            node_refs: [105, 106, 108, 105]
+                .into_iter()
+                .map(|node_id| NodeRef { node_id })
+                .collect(),
+            tags: vec![Tag::new("building", "yes"), Tag::new("name", "triangle")],
+        }),
+        Element::Relation(Relation {
+            id: 120,
+            visible: true,
+            version: 1,
+            changeset: 0,
+            timestamp: "2003-04-05T06:07:12Z".into(),
+            user: "testuser".into(),
+            uid: 17,
+            members: vec![Member {
+                member_type: "way".into(),
+                node_id: 107,
+                role: "test_role".into(),
+            }],
+            tags: vec![Tag::new("rel_key", "rel_value")],
+        }),
+    ];
+
+    assert_eq!(actual, expected);
+}
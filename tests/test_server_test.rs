@@ -0,0 +1,508 @@
+#![cfg(feature = "test-server")]
+
+use futures::StreamExt;
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::test_server::TestServer;
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+
+fn client(server: &TestServer) -> Openstreetmap {
+    server.add_user("mapper", "secret");
+
+    let credentials = types::Credentials::Basic("mapper".into(), "secret".into());
+
+    Openstreetmap::new(server.uri(), credentials)
+}
+
+fn node(changeset: u64, lat: f64, lon: f64) -> types::Node {
+    types::Node {
+        id: 0,
+        visible: true,
+        version: 0,
+        changeset,
+        timestamp: String::new(),
+        user: None,
+        uid: None,
+        lat: Some(lat),
+        lon: Some(lon),
+        tags: vec![types::Tag::new("amenity", "bench")],
+    }
+}
+
+async fn open_changeset(client: &Openstreetmap) -> u64 {
+    let changeset =
+        types::ChangesetCreate::new("0.6", "test", vec![types::Tag::new("comment", "test")]);
+
+    client.changeset().create(vec![changeset]).await.unwrap()
+}
+
+#[actix_rt::test]
+async fn test_element_crud() {
+    /*
+    GIVEN a test server with a user
+    WHEN creating, updating and deleting a node in a changeset
+    THEN every edit creates a new version of the node
+        AND the deleted node is gone
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+
+    // WHEN
+    let node_id = client
+        .nodes()
+        .create(node(changeset_id, 51.5, -0.1))
+        .await
+        .unwrap();
+    let mut created = client.nodes().get(node_id).await.unwrap();
+
+    created.tags.push(types::Tag::new("backrest", "yes"));
+
+    let updated_version = client.nodes().update(created).await.unwrap();
+    let updated = client.nodes().get(node_id).await.unwrap();
+    let deleted_version = client.nodes().delete(updated).await.unwrap();
+
+    // THEN
+    let history = client.nodes().history(node_id).await.unwrap();
+    let versions = history
+        .iter()
+        .map(|node| (node.version, node.visible, node.tags.len()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(updated_version, 2);
+    assert_eq!(deleted_version, 3);
+    assert_eq!(versions, vec![(1, true, 1), (2, true, 2), (3, false, 0)]);
+    assert!(matches!(
+        client.nodes().get(node_id).await,
+        Err(OpenstreetmapError::Client { code, .. }) if code == http::StatusCode::GONE
+    ));
+}
+
+#[actix_rt::test]
+async fn test_version_conflict() {
+    /*
+    GIVEN a test server with a node updated since it was fetched
+    WHEN updating the node fetched before
+    THEN the server answers 409 Conflict
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+    let node_id = client
+        .nodes()
+        .create(node(changeset_id, 51.5, -0.1))
+        .await
+        .unwrap();
+    let stale = client.nodes().get(node_id).await.unwrap();
+    let current = client.nodes().get(node_id).await.unwrap();
+
+    client.nodes().update(current).await.unwrap();
+
+    // WHEN
+    let result = client.nodes().update(stale).await;
+
+    // THEN
+    match result {
+        Err(OpenstreetmapError::Client { code, error }) => {
+            assert_eq!(code, http::StatusCode::CONFLICT);
+            assert_eq!(
+                error,
+                format!("Version mismatch: Provided 1, server had: 2 of Node {node_id}")
+            );
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn test_upload_placeholders() {
+    /*
+    GIVEN a test server with an open changeset
+    WHEN uploading two nodes and a way referencing them by placeholder ids
+    THEN the diff result maps the placeholders to the new ids
+        AND the way references the new nodes
+        AND the changeset downloads the created elements
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+    let mut first = node(changeset_id, 51.5, -0.1);
+    let mut second = node(changeset_id, 51.6, -0.2);
+
+    first.id = -1;
+    second.id = -2;
+
+    let way = types::Way {
+        id: -1,
+        visible: true,
+        version: 0,
+        changeset: changeset_id,
+        timestamp: String::new(),
+        user: String::new(),
+        uid: 0,
+        node_refs: vec![
+            types::NodeRef { node_id: -1 },
+            types::NodeRef { node_id: -2 },
+        ],
+        tags: vec![types::Tag::new("highway", "footway")],
+    };
+    let changes = types::ChangesetChanges {
+        modifications: vec![],
+        creations: vec![types::Creation {
+            nodes: vec![first, second],
+            ways: vec![way],
+            relations: vec![],
+        }],
+        deletions: vec![],
+    };
+
+    // WHEN
+    let diff = client
+        .changeset()
+        .upload(changeset_id, changes)
+        .await
+        .unwrap();
+
+    // THEN
+    let way_id = diff.ways[0].new_id.unwrap();
+    let way = client.ways().get(way_id).await.unwrap();
+    let node_ids = diff
+        .nodes
+        .iter()
+        .map(|node| types::NodeRef {
+            node_id: node.new_id.unwrap(),
+        })
+        .collect::<Vec<_>>();
+    let downloaded = client.changeset().download(changeset_id).await.unwrap();
+
+    assert_eq!(way.node_refs, node_ids);
+    assert_eq!(downloaded.creations[0].nodes.len(), 2);
+    assert_eq!(downloaded.creations[0].ways.len(), 1);
+}
+
+async fn post_upload(server: &TestServer, changeset_id: u64, body: String) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!(
+            "{}/api/0.6/changeset/{changeset_id}/upload",
+            server.uri()
+        ))
+        .basic_auth("mapper", Some("secret"))
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn test_upload_negative_placeholders() {
+    /*
+    GIVEN a test server with an open changeset
+    WHEN posting an osmChange whose way references a missing node
+        AND posting it again with the missing node
+    THEN the first upload is rejected with 412 Precondition Failed without creating anything
+        AND the diff result of the second one maps the negative ids to the new ids
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+    let upload = |extra_node: &str| {
+        format!(
+            r#"<osmChange version="0.6">
+                <create>
+                    <node id="-1" changeset="{changeset_id}" lat="51.5" lon="-0.1"/>
+                    {extra_node}
+                    <way id="-2" changeset="{changeset_id}"><nd ref="-1"/><nd ref="-3"/></way>
+                </create>
+            </osmChange>"#
+        )
+    };
+
+    // WHEN
+    let rejected = post_upload(&server, changeset_id, upload("")).await;
+    let rejected_status = rejected.status();
+    let missing = client.nodes().get(1).await;
+    let accepted = post_upload(
+        &server,
+        changeset_id,
+        upload(&format!(
+            r#"<node id="-3" changeset="{changeset_id}" lat="51.6" lon="-0.1"/>"#
+        )),
+    )
+    .await;
+
+    // THEN
+    let diff_result = accepted.text().await.unwrap();
+    let diffs = diff_result
+        .lines()
+        .filter(|line| line.contains("old_id"))
+        .collect::<Vec<_>>();
+
+    assert_eq!(rejected_status, http::StatusCode::PRECONDITION_FAILED);
+    assert!(matches!(missing, Err(OpenstreetmapError::NotFound)));
+    assert_eq!(
+        diffs,
+        vec![
+            r#"<node old_id="-1" new_id="1" new_version="1"/>"#,
+            r#"<node old_id="-3" new_id="2" new_version="1"/>"#,
+            r#"<way old_id="-2" new_id="1" new_version="1"/>"#,
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn test_closed_changeset() {
+    /*
+    GIVEN a test server with a closed changeset
+    WHEN creating a node in the changeset
+    THEN the server answers 409 Conflict
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+
+    client.changeset().close(changeset_id).await.unwrap();
+
+    // WHEN
+    let result = client.nodes().create(node(changeset_id, 51.5, -0.1)).await;
+
+    // THEN
+    let changeset = client.changeset().get(changeset_id).await.unwrap();
+
+    assert!(!changeset.open);
+    assert!(matches!(
+        result,
+        Err(OpenstreetmapError::Client { code, .. }) if code == http::StatusCode::CONFLICT
+    ));
+}
+
+#[actix_rt::test]
+async fn test_map() {
+    /*
+    GIVEN a test server with a way crossing the edge of a bounding box
+    WHEN fetching the map of the bounding box
+    THEN the way is returned with all its nodes
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let changeset_id = open_changeset(&client).await;
+    let inside = client
+        .nodes()
+        .create(node(changeset_id, 51.5, -0.1))
+        .await
+        .unwrap();
+    let outside = client
+        .nodes()
+        .create(node(changeset_id, 52.5, -0.1))
+        .await
+        .unwrap();
+    let way = types::Way {
+        id: 0,
+        visible: true,
+        version: 0,
+        changeset: changeset_id,
+        timestamp: String::new(),
+        user: String::new(),
+        uid: 0,
+        node_refs: vec![
+            types::NodeRef { node_id: inside },
+            types::NodeRef { node_id: outside },
+        ],
+        tags: vec![],
+    };
+    let way_id = client.ways().create(way).await.unwrap();
+    let bbox = types::BoundingBox {
+        left: -0.2,
+        bottom: 51.4,
+        right: 0.0,
+        top: 51.6,
+    };
+
+    // WHEN
+    let map = client.map(&bbox).await.unwrap();
+
+    // THEN
+    let node_ids = map.nodes.iter().map(|node| node.id).collect::<Vec<_>>();
+    let way_ids = map.ways.iter().map(|way| way.id).collect::<Vec<_>>();
+
+    assert_eq!(map.bounds, bbox);
+    assert_eq!(node_ids, vec![inside, outside]);
+    assert_eq!(way_ids, vec![way_id]);
+}
+
+#[actix_rt::test]
+async fn test_notes() {
+    /*
+    GIVEN a test server with a user
+    WHEN creating, commenting and closing a note
+    THEN the note has the three comments
+        AND it is found by text while closed notes are searched
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let content = types::NoteContent {
+        lat: 51.5,
+        lon: -0.1,
+        text: "Missing bench".into(),
+    };
+
+    // WHEN
+    let note = client.notes().create(content).await.unwrap();
+
+    client
+        .notes()
+        .create_comment(note.id, "Still missing")
+        .await
+        .unwrap();
+
+    let closed = client.notes().close(note.id, "Added").await.unwrap();
+
+    // THEN
+    let actions = closed
+        .comments
+        .iter()
+        .map(|comment| comment.action)
+        .collect::<Vec<_>>();
+    let open_only = client
+        .notes()
+        .search(&types::NoteSearchOptions {
+            q: "bench".into(),
+            closed: Some(types::NoteClosedOption::OpenOnly),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let all = client
+        .notes()
+        .search(&types::NoteSearchOptions {
+            q: "bench".into(),
+            closed: Some(types::NoteClosedOption::All),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(closed.status, types::NoteStatus::Closed);
+    assert_eq!(
+        actions,
+        vec![
+            types::CommentAction::Opened,
+            types::CommentAction::Commented,
+            types::CommentAction::Closed
+        ]
+    );
+    assert_eq!(open_only, vec![]);
+    assert_eq!(all, vec![closed]);
+}
+
+#[actix_rt::test]
+async fn test_changesets_stream() {
+    /*
+    GIVEN a test server with changesets opened a second apart
+    WHEN streaming the changesets with a page smaller than their number
+    THEN every changeset is returned once, newest first
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let mut changeset_ids = vec![];
+
+    for _ in 0..5 {
+        changeset_ids.push(open_changeset(&client).await);
+        server.advance_clock(1);
+    }
+
+    let query = types::ChangesetQueryParams {
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .changesets_stream(query)
+        .map(|changeset| changeset.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    changeset_ids.reverse();
+
+    assert_eq!(actual, changeset_ids);
+}
+
+#[actix_rt::test]
+async fn test_notes_search_stream() {
+    /*
+    GIVEN a test server with notes opened a second apart by a user
+        AND a note of another user
+    WHEN streaming the notes of the user with a page smaller than their number
+    THEN every note of the user is returned once, oldest first
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let other_credentials = types::Credentials::Basic("other".into(), "secret".into());
+    let other = Openstreetmap::new(server.uri(), other_credentials);
+    let content = || types::NoteContent {
+        lat: 51.5,
+        lon: -0.1,
+        text: "Missing bench".into(),
+    };
+    let mut note_ids = vec![];
+
+    for _ in 0..5 {
+        note_ids.push(client.notes().create(content()).await.unwrap().id);
+        server.advance_clock(1);
+    }
+
+    server.add_user("other", "secret");
+    other.notes().create(content()).await.unwrap();
+
+    let options = types::NoteSearchOptions {
+        limit: Some(2),
+        display_name: Some("mapper".into()),
+        ..Default::default()
+    };
+
+    // WHEN
+    let actual = client
+        .notes()
+        .search_stream(options)
+        .map(|note| note.unwrap().id)
+        .collect::<Vec<u64>>()
+        .await;
+
+    // THEN
+    assert_eq!(actual, note_ids);
+}
+
+#[actix_rt::test]
+async fn test_user_details() {
+    /*
+    GIVEN a test server with a user
+    WHEN fetching the user details with valid and with wrong credentials
+    THEN the details of the user are returned
+        AND the wrong credentials are unauthorized
+    */
+    // GIVEN
+    let server = TestServer::start();
+    let client = client(&server);
+    let wrong_credentials = types::Credentials::Basic("mapper".into(), "wrong".into());
+    let unauthorized = Openstreetmap::new(server.uri(), wrong_credentials);
+
+    // WHEN
+    let user = client.user().details().await.unwrap();
+    let result = unauthorized.user().details().await;
+
+    // THEN
+    assert_eq!(user.id, 1);
+    assert_eq!(user.display_name, "mapper");
+    assert!(matches!(result, Err(OpenstreetmapError::Unauthorized)));
+}
@@ -1 +1,4 @@
+// The API tests drive the default reqwest client
+#![cfg(feature = "reqwest")]
+
 mod api;
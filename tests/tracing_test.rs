@@ -0,0 +1,174 @@
+#![cfg(feature = "tracing")]
+
+use futures::StreamExt;
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Metadata, Subscriber};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const NODE_RESPONSE: &str = r#"
+<osm>
+    <node id="1234" changeset="42" version="2" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true"/>
+</osm>
+"#;
+
+#[actix_rt::test]
+async fn test_metrics() {
+    /*
+    GIVEN an OSM client
+    WHEN fetching two existing nodes and a missing one
+    THEN the metrics count the requests, the bytes and the errors of the operation
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/node/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+
+    // WHEN
+    client.nodes().get(1234).await.unwrap();
+    client.clone().nodes().get(1234).await.unwrap();
+    client.nodes().get(1).await.unwrap_err();
+
+    // THEN
+    let actual = client.metrics().operation("node/{id}").unwrap();
+
+    assert_eq!(actual.requests, 3);
+    assert_eq!(actual.bytes_received, 2 * NODE_RESPONSE.len() as u64);
+    assert_eq!(actual.errors, HashMap::from([("NotFound".to_string(), 1)]));
+    assert_eq!(actual.latency.count(), 3);
+    assert_eq!(client.metrics().snapshot().len(), 1);
+}
+
+#[actix_rt::test]
+async fn test_metrics_stream() {
+    /*
+    GIVEN an OSM client
+    WHEN reading the elements of map_stream()
+    THEN the bytes of the streamed response are counted
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    let elements = client
+        .map_stream(&bbox)
+        .await
+        .unwrap()
+        .1
+        .collect::<Vec<_>>()
+        .await;
+
+    // THEN
+    let actual = client.metrics().operation("map").unwrap();
+
+    assert_eq!(elements.len(), 1);
+    assert_eq!(actual.requests, 1);
+    assert_eq!(actual.bytes_received, NODE_RESPONSE.len() as u64);
+    assert!(actual.errors.is_empty());
+}
+
+// Subscriber keeping the values recorded into the `bytes` field of any span
+#[derive(Clone, Default)]
+struct BytesRecorder {
+    bytes: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Visit for BytesRecorder {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "bytes" {
+            self.bytes.lock().unwrap().push(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+impl Subscriber for BytesRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, values: &span::Record<'_>) {
+        values.record(&mut self.clone());
+    }
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[actix_rt::test]
+async fn test_span_bytes_stream() {
+    /*
+    GIVEN an OSM client
+        AND a subscriber recording the fields of the spans
+    WHEN reading the elements of map_stream()
+    THEN the bytes field of the span holds the size of the streamed response
+    */
+    // GIVEN
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/0.6/map"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(NODE_RESPONSE, "application/xml"))
+        .mount(&mock_server)
+        .await;
+
+    let recorder = BytesRecorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    let client = Openstreetmap::new(mock_server.uri(), types::Credentials::None);
+    let bbox = types::BoundingBox {
+        left: 1.0,
+        bottom: 2.0,
+        right: 3.0,
+        top: 4.0,
+    };
+
+    // WHEN
+    client
+        .map_stream(&bbox)
+        .await
+        .unwrap()
+        .1
+        .collect::<Vec<_>>()
+        .await;
+
+    // THEN
+    let actual = recorder.bytes.lock().unwrap().last().copied();
+
+    assert_eq!(actual, Some(NODE_RESPONSE.len() as u64));
+}
@@ -0,0 +1,145 @@
+use futures::future::BoxFuture;
+use http::header::{HeaderMap, AUTHORIZATION};
+use http::{Method, StatusCode};
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::transport::{HttpRequest, HttpResponse, Transport};
+use openstreetmap_api::types;
+use openstreetmap_api::Openstreetmap;
+use pretty_assertions::assert_eq;
+use std::sync::{Arc, Mutex};
+
+/// An in-process transport recording the requests and answering with a canned response
+#[derive(Debug, Clone)]
+struct FakeTransport {
+    status: StatusCode,
+    body: &'static str,
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+impl FakeTransport {
+    fn new(status: StatusCode, body: &'static str) -> Self {
+        Self {
+            status,
+            body,
+            requests: Arc::default(),
+        }
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> BoxFuture<'_, Result<HttpResponse, OpenstreetmapError>> {
+        self.requests.lock().unwrap().push(request);
+
+        let response = HttpResponse::new(self.status, HeaderMap::new(), self.body.into());
+
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[actix_rt::test]
+async fn test_get() {
+    /*
+    GIVEN an OSM client with a fake transport
+    WHEN calling nodes().get() with a node ID
+    THEN the request is sent through the transport
+        AND returns the node parsed from the fake response
+    */
+    // GIVEN
+    let transport = FakeTransport::new(
+        StatusCode::OK,
+        r#"
+        <osm>
+            <node id="1234" changeset="42" version="2" lat="12.1234567" lon="-8.7654321" timestamp="2009-12-09T08:19:00Z" uid="1" user="user" visible="true"/>
+        </osm>
+        "#,
+    );
+    let client = Openstreetmap::from_transport(
+        "https://osm.example",
+        types::Credentials::None,
+        transport.clone(),
+    );
+
+    // WHEN
+    let actual = client.nodes().get(1234).await.unwrap();
+
+    // THEN
+    assert_eq!(actual.id, 1234);
+    assert_eq!(actual.version, 2);
+
+    let requests = transport.requests.lock().unwrap();
+
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(
+        requests[0].url.as_str(),
+        "https://osm.example/api/0.6/node/1234"
+    );
+    assert_eq!(requests[0].body, None);
+}
+
+#[actix_rt::test]
+async fn test_update_with_auth() {
+    /*
+    GIVEN an OSM client with a fake transport and credentials
+    WHEN calling nodes().update() with a node
+    THEN the transport receives the XML body and the authorization header
+    */
+    // GIVEN
+    let transport = FakeTransport::new(StatusCode::OK, "3");
+    let credentials = types::Credentials::Basic("user".into(), "pass".into());
+    let client =
+        Openstreetmap::from_transport("https://osm.example", credentials, transport.clone());
+    let node = types::Node {
+        id: 1234,
+        changeset: 42,
+        version: 2,
+        uid: None,
+        timestamp: "2009-12-09T08:19:00Z".into(),
+        user: None,
+        visible: true,
+        lat: Some(12.1234567),
+        lon: Some(-8.7654321),
+        tags: vec![],
+    };
+
+    // WHEN
+    let actual = client.nodes().update(node).await.unwrap();
+
+    // THEN
+    assert_eq!(actual, 3);
+
+    let requests = transport.requests.lock().unwrap();
+    let body = String::from_utf8(requests[0].body.clone().unwrap()).unwrap();
+
+    assert_eq!(requests[0].method, Method::PUT);
+    assert_eq!(requests[0].headers[AUTHORIZATION], "Basic dXNlcjpwYXNz");
+    assert!(body.contains(r#"<node id="1234""#));
+}
+
+#[actix_rt::test]
+async fn test_client_error() {
+    /*
+    GIVEN an OSM client with a fake transport answering with a client error
+    WHEN calling notes().get()
+    THEN returns the error built from the status and body of the response
+    */
+    // GIVEN
+    let transport = FakeTransport::new(StatusCode::GONE, "The note has been hidden");
+    let client =
+        Openstreetmap::from_transport("https://osm.example", types::Credentials::None, transport);
+
+    // WHEN
+    let actual = client.notes().get(1).await;
+
+    // THEN
+    match actual {
+        Err(OpenstreetmapError::Client { code, error }) => {
+            assert_eq!(code, StatusCode::GONE);
+            assert_eq!(error, "The note has been hidden");
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+}
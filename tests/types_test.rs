@@ -132,6 +132,7 @@ fn test_changeset_create_serialize_xml() {
             modifications: vec![],
             creations: vec![],
             deletions:vec![Deletion {
+                if_unused: false,
                 nodes: vec![Node {
                     id: 1234,
                     changeset: 42,
@@ -206,7 +207,7 @@ fn test_element_id_param_display(element_id_param: ElementIdParam, expected: &st
         ElementIdParam { id: 1, version: Some(1) },
     )
 )]
-fn test_element_id_param_new(id: u64, version: Option<u64>, expected: ElementIdParam) {
+fn test_element_id_param_new(id: i64, version: Option<u64>, expected: ElementIdParam) {
     /*
     GIVEN an id and version
     WHEN calling new()
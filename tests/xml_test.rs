@@ -0,0 +1,272 @@
+use openstreetmap_api::errors::OpenstreetmapError;
+use openstreetmap_api::types::*;
+use openstreetmap_api::xml::{read_map, write_map, ElementReader};
+use pretty_assertions::assert_eq;
+use quick_xml::DeError;
+use rstest::*;
+
+const OSM_XML: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="CGImap 0.8.3 (27359 errol.openstreetmap.org)" copyright="OpenStreetMap and contributors">
+    <bounds minlat="2.0000000" minlon="1.0000000" maxlat="4.0000000" maxlon="3.0000000"/>
+    <node id="1150316" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1.0000000" lon="1.0000000"/>
+    <node id="2935283" visible="false" version="2" changeset="3180" timestamp="2010-02-19T16:29:45Z" user="EtienneChove" uid="34">
+        <tag k="name" v="Caf&#233; &amp; Bar"/>
+    </node>
+    <way id="49780" visible="true" version="1" changeset="2308" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1">
+        <nd ref="1150316"/>
+        <nd ref="2935283"/>
+        <tag k="highway" v="residential"/>
+    </way>
+    <relation id="4507" visible="true" version="1" changeset="3198" timestamp="2010-02-25T19:52:18Z" user="rus" uid="96">
+        <member type="way" ref="49780" role="outer"/>
+        <tag k="type" v="multipolygon"/>
+    </relation>
+</osm>
+"##;
+
+#[fixture]
+fn map() -> Map {
+    Map {
+        bounds: BoundingBox {
+            left: 1.0,
+            bottom: 2.0,
+            right: 3.0,
+            top: 4.0,
+        },
+        nodes: vec![
+            Node {
+                id: 1150316,
+                visible: true,
+                version: 1,
+                changeset: 2297,
+                timestamp: "2009-12-09T08:19:00Z".into(),
+                user: Some("guggis".into()),
+                uid: Some(1),
+                lat: Some(1.0),
+                lon: Some(1.0),
+                tags: vec![],
+            },
+            Node {
+                id: 2935283,
+                visible: false,
+                version: 2,
+                changeset: 3180,
+                timestamp: "2010-02-19T16:29:45Z".into(),
+                user: Some("EtienneChove".into()),
+                uid: Some(34),
+                lat: None,
+                lon: None,
+                tags: vec![Tag::new("name", "Café & Bar")],
+            },
+        ],
+        ways: vec![Way {
+            id: 49780,
+            visible: true,
+            version: 1,
+            changeset: 2308,
+            timestamp: "2009-12-09T08:51:50Z".into(),
+            user: "guggis".into(),
+            uid: 1,
+            node_refs: vec![NodeRef { node_id: 1150316 }, NodeRef { node_id: 2935283 }],
+            tags: vec![Tag::new("highway", "residential")],
+        }],
+        relations: vec![Relation {
+            id: 4507,
+            visible: true,
+            version: 1,
+            changeset: 3198,
+            timestamp: "2010-02-25T19:52:18Z".into(),
+            user: "rus".into(),
+            uid: 96,
+            members: vec![Member {
+                member_type: "way".into(),
+                node_id: 49780,
+                role: "outer".into(),
+            }],
+            tags: vec![Tag::new("type", "multipolygon")],
+        }],
+    }
+}
+
+#[rstest]
+fn test_read_map(map: Map) {
+    /*
+    GIVEN an OSM XML document
+    WHEN reading it
+    THEN a Map with all the elements is returned
+    */
+    // WHEN
+    let actual = read_map(OSM_XML.as_bytes()).unwrap();
+
+    // THEN
+    assert_eq!(actual, map);
+}
+
+#[rstest]
+fn test_element_reader(map: Map) {
+    /*
+    GIVEN an OSM XML document
+    WHEN streaming its elements
+    THEN the elements are returned in document order
+        AND the bounds are available
+    */
+    // GIVEN
+    let mut reader = ElementReader::new(OSM_XML.as_bytes());
+
+    // WHEN
+    let actual = reader
+        .by_ref()
+        .map(|e| match e.unwrap() {
+            Element::Node(n) => format!("node/{}", n.id),
+            Element::Way(w) => format!("way/{}", w.id),
+            Element::Relation(r) => format!("relation/{}", r.id),
+        })
+        .collect::<Vec<String>>();
+
+    // THEN
+    assert_eq!(
+        actual,
+        vec!["node/1150316", "node/2935283", "way/49780", "relation/4507"]
+    );
+    assert_eq!(reader.bounds(), Some(map.bounds));
+}
+
+#[rstest]
+fn test_write_map(map: Map) {
+    /*
+    GIVEN a Map
+    WHEN writing it
+    THEN an OSM XML document is returned
+    */
+    // WHEN
+    let actual = String::from_utf8(write_map(vec![], &map, "test & co").unwrap()).unwrap();
+
+    // THEN
+    let expected = [
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        r#"<osm version="0.6" generator="test &amp; co">"#,
+        r#"<bounds minlat="2" minlon="1" maxlat="4" maxlon="3"/>"#,
+        r#"<node id="1150316" visible="true" version="1" changeset="2297" timestamp="2009-12-09T08:19:00Z" user="guggis" uid="1" lat="1" lon="1"/>"#,
+        r#"<node id="2935283" visible="false" version="2" changeset="3180" timestamp="2010-02-19T16:29:45Z" user="EtienneChove" uid="34"><tag k="name" v="Café &amp; Bar"/></node>"#,
+        r#"<way id="49780" visible="true" version="1" changeset="2308" timestamp="2009-12-09T08:51:50Z" user="guggis" uid="1"><nd ref="1150316"/><nd ref="2935283"/><tag k="highway" v="residential"/></way>"#,
+        r#"<relation id="4507" visible="true" version="1" changeset="3198" timestamp="2010-02-25T19:52:18Z" user="rus" uid="96"><tag k="type" v="multipolygon"/><member type="way" ref="49780" role="outer"/></relation>"#,
+        r#"</osm>"#,
+        "",
+    ]
+    .join("\n");
+
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn test_round_trip(map: Map) {
+    /*
+    GIVEN a Map
+    WHEN writing it and reading it back
+    THEN the same Map is returned
+    */
+    // WHEN
+    let xml = write_map(vec![], &map, "test").unwrap();
+    let actual = read_map(xml.as_slice()).unwrap();
+
+    // THEN
+    assert_eq!(actual, map);
+}
+
+#[test]
+fn test_round_trip_anonymous_node() {
+    /*
+    GIVEN a Map with a new node without user, uid nor coordinates
+    WHEN writing it and reading it back
+    THEN the missing attributes are not written
+        AND the same Map is returned
+    */
+    // GIVEN
+    let map = Map {
+        bounds: BoundingBox {
+            left: 0.0,
+            bottom: 0.0,
+            right: 0.0,
+            top: 0.0,
+        },
+        nodes: vec![Node {
+            id: 1,
+            visible: false,
+            version: 0,
+            changeset: 42,
+            timestamp: "2009-12-09T08:19:00Z".into(),
+            user: None,
+            uid: None,
+            lat: None,
+            lon: None,
+            tags: vec![],
+        }],
+        ways: vec![],
+        relations: vec![],
+    };
+
+    // WHEN
+    let xml = write_map(vec![], &map, "test").unwrap();
+    let actual = read_map(xml.as_slice()).unwrap();
+
+    // THEN
+    let xml = String::from_utf8(xml).unwrap();
+
+    assert!(xml.contains(
+        r#"<node id="1" visible="false" version="0" changeset="42" timestamp="2009-12-09T08:19:00Z"/>"#
+    ));
+    assert_eq!(actual, map);
+}
+
+#[test]
+fn test_read_map_invalid_bounds() {
+    /*
+    GIVEN an OSM XML document with a bound which is not a number
+    WHEN reading it
+    THEN an error is returned
+    */
+    // GIVEN
+    let xml = r#"
+        <osm version="0.6">
+            <bounds minlat="south" minlon="1" maxlat="2" maxlon="3"/>
+        </osm>
+    "#;
+
+    // WHEN
+    let actual = read_map(xml.as_bytes());
+
+    // THEN
+    assert!(matches!(
+        actual,
+        Err(OpenstreetmapError::Serde(DeError::InvalidFloat(_)))
+    ));
+}
+
+#[test]
+fn test_read_map_without_bounds() {
+    /*
+    GIVEN an OSM XML document without bounds
+    WHEN reading it
+    THEN the bounding box is the extent of the nodes
+    */
+    // GIVEN
+    let xml = r#"
+        <osm version="0.6">
+            <node id="1" visible="true" version="1" changeset="1" timestamp="2009-12-09T08:19:00Z" lat="1.5" lon="-2"/>
+            <node id="2" visible="true" version="1" changeset="1" timestamp="2009-12-09T08:19:00Z" lat="3" lon="4"/>
+        </osm>
+    "#;
+
+    // WHEN
+    let actual = read_map(xml.as_bytes()).unwrap();
+
+    // THEN
+    let expected = BoundingBox {
+        left: -2.0,
+        bottom: 1.5,
+        right: 4.0,
+        top: 3.0,
+    };
+
+    assert_eq!(actual.bounds, expected);
+}